@@ -43,7 +43,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // 创建并派发订单
     println!("\n📦 开始派单...\n");
-    let mut orders = vec![
+    let mut orders = [
         Order::new(
             OrderType::Food,
             Location::new(39.9050, 116.4080),
@@ -89,7 +89,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         println!(
             "  {} - 负载: {}/{} ({:.0}%), 评分: {:.1}, 总单数: {}",
             driver.name,
-            driver.current_load,
+            driver.current_load(),
             driver.capacity,
             driver.load_ratio() * 100.0,
             driver.rating,
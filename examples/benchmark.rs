@@ -0,0 +1,47 @@
+use dispatcher::*;
+use std::env;
+
+/// cargo run --example benchmark -- [config/benchmark.toml]
+///     [--scenario recorded.json] [--save-scenario out.json] [--json out.json] [--csv out.csv]
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut config_path = "config/benchmark.toml".to_string();
+    let mut scenario_path = None;
+    let mut save_scenario = None;
+    let mut json_path = None;
+    let mut csv_path = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--scenario" => scenario_path = args.next(),
+            "--save-scenario" => save_scenario = args.next(),
+            "--json" => json_path = args.next(),
+            "--csv" => csv_path = args.next(),
+            _ => config_path = arg,
+        }
+    }
+
+    let config = BenchmarkConfig::from_file(&config_path)?;
+    let benchmark = config.build()?;
+
+    // 回放录制的订单流，或按随机种子生成
+    let scenario = match &scenario_path {
+        Some(path) => Scenario::from_json_file(path)?,
+        None => benchmark.scenario()?,
+    };
+    if let Some(path) = &save_scenario {
+        scenario.save_json(path)?;
+    }
+
+    let report = benchmark.run_scenario(&scenario)?;
+    println!("📊 {} 个配送员，{} 个订单\n", report.drivers, report.orders);
+    print!("{}", report.to_table());
+
+    if let Some(path) = &json_path {
+        std::fs::write(path, serde_json::to_string_pretty(&report)?)?;
+    }
+    if let Some(path) = &csv_path {
+        std::fs::write(path, report.to_csv())?;
+    }
+    Ok(())
+}
@@ -15,7 +15,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // 添加骑手
     println!("📍 初始化骑手团队...");
-    let mut riders = vec![
+    let mut riders = [
         Driver::new(
             "张骑手".to_string(),
             VehicleType::ElectricBike,
@@ -52,7 +52,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // 模拟午高峰订单
     println!("\n🍜 午高峰订单涌入...\n");
-    let restaurants = vec![
+    let restaurants = [
         ("麦当劳", Location::new(39.9050, 116.4080)),
         ("肯德基", Location::new(39.9030, 116.4060)),
         ("必胜客", Location::new(39.9070, 116.4110)),
@@ -109,7 +109,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         println!(
             "  {} - 当前接单: {} 单, 负载率: {:.0}%",
             driver.name,
-            driver.current_load,
+            driver.current_load(),
             driver.load_ratio() * 100.0
         );
     }
@@ -15,7 +15,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // 添加司机
     println!("📍 初始化司机团队...");
-    let mut drivers = vec![
+    let mut drivers = [
         Driver::new(
             "赵师傅".to_string(),
             VehicleType::Car,
@@ -49,7 +49,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // 模拟打车订单
     println!("\n🚖 乘客呼叫...\n");
 
-    let rides = vec![
+    let rides = [
         (
             "王先生 - 从国贸到首都机场",
             Location::new(39.9088, 116.3974),
@@ -98,7 +98,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     for driver in engine.get_all_drivers() {
         println!(
             "  {} - 正在服务: {} 单, 总计完成: {} 单, 评分: {:.1}",
-            driver.name, driver.current_load, driver.total_orders, driver.rating
+            driver.name,
+            driver.current_load(),
+            driver.total_orders,
+            driver.rating
         );
     }
 
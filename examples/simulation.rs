@@ -0,0 +1,52 @@
+use dispatcher::*;
+use std::sync::Arc;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("🚀 Dispatcher 仿真示例\n");
+
+    let zones = Arc::new(ZoneMap::from_geojson_file("config/zones.geojson")?);
+
+    // 午高峰两小时，中心片区订单最多
+    let mut lunch = [1.0; 24];
+    lunch[11] = 1.5;
+    lunch[12] = 1.5;
+    let config = SimulationConfig::new()
+        .with_demand(ZoneDemand::new("west", 15.0).with_hourly_factors(lunch))
+        .with_demand(ZoneDemand::new("central", 30.0).with_hourly_factors(lunch))
+        .with_demand(ZoneDemand::new("east", 15.0).with_hourly_factors(lunch))
+        .with_fleet(FleetSpec::new("west", VehicleType::ElectricBike, 6))
+        .with_fleet(FleetSpec::new("central", VehicleType::ElectricBike, 10))
+        .with_fleet(FleetSpec::new("central", VehicleType::Motorcycle, 4))
+        .with_fleet(FleetSpec::new("east", VehicleType::ElectricBike, 6));
+    let simulation = Simulation::new(config, zones.clone());
+
+    let strategies: Vec<Arc<dyn DispatchStrategy>> = vec![
+        Arc::new(NearestFirstStrategy::new()),
+        Arc::new(LoadBalancedStrategy::new()),
+        Arc::new(WeightedScoreStrategy::new(ScoreWeights::default())),
+    ];
+    for strategy in strategies {
+        let engine = DispatchEngine::new(strategy)
+            .with_rules(vec![
+                Box::new(CapacityRule),
+                Box::new(DistanceRule::new(5.0)),
+                Box::new(ZoneRule::new()),
+            ])
+            .with_zones(zones.clone())
+            .with_clock(simulation.clock());
+        let report = simulation.run(&engine)?;
+
+        println!("📊 {}", report.strategy);
+        println!(
+            "  订单: {} 生成 / {} 派出 / {} 送达",
+            report.orders_generated, report.orders_dispatched, report.orders_delivered
+        );
+        println!("  未派出率: {:.1}%", report.unassigned_rate * 100.0);
+        println!("  平均预计取货: {:.1} 分钟", report.avg_pickup_eta_minutes);
+        println!("  平均实际取货: {:.1} 分钟", report.avg_pickup_wait_minutes);
+        println!("  平均送达: {:.1} 分钟", report.avg_delivery_minutes);
+        println!("  配送员利用率: {:.1}%\n", report.utilization * 100.0);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use super::*;
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum RequestError {
+    #[error("Unknown order type: {0}")]
+    UnknownOrderType(String),
+
+    #[error("Unknown vehicle type: {0}")]
+    UnknownVehicleType(String),
+
+    #[error("Invalid shift: {0}")]
+    InvalidShift(#[from] ShiftError),
+}
+
+/// 派单请求，`POST /api/dispatch` 的请求体
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DispatchRequest {
+    pub order_type: String,
+    pub pickup_lat: f64,
+    pub pickup_lng: f64,
+    pub delivery_lat: f64,
+    pub delivery_lng: f64,
+    /// 要求在多少分钟内送达
+    #[serde(default)]
+    pub deliver_within_minutes: Option<u32>,
+}
+
+impl DispatchRequest {
+    pub fn to_order(&self) -> Result<Order, RequestError> {
+        self.to_order_with(&SystemClock, &RandomIdGenerator)
+    }
+
+    /// 使用指定的时钟和 ID 生成器创建订单，截止时间从 `clock` 的当前时间算起
+    pub fn to_order_with(
+        &self,
+        clock: &dyn Clock,
+        ids: &dyn IdGenerator,
+    ) -> Result<Order, RequestError> {
+        let pickup = Location::new(self.pickup_lat, self.pickup_lng);
+        let delivery = Location::new(self.delivery_lat, self.delivery_lng);
+        let order = Order::new_with(
+            parse_order_type(&self.order_type)?,
+            pickup,
+            delivery,
+            clock,
+            ids,
+        );
+        Ok(match self.deliver_within_minutes {
+            Some(minutes) => {
+                let deadline = order.created_at + minutes as i64 * 60;
+                order.with_deadline(deadline)
+            }
+            None => order,
+        })
+    }
+}
+
+/// 添加配送员请求，`POST /api/drivers` 的请求体
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AddDriverRequest {
+    pub name: String,
+    pub vehicle_type: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// 所属服务区，未指定时按位置判断
+    #[serde(default)]
+    pub zone_id: Option<String>,
+    /// 班次安排，未指定时不受班次限制
+    #[serde(default)]
+    pub shift: Option<Shift>,
+}
+
+impl AddDriverRequest {
+    pub fn to_driver(&self) -> Result<Driver, RequestError> {
+        self.to_driver_with(&SystemClock, &RandomIdGenerator)
+    }
+
+    /// 车型无法识别或班次不合法时返回错误
+    pub fn to_driver_with(
+        &self,
+        clock: &dyn Clock,
+        ids: &dyn IdGenerator,
+    ) -> Result<Driver, RequestError> {
+        if let Some(shift) = &self.shift {
+            shift.validate()?;
+        }
+        let location = Location::new(self.latitude, self.longitude);
+        let mut driver = Driver::new_with(
+            self.name.clone(),
+            parse_vehicle_type(&self.vehicle_type)?,
+            location,
+            clock,
+            ids,
+        );
+        driver.zone_id = self.zone_id.clone();
+        driver.shift = self.shift.clone();
+        Ok(driver)
+    }
+}
+
+/// 更新配送员位置请求，`PUT /api/drivers/location` 的请求体
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UpdateLocationRequest {
+    pub driver_id: String,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// 释放订单请求，对应 `POST /api/drivers/{driver_id}/release/{order_id}` 的路径参数
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReleaseRequest {
+    pub driver_id: String,
+    pub order_id: String,
+}
+
+/// 接受中文名称或枚举名
+pub fn parse_order_type(order_type: &str) -> Result<OrderType, RequestError> {
+    match order_type {
+        "外卖订单" | "Food" => Ok(OrderType::Food),
+        "快递订单" | "Express" => Ok(OrderType::Express),
+        "生鲜订单" | "FreshFood" => Ok(OrderType::FreshFood),
+        "打车订单" | "RideHailing" => Ok(OrderType::RideHailing),
+        "货运订单" | "Cargo" => Ok(OrderType::Cargo),
+        other => Err(RequestError::UnknownOrderType(other.to_string())),
+    }
+}
+
+/// 接受中文名称或枚举名
+pub fn parse_vehicle_type(vehicle_type: &str) -> Result<VehicleType, RequestError> {
+    match vehicle_type {
+        "电动车" | "ElectricBike" => Ok(VehicleType::ElectricBike),
+        "摩托车" | "Motorcycle" => Ok(VehicleType::Motorcycle),
+        "汽车" | "Car" => Ok(VehicleType::Car),
+        "面包车" | "Van" => Ok(VehicleType::Van),
+        "卡车" | "Truck" => Ok(VehicleType::Truck),
+        other => Err(RequestError::UnknownVehicleType(other.to_string())),
+    }
+}
@@ -0,0 +1,196 @@
+use super::*;
+use std::sync::Arc;
+
+/// 批量派单策略：一次性为一批订单分配配送员，追求全局最优
+pub trait BatchDispatchStrategy: Send + Sync {
+    /// 返回与 `orders` 一一对应的配送员 ID，无法分配的订单为 `None`
+    fn assign(
+        &self,
+        orders: &[&Order],
+        drivers: Vec<&Driver>,
+        rules: &[Box<dyn DispatchRule>],
+    ) -> Vec<Option<Uuid>>;
+
+    fn name(&self) -> &str;
+
+    /// 引擎设置批量策略或行程时间模型时传入自身的模型，返回改用该模型的策略副本；
+    /// 不依赖行程时间的策略返回 None
+    fn bind_travel_model(
+        &self,
+        _model: Arc<dyn TravelTimeModel>,
+    ) -> Option<Arc<dyn BatchDispatchStrategy>> {
+        None
+    }
+}
+
+/// 最小成本匹配策略：以取货距离为成本，使用匈牙利算法求解总接单距离最小的分配方案
+///
+/// 每个配送员按剩余运力拆分为多个"座位"，因此同一配送员可以在一批中承接多单，
+/// 但不会超过其容量。
+#[derive(Clone, Default)]
+pub struct MinCostBatchStrategy {
+    /// 设置后以道路距离为成本，加入引擎后使用引擎的行程时间模型
+    travel_model: Option<Arc<dyn TravelTimeModel>>,
+}
+
+impl MinCostBatchStrategy {
+    pub fn new() -> Self {
+        Self { travel_model: None }
+    }
+
+    pub fn with_travel_model(mut self, travel_model: Arc<dyn TravelTimeModel>) -> Self {
+        self.travel_model = Some(travel_model);
+        self
+    }
+}
+
+/// 不可行分配的成本，远大于任何真实距离，保证算法优先最大化成功分配的订单数
+const INFEASIBLE_COST: f64 = 1e9;
+
+impl BatchDispatchStrategy for MinCostBatchStrategy {
+    fn assign(
+        &self,
+        orders: &[&Order],
+        drivers: Vec<&Driver>,
+        rules: &[Box<dyn DispatchRule>],
+    ) -> Vec<Option<Uuid>> {
+        // 展开配送员座位
+        let slots: Vec<&Driver> = drivers
+            .into_iter()
+            .flat_map(|driver| {
+                let remaining = driver.capacity.saturating_sub(driver.current_load());
+                std::iter::repeat_n(driver, remaining.min(orders.len()))
+            })
+            .collect();
+
+        if orders.is_empty() || slots.is_empty() {
+            return vec![None; orders.len()];
+        }
+
+        let cost: Vec<Vec<f64>> = orders
+            .iter()
+            .map(|order| {
+                slots
+                    .iter()
+                    .map(|driver| {
+                        if rules.iter().all(|rule| rule.evaluate(order, driver)) {
+                            match &self.travel_model {
+                                Some(model) => model
+                                    .distance_km(driver.current_location, order.pickup_location),
+                                None => driver.distance_to(order.pickup_location),
+                            }
+                        } else {
+                            INFEASIBLE_COST
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        solve_assignment(&cost)
+            .into_iter()
+            .enumerate()
+            .map(|(row, col)| {
+                col.filter(|&col| cost[row][col] < INFEASIBLE_COST)
+                    .map(|col| slots[col].id)
+            })
+            .collect()
+    }
+
+    fn name(&self) -> &str {
+        "MinCostBatch"
+    }
+
+    fn bind_travel_model(
+        &self,
+        model: Arc<dyn TravelTimeModel>,
+    ) -> Option<Arc<dyn BatchDispatchStrategy>> {
+        Some(Arc::new(self.clone().with_travel_model(model)))
+    }
+}
+
+/// 匈牙利算法求解最小成本指派问题
+///
+/// 返回每一行分配到的列，行数多于列数时多出的行为 `None`。
+pub fn solve_assignment(cost: &[Vec<f64>]) -> Vec<Option<usize>> {
+    let rows = cost.len();
+    if rows == 0 {
+        return Vec::new();
+    }
+    let real_cols = cost[0].len();
+    // 行数多于列数时补充虚拟列
+    let cols = real_cols.max(rows);
+    let cell = |i: usize, j: usize| {
+        if j < real_cols {
+            cost[i][j]
+        } else {
+            INFEASIBLE_COST
+        }
+    };
+
+    // 势函数与匹配关系，下标从 1 开始，0 为哨兵
+    let mut u = vec![0.0; rows + 1];
+    let mut v = vec![0.0; cols + 1];
+    let mut matched_row = vec![0usize; cols + 1];
+    let mut way = vec![0usize; cols + 1];
+
+    for i in 1..=rows {
+        matched_row[0] = i;
+        let mut j0 = 0;
+        let mut min_v = vec![f64::INFINITY; cols + 1];
+        let mut used = vec![false; cols + 1];
+
+        loop {
+            used[j0] = true;
+            let i0 = matched_row[j0];
+            let mut delta = f64::INFINITY;
+            let mut j1 = 0;
+
+            for j in 1..=cols {
+                if used[j] {
+                    continue;
+                }
+                let reduced = cell(i0 - 1, j - 1) - u[i0] - v[j];
+                if reduced < min_v[j] {
+                    min_v[j] = reduced;
+                    way[j] = j0;
+                }
+                if min_v[j] < delta {
+                    delta = min_v[j];
+                    j1 = j;
+                }
+            }
+
+            for j in 0..=cols {
+                if used[j] {
+                    u[matched_row[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    min_v[j] -= delta;
+                }
+            }
+
+            j0 = j1;
+            if matched_row[j0] == 0 {
+                break;
+            }
+        }
+
+        loop {
+            let j1 = way[j0];
+            matched_row[j0] = matched_row[j1];
+            j0 = j1;
+            if j0 == 0 {
+                break;
+            }
+        }
+    }
+
+    let mut assignment = vec![None; rows];
+    for (j, &i) in matched_row.iter().enumerate().skip(1).take(real_cols) {
+        if i != 0 {
+            assignment[i - 1] = Some(j - 1);
+        }
+    }
+    assignment
+}
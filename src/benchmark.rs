@@ -0,0 +1,235 @@
+use super::*;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BenchmarkError {
+    #[error(transparent)]
+    Simulation(#[from] SimulationError),
+
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+
+    #[error(transparent)]
+    Zone(#[from] ZoneError),
+
+    #[error("Failed to read benchmark: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid TOML: {0}")]
+    Toml(#[from] toml::de::Error),
+
+    #[error("Invalid benchmark: {0}")]
+    Invalid(String),
+}
+
+/// 参与对比的一组引擎配置：策略、规则、行程时间模型等
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BenchmarkVariant {
+    pub name: String,
+    #[serde(flatten)]
+    pub config: DispatchConfig,
+}
+
+impl BenchmarkVariant {
+    pub fn new(name: impl Into<String>, config: DispatchConfig) -> Self {
+        Self {
+            name: name.into(),
+            config,
+        }
+    }
+}
+
+/// 对比配置文件：服务区、仿真参数和参与对比的引擎配置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BenchmarkConfig {
+    pub zones: PathBuf,
+    #[serde(default)]
+    pub simulation: SimulationConfig,
+    pub variants: Vec<BenchmarkVariant>,
+}
+
+impl BenchmarkConfig {
+    pub fn from_toml_str(content: &str) -> Result<Self, BenchmarkError> {
+        let config: Self = toml::from_str(content)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, BenchmarkError> {
+        Self::from_toml_str(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn validate(&self) -> Result<(), BenchmarkError> {
+        if self.variants.is_empty() {
+            return Err(BenchmarkError::Invalid("no variants".to_string()));
+        }
+        let mut names = std::collections::BTreeSet::new();
+        for variant in &self.variants {
+            if !names.insert(variant.name.as_str()) {
+                return Err(BenchmarkError::Invalid(format!(
+                    "duplicate variant {}",
+                    variant.name
+                )));
+            }
+            variant.config.validate()?;
+        }
+        Ok(())
+    }
+
+    pub fn build(&self) -> Result<Benchmark, BenchmarkError> {
+        let zones = Arc::new(ZoneMap::from_geojson_file(&self.zones)?);
+        let simulation = Simulation::new(self.simulation.clone(), zones);
+        Ok(self
+            .variants
+            .iter()
+            .cloned()
+            .fold(Benchmark::new(simulation), |benchmark, variant| {
+                benchmark.with_variant(variant)
+            }))
+    }
+}
+
+/// 单个配置在同一场景下的仿真结果
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BenchmarkRow {
+    pub variant: String,
+    pub report: SimulationReport,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct BenchmarkReport {
+    pub orders: usize,
+    pub drivers: usize,
+    pub rows: Vec<BenchmarkRow>,
+}
+
+/// CSV 和表格的列
+const COLUMNS: [&str; 11] = [
+    "variant",
+    "strategy",
+    "dispatched",
+    "delivered",
+    "failure_rate",
+    "pickup_eta_min",
+    "delivery_min",
+    "lateness_min",
+    "late_rate",
+    "distance_km",
+    "fairness",
+];
+
+impl BenchmarkReport {
+    pub fn row(&self, variant: &str) -> Option<&SimulationReport> {
+        self.rows
+            .iter()
+            .find(|row| row.variant == variant)
+            .map(|row| &row.report)
+    }
+
+    fn cells(row: &BenchmarkRow) -> [String; 11] {
+        let report = &row.report;
+        [
+            row.variant.clone(),
+            report.strategy.clone(),
+            report.orders_dispatched.to_string(),
+            report.orders_delivered.to_string(),
+            format!("{:.3}", report.unassigned_rate),
+            format!("{:.1}", report.avg_pickup_eta_minutes),
+            format!("{:.1}", report.avg_delivery_minutes),
+            format!("{:.1}", report.avg_lateness_minutes),
+            format!("{:.3}", report.late_rate),
+            format!("{:.1}", report.total_distance_km),
+            format!("{:.3}", report.fairness_index),
+        ]
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = COLUMNS.join(",");
+        csv.push('\n');
+        for row in &self.rows {
+            csv.push_str(&Self::cells(row).join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// 按列对齐的文本表格
+    pub fn to_table(&self) -> String {
+        let rows: Vec<[String; 11]> = self.rows.iter().map(Self::cells).collect();
+        let widths: Vec<usize> = (0..COLUMNS.len())
+            .map(|i| {
+                rows.iter()
+                    .map(|cells| cells[i].chars().count())
+                    .chain(std::iter::once(COLUMNS[i].len()))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let mut table = String::new();
+        let header = COLUMNS.map(str::to_string);
+        for cells in std::iter::once(&header).chain(&rows) {
+            let line: Vec<String> = cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect();
+            let _ = writeln!(table, "{}", line.join("  ").trim_end());
+        }
+        table
+    }
+}
+
+/// 策略对比：同一份车队和订单流依次回放到每个配置新建的引擎上，
+/// 引擎使用仿真时钟，相同的配置和场景总是得到相同的报告
+pub struct Benchmark {
+    simulation: Simulation,
+    variants: Vec<BenchmarkVariant>,
+}
+
+impl Benchmark {
+    pub fn new(simulation: Simulation) -> Self {
+        Self {
+            simulation,
+            variants: Vec::new(),
+        }
+    }
+
+    pub fn with_variant(mut self, variant: BenchmarkVariant) -> Self {
+        self.variants.push(variant);
+        self
+    }
+
+    /// 按仿真配置的随机种子生成的场景
+    pub fn scenario(&self) -> Result<Scenario, BenchmarkError> {
+        Ok(self.simulation.scenario()?)
+    }
+
+    pub fn run(&self) -> Result<BenchmarkReport, BenchmarkError> {
+        self.run_scenario(&self.scenario()?)
+    }
+
+    /// 回放给定的场景，例如从文件加载的历史订单
+    pub fn run_scenario(&self, scenario: &Scenario) -> Result<BenchmarkReport, BenchmarkError> {
+        let mut report = BenchmarkReport {
+            orders: scenario.orders.len(),
+            drivers: scenario.drivers.len(),
+            rows: Vec::with_capacity(self.variants.len()),
+        };
+        for variant in &self.variants {
+            let mut engine =
+                DispatchEngine::from_config_with_clock(&variant.config, self.simulation.clock())?;
+            // 未单独指定服务区的配置使用仿真的服务区，服务区规则才有区域可判断
+            if variant.config.zones.is_none() {
+                engine = engine.with_zones(self.simulation.zones());
+            }
+            report.rows.push(BenchmarkRow {
+                variant: variant.name.clone(),
+                report: self.simulation.replay(&engine, scenario)?,
+            });
+        }
+        Ok(report)
+    }
+}
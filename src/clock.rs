@@ -0,0 +1,91 @@
+use super::*;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+
+/// 时钟：引擎、规则和策略通过它读取当前时间（Unix 秒），便于仿真和回放控制时间
+pub trait Clock: Send + Sync {
+    fn now(&self) -> i64;
+}
+
+/// 系统时钟，返回真实的当前时间
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> i64 {
+        chrono::Utc::now().timestamp()
+    }
+}
+
+/// 固定时钟：只在调用 `set` 或 `advance` 时前进，相同输入得到相同输出
+#[derive(Debug, Default)]
+pub struct FixedClock {
+    now: AtomicI64,
+}
+
+impl FixedClock {
+    pub fn new(now: i64) -> Self {
+        Self {
+            now: AtomicI64::new(now),
+        }
+    }
+
+    pub fn set(&self, now: i64) {
+        self.now.store(now, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, secs: i64) {
+        self.now.fetch_add(secs, Ordering::SeqCst);
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> i64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
+
+/// ID 生成器：订单和配送员的 ID 由它分配
+pub trait IdGenerator: Send + Sync {
+    fn next_id(&self) -> Uuid;
+}
+
+/// 随机 v4 UUID
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomIdGenerator;
+
+impl IdGenerator for RandomIdGenerator {
+    fn next_id(&self) -> Uuid {
+        Uuid::new_v4()
+    }
+}
+
+/// 按种子生成的 v4 格式 UUID，相同种子总是得到相同的 ID 序列
+#[derive(Debug, Default)]
+pub struct SeededIdGenerator {
+    state: AtomicU64,
+}
+
+impl SeededIdGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: AtomicU64::new(seed),
+        }
+    }
+}
+
+impl IdGenerator for SeededIdGenerator {
+    fn next_id(&self) -> Uuid {
+        let mut bytes = [0u8; 16];
+        for chunk in bytes.chunks_mut(8) {
+            let state = self
+                .state
+                .fetch_add(0x9E37_79B9_7F4A_7C15, Ordering::SeqCst)
+                .wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            chunk.copy_from_slice(&(z ^ (z >> 31)).to_le_bytes());
+        }
+        uuid::Builder::from_random_bytes(bytes).into_uuid()
+    }
+}
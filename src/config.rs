@@ -0,0 +1,598 @@
+use super::*;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("Failed to read config: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid TOML config: {0}")]
+    Toml(#[from] toml::de::Error),
+
+    #[error("Invalid JSON config: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Unsupported config format: {0}")]
+    UnsupportedFormat(String),
+
+    #[error("Invalid config: {0}")]
+    Invalid(String),
+
+    #[error(transparent)]
+    Routing(#[from] RoutingError),
+
+    #[error(transparent)]
+    Zone(#[from] ZoneError),
+}
+
+/// 派单策略配置，`name` 字段选择策略，其余字段为策略参数
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "name", rename_all = "snake_case", deny_unknown_fields)]
+pub enum StrategyConfig {
+    #[default]
+    NearestFirst,
+    LoadBalanced,
+    RatingPriority,
+    CapacityMatch,
+    WeightedScore {
+        #[serde(default)]
+        weights: ScoreWeights,
+        #[serde(default = "default_score_max_distance_km")]
+        max_distance_km: f64,
+        #[serde(default = "default_score_max_idle_minutes")]
+        max_idle_minutes: f64,
+    },
+    /// 绕路时间按引擎的行程时间模型估算
+    Pooling {
+        #[serde(default = "default_max_detour_minutes")]
+        max_detour_minutes: f64,
+    },
+}
+
+fn default_score_max_distance_km() -> f64 {
+    WeightedScoreStrategy::default().max_distance_km
+}
+
+fn default_score_max_idle_minutes() -> f64 {
+    WeightedScoreStrategy::default().max_idle_minutes
+}
+
+fn default_max_detour_minutes() -> f64 {
+    PoolingStrategy::default().max_detour_minutes
+}
+
+impl StrategyConfig {
+    /// 按名称（如 `nearest_first`）创建使用默认参数的策略配置
+    pub fn from_name(name: &str) -> Option<Self> {
+        serde_json::from_value(serde_json::json!({ "name": name })).ok()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StrategyConfig::NearestFirst => "nearest_first",
+            StrategyConfig::LoadBalanced => "load_balanced",
+            StrategyConfig::RatingPriority => "rating_priority",
+            StrategyConfig::CapacityMatch => "capacity_match",
+            StrategyConfig::WeightedScore { .. } => "weighted_score",
+            StrategyConfig::Pooling { .. } => "pooling",
+        }
+    }
+
+    pub fn build(&self) -> Arc<dyn DispatchStrategy> {
+        self.build_with_clock(Arc::new(SystemClock))
+    }
+
+    /// 创建策略，依赖当前时间的策略使用 `clock`
+    pub fn build_with_clock(&self, clock: Arc<dyn Clock>) -> Arc<dyn DispatchStrategy> {
+        match self {
+            StrategyConfig::NearestFirst => Arc::new(NearestFirstStrategy::new()),
+            StrategyConfig::LoadBalanced => Arc::new(LoadBalancedStrategy::new()),
+            StrategyConfig::RatingPriority => Arc::new(RatingPriorityStrategy::new()),
+            StrategyConfig::CapacityMatch => Arc::new(CapacityMatchStrategy::new()),
+            StrategyConfig::WeightedScore {
+                weights,
+                max_distance_km,
+                max_idle_minutes,
+            } => Arc::new(
+                WeightedScoreStrategy::new(weights.clone())
+                    .with_max_distance_km(*max_distance_km)
+                    .with_max_idle_minutes(*max_idle_minutes)
+                    .with_clock(clock),
+            ),
+            StrategyConfig::Pooling { max_detour_minutes } => {
+                Arc::new(PoolingStrategy::new(*max_detour_minutes))
+            }
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        match self {
+            StrategyConfig::WeightedScore {
+                weights,
+                max_distance_km,
+                max_idle_minutes,
+            } => {
+                let all = [
+                    weights.distance,
+                    weights.load,
+                    weights.rating,
+                    weights.vehicle_fit,
+                    weights.idle_time,
+                ];
+                if all.iter().any(|w| !(*w >= 0.0 && w.is_finite())) {
+                    return invalid("weighted_score weights must be non-negative");
+                }
+                ensure_in_range(
+                    "weighted_score.max_distance_km",
+                    *max_distance_km,
+                    MAX_DISTANCE_KM,
+                )?;
+                ensure_in_range(
+                    "weighted_score.max_idle_minutes",
+                    *max_idle_minutes,
+                    MAX_MINUTES,
+                )
+            }
+            StrategyConfig::Pooling { max_detour_minutes }
+                if !(0.0..=MAX_MINUTES).contains(max_detour_minutes) =>
+            {
+                invalid(&format!(
+                    "pooling.max_detour_minutes must be between 0 and {}",
+                    MAX_MINUTES
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 派单规则配置，`name` 字段选择规则，其余字段为规则参数
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "name", rename_all = "snake_case", deny_unknown_fields)]
+pub enum RuleConfig {
+    Capacity,
+    Distance {
+        max_distance_km: f64,
+        /// 按行程时间模型的道路距离判断
+        #[serde(default)]
+        road_distance: bool,
+    },
+    VehicleType,
+    Rating {
+        min_rating: f64,
+    },
+    Deadline,
+    /// 服务区范围由 `DispatchConfig::zones` 指定
+    Zone,
+    Shift {
+        /// 连续工作时长上限（分钟）
+        #[serde(default = "default_max_continuous_work_minutes")]
+        max_continuous_work_minutes: u32,
+    },
+}
+
+impl RuleConfig {
+    pub fn build(&self) -> Box<dyn DispatchRule> {
+        match self {
+            RuleConfig::Capacity => Box::new(CapacityRule),
+            RuleConfig::Distance {
+                max_distance_km,
+                road_distance,
+            } => {
+                let rule = DistanceRule::new(*max_distance_km);
+                if *road_distance {
+                    Box::new(rule.with_road_distance())
+                } else {
+                    Box::new(rule)
+                }
+            }
+            RuleConfig::VehicleType => Box::new(VehicleTypeRule),
+            RuleConfig::Rating { min_rating } => Box::new(RatingRule::new(*min_rating)),
+            RuleConfig::Deadline => Box::new(DeadlineRule::new()),
+            RuleConfig::Zone => Box::new(ZoneRule::new()),
+            RuleConfig::Shift {
+                max_continuous_work_minutes,
+            } => Box::new(
+                ShiftRule::new().with_max_continuous_work_minutes(*max_continuous_work_minutes),
+            ),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        match self {
+            RuleConfig::Distance {
+                max_distance_km, ..
+            } => ensure_in_range(
+                "distance.max_distance_km",
+                *max_distance_km,
+                MAX_DISTANCE_KM,
+            ),
+            RuleConfig::Rating { min_rating } if !(0.0..=5.0).contains(min_rating) => {
+                invalid("rating.min_rating must be between 0 and 5")
+            }
+            RuleConfig::Shift {
+                max_continuous_work_minutes: 0,
+            } => invalid("shift.max_continuous_work_minutes must be positive"),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 行程时间模型配置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "name", rename_all = "snake_case", deny_unknown_fields)]
+pub enum TravelModelConfig {
+    ConstantSpeed {
+        speed_kmh: f64,
+    },
+    Road {
+        #[serde(default = "default_detour_factor")]
+        detour_factor: f64,
+        /// 不填写时使用默认的北京时间拥堵曲线
+        #[serde(default)]
+        congestion: Option<Box<CongestionCurve>>,
+        /// 按车型覆盖畅通时速度
+        #[serde(default)]
+        vehicle_speeds: HashMap<VehicleType, f64>,
+    },
+    /// 离线路网，`path` 为 `.json` 简化路网或 `.osm` 文件
+    Graph {
+        path: PathBuf,
+        #[serde(default)]
+        congestion: Option<Box<CongestionCurve>>,
+        #[serde(default)]
+        vehicle_speeds: HashMap<VehicleType, f64>,
+    },
+}
+
+fn default_max_continuous_work_minutes() -> u32 {
+    ShiftRule::new().max_continuous_work_minutes
+}
+
+fn default_detour_factor() -> f64 {
+    RoadTravelModel::default().detour_factor
+}
+
+impl Default for TravelModelConfig {
+    fn default() -> Self {
+        TravelModelConfig::ConstantSpeed {
+            speed_kmh: ConstantSpeedModel::default().speed_kmh,
+        }
+    }
+}
+
+impl TravelModelConfig {
+    /// 构建行程时间模型，路网模型会在此时读取路网文件
+    pub fn build(&self) -> Result<Arc<dyn TravelTimeModel>, ConfigError> {
+        Ok(match self {
+            TravelModelConfig::ConstantSpeed { speed_kmh } => {
+                Arc::new(ConstantSpeedModel::new(*speed_kmh))
+            }
+            TravelModelConfig::Road {
+                detour_factor,
+                congestion,
+                vehicle_speeds,
+            } => Arc::new(road_model(*detour_factor, congestion, vehicle_speeds)),
+            TravelModelConfig::Graph {
+                path,
+                congestion,
+                vehicle_speeds,
+            } => {
+                let graph = Arc::new(RoadGraph::from_file(path)?);
+                let fallback = road_model(default_detour_factor(), congestion, vehicle_speeds);
+                Arc::new(
+                    GraphTravelModel::new(graph)
+                        .with_congestion(fallback.congestion.clone())
+                        .with_fallback(fallback),
+                )
+            }
+        })
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        match self {
+            TravelModelConfig::ConstantSpeed { speed_kmh } => {
+                ensure_in_range("constant_speed.speed_kmh", *speed_kmh, MAX_SPEED_KMH)
+            }
+            TravelModelConfig::Road {
+                detour_factor,
+                congestion,
+                vehicle_speeds,
+            } => {
+                if !(1.0..=MAX_DETOUR_FACTOR).contains(detour_factor) {
+                    return invalid(&format!(
+                        "road.detour_factor must be between 1 and {}",
+                        MAX_DETOUR_FACTOR
+                    ));
+                }
+                validate_speeds("road", congestion, vehicle_speeds)
+            }
+            TravelModelConfig::Graph {
+                congestion,
+                vehicle_speeds,
+                ..
+            } => validate_speeds("graph", congestion, vehicle_speeds),
+        }
+    }
+}
+
+fn road_model(
+    detour_factor: f64,
+    congestion: &Option<Box<CongestionCurve>>,
+    vehicle_speeds: &HashMap<VehicleType, f64>,
+) -> RoadTravelModel {
+    let mut model = RoadTravelModel::new(detour_factor);
+    if let Some(congestion) = congestion {
+        model = model.with_congestion(congestion.as_ref().clone());
+    }
+    for (vehicle_type, speed_kmh) in vehicle_speeds {
+        model = model.with_vehicle_speed(vehicle_type.clone(), *speed_kmh);
+    }
+    model
+}
+
+fn validate_speeds(
+    model: &str,
+    congestion: &Option<Box<CongestionCurve>>,
+    vehicle_speeds: &HashMap<VehicleType, f64>,
+) -> Result<(), ConfigError> {
+    if let Some(congestion) = congestion
+        && congestion
+            .hourly_speed_factors
+            .iter()
+            .any(|f| *f <= 0.0 || !f.is_finite())
+    {
+        return invalid(&format!("{}.congestion factors must be positive", model));
+    }
+    for (vehicle_type, speed_kmh) in vehicle_speeds {
+        ensure_in_range(
+            &format!("{}.{:?} speed", model, vehicle_type),
+            *speed_kmh,
+            MAX_SPEED_KMH,
+        )?;
+    }
+    Ok(())
+}
+
+/// 计价模型配置，`surge` 未填写的参数使用 `SurgePricing` 的默认值
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "name", rename_all = "snake_case", deny_unknown_fields)]
+pub enum PricingConfig {
+    #[default]
+    Fixed,
+    Surge(Box<SurgePricing>),
+}
+
+impl PricingConfig {
+    pub fn build(&self) -> Arc<dyn PricingModel> {
+        match self {
+            PricingConfig::Fixed => Arc::new(FixedPricing),
+            PricingConfig::Surge(surge) => Arc::new(surge.as_ref().clone()),
+        }
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let PricingConfig::Surge(surge) = self else {
+            return Ok(());
+        };
+        ensure_in_range(
+            "surge.area_radius_km",
+            surge.area_radius_km,
+            MAX_DISTANCE_KM,
+        )?;
+        if !(surge.sensitivity >= 0.0 && surge.sensitivity.is_finite()) {
+            return invalid("surge.sensitivity must not be negative");
+        }
+        if !(surge.max_multiplier >= 1.0 && surge.max_multiplier.is_finite()) {
+            return invalid("surge.max_multiplier must be at least 1");
+        }
+        if surge
+            .hourly_multipliers
+            .iter()
+            .any(|m| *m <= 0.0 || !m.is_finite())
+        {
+            return invalid("surge.hourly_multipliers must be positive");
+        }
+        if !(surge.night_surcharge >= 0.0 && surge.night_surcharge.is_finite()) {
+            return invalid("surge.night_surcharge must not be negative");
+        }
+        if surge.night_start_hour > 23 || surge.night_end_hour > 23 {
+            return invalid("surge night hours must be between 0 and 23");
+        }
+        Ok(())
+    }
+}
+
+fn default_rules() -> Vec<RuleConfig> {
+    vec![RuleConfig::Capacity, RuleConfig::VehicleType]
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct VehicleConfig {
+    #[serde(default)]
+    pub capacity: Option<usize>,
+}
+
+/// 订单类型的计价参数，未填写的字段使用默认值。
+/// 配送时长由 `travel_model` 估算
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct OrderTypeConfig {
+    #[serde(default)]
+    pub base_price: Option<f64>,
+    #[serde(default)]
+    pub per_km_price: Option<f64>,
+}
+
+impl OrderTypeConfig {
+    fn profile(&self, order_type: &OrderType) -> OrderTypeProfile {
+        let default = order_type.default_profile();
+        OrderTypeProfile {
+            speed_kmh: default.speed_kmh,
+            base_price: self.base_price.unwrap_or(default.base_price),
+            per_km_price: self.per_km_price.unwrap_or(default.per_km_price),
+        }
+    }
+}
+
+/// 引擎配置，可从 TOML 或 JSON 文件加载
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct DispatchConfig {
+    #[serde(default)]
+    pub strategy: StrategyConfig,
+    #[serde(default = "default_rules")]
+    pub rules: Vec<RuleConfig>,
+    /// 按车型覆盖运力
+    #[serde(default)]
+    pub vehicles: HashMap<VehicleType, VehicleConfig>,
+    /// 按订单类型覆盖速度与计价表
+    #[serde(default)]
+    pub order_types: HashMap<OrderType, OrderTypeConfig>,
+    #[serde(default)]
+    pub pending_timeout_secs: Option<i64>,
+    #[serde(default)]
+    pub travel_model: TravelModelConfig,
+    #[serde(default)]
+    pub pricing: PricingConfig,
+    /// 服务区 GeoJSON 文件
+    #[serde(default)]
+    pub zones: Option<PathBuf>,
+}
+
+impl Default for DispatchConfig {
+    fn default() -> Self {
+        Self {
+            strategy: StrategyConfig::default(),
+            rules: default_rules(),
+            vehicles: HashMap::new(),
+            order_types: HashMap::new(),
+            pending_timeout_secs: None,
+            travel_model: TravelModelConfig::default(),
+            pricing: PricingConfig::default(),
+            zones: None,
+        }
+    }
+}
+
+impl DispatchConfig {
+    pub fn from_toml_str(content: &str) -> Result<Self, ConfigError> {
+        let config: Self = toml::from_str(content)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_json_str(content: &str) -> Result<Self, ConfigError> {
+        let config: Self = serde_json::from_str(content)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// 根据扩展名（`.toml` / `.json`）加载配置文件
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml_str(&content),
+            Some("json") => Self::from_json_str(&content),
+            _ => Err(ConfigError::UnsupportedFormat(path.display().to_string())),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.strategy.validate()?;
+        self.travel_model.validate()?;
+        self.pricing.validate()?;
+        for rule in &self.rules {
+            rule.validate()?;
+        }
+        for (vehicle_type, vehicle) in &self.vehicles {
+            if vehicle.capacity == Some(0) {
+                return invalid(&format!("capacity of {:?} must be positive", vehicle_type));
+            }
+        }
+        for (order_type, order) in &self.order_types {
+            let profile = order.profile(order_type);
+            let prices = [profile.base_price, profile.per_km_price];
+            if prices.iter().any(|p| !(*p >= 0.0 && p.is_finite())) {
+                return invalid(&format!("prices of {:?} must be non-negative", order_type));
+            }
+        }
+        if self.pending_timeout_secs.is_some_and(|secs| secs < 0) {
+            return invalid("pending_timeout_secs must be non-negative");
+        }
+        Ok(())
+    }
+
+    pub fn build_rules(&self) -> Vec<Box<dyn DispatchRule>> {
+        self.rules.iter().map(RuleConfig::build).collect()
+    }
+
+    pub fn order_profiles(&self) -> HashMap<OrderType, OrderTypeProfile> {
+        self.order_types
+            .iter()
+            .map(|(order_type, config)| (order_type.clone(), config.profile(order_type)))
+            .collect()
+    }
+}
+
+impl DispatchEngine {
+    /// 根据配置创建引擎
+    pub fn from_config(config: &DispatchConfig) -> Result<Self, ConfigError> {
+        Self::from_config_with_clock(config, Arc::new(SystemClock))
+    }
+
+    /// 按配置创建使用 `clock` 的引擎，仿真和回放用它控制时间
+    pub fn from_config_with_clock(
+        config: &DispatchConfig,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Self, ConfigError> {
+        config.validate()?;
+
+        let mut engine = DispatchEngine::new(config.strategy.build())
+            .with_clock(clock)
+            .with_travel_model(config.travel_model.build()?)
+            .with_pricing_model(config.pricing.build())
+            .with_rules(config.build_rules());
+        if let Some(timeout_secs) = config.pending_timeout_secs {
+            engine = engine.with_pending_timeout(timeout_secs);
+        }
+        for (vehicle_type, vehicle) in &config.vehicles {
+            if let Some(capacity) = vehicle.capacity {
+                engine = engine.with_vehicle_capacity(vehicle_type.clone(), capacity);
+            }
+        }
+        for (order_type, profile) in config.order_profiles() {
+            engine = engine.with_order_profile(order_type, profile);
+        }
+        if let Some(path) = &config.zones {
+            engine = engine.with_zones(Arc::new(ZoneMap::from_geojson_file(path)?));
+        }
+        Ok(engine)
+    }
+}
+
+fn invalid<T>(message: &str) -> Result<T, ConfigError> {
+    Err(ConfigError::Invalid(message.to_string()))
+}
+
+/// 距离、半径字段的上限（公里），更大的取值多半是笔误，
+/// 空间查询和行程估算也不再有意义
+const MAX_DISTANCE_KM: f64 = 1_000.0;
+/// 速度字段的上限（公里/小时）
+const MAX_SPEED_KMH: f64 = 300.0;
+/// 时长字段的上限（分钟），一周
+const MAX_MINUTES: f64 = 7.0 * 24.0 * 60.0;
+/// 道路绕行系数的上限
+const MAX_DETOUR_FACTOR: f64 = 10.0;
+
+/// 取值必须在 (0, max] 之间，NaN 和无穷大都会被拒绝
+fn ensure_in_range(field: &str, value: f64, max: f64) -> Result<(), ConfigError> {
+    if value > 0.0 && value <= max {
+        Ok(())
+    } else {
+        invalid(&format!("{} must be positive and at most {}", field, max))
+    }
+}
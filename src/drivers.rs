@@ -1,4 +1,5 @@
 use super::*;
+use std::collections::BTreeSet;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum DriverStatus {
@@ -7,7 +8,7 @@ pub enum DriverStatus {
     Offline, // 离线
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum VehicleType {
     ElectricBike, // 电动车
     Motorcycle,   // 摩托车
@@ -37,6 +38,26 @@ impl VehicleType {
         }
     }
 
+    /// 畅通路况下的平均行驶速度
+    pub fn average_speed_kmh(&self) -> f64 {
+        match self {
+            VehicleType::ElectricBike => 18.0,
+            VehicleType::Motorcycle => 30.0,
+            VehicleType::Car => 35.0,
+            VehicleType::Van => 30.0,
+            VehicleType::Truck => 25.0,
+        }
+    }
+
+    /// 受道路拥堵影响的程度，1.0 表示完全随车流减速
+    pub fn congestion_sensitivity(&self) -> f64 {
+        match self {
+            VehicleType::ElectricBike => 0.3,
+            VehicleType::Motorcycle => 0.5,
+            VehicleType::Car | VehicleType::Van | VehicleType::Truck => 1.0,
+        }
+    }
+
     pub fn can_carry_large_items(&self) -> bool {
         matches!(
             self,
@@ -45,6 +66,111 @@ impl VehicleType {
     }
 }
 
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum ShiftError {
+    #[error("Shift must end after it starts: [{start}, {end})")]
+    EmptyShift { start: i64, end: i64 },
+
+    #[error("Break [{start}, {end}) must end after it starts and lie within the shift")]
+    InvalidBreak { start: i64, end: i64 },
+
+    #[error("Break starting at {0} overlaps the previous break")]
+    OverlappingBreaks(i64),
+}
+
+/// 休息时段 [start, end)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BreakPeriod {
+    pub start: i64,
+    pub end: i64,
+}
+
+/// 配送员班次 [start, end)，时间均为 Unix 时间戳
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Shift {
+    pub start: i64,
+    pub end: i64,
+    /// 班次内的休息时段，按开始时间排序
+    #[serde(default)]
+    pub breaks: Vec<BreakPeriod>,
+}
+
+impl Shift {
+    pub fn new(start: i64, end: i64) -> Self {
+        Self {
+            start,
+            end,
+            breaks: Vec::new(),
+        }
+    }
+
+    pub fn with_break(mut self, start: i64, end: i64) -> Self {
+        self.breaks.push(BreakPeriod { start, end });
+        self.breaks.sort_by_key(|period| period.start);
+        self
+    }
+
+    /// 班次必须非空，休息时段非空、互不重叠且落在班次内
+    pub fn validate(&self) -> Result<(), ShiftError> {
+        if self.end <= self.start {
+            return Err(ShiftError::EmptyShift {
+                start: self.start,
+                end: self.end,
+            });
+        }
+        for period in &self.breaks {
+            if period.end <= period.start || period.start < self.start || period.end > self.end {
+                return Err(ShiftError::InvalidBreak {
+                    start: period.start,
+                    end: period.end,
+                });
+            }
+        }
+        // 反序列化得到的休息时段未必有序
+        let mut breaks = self.breaks.clone();
+        breaks.sort_by_key(|period| period.start);
+        for pair in breaks.windows(2) {
+            if pair[1].start < pair[0].end {
+                return Err(ShiftError::OverlappingBreaks(pair[1].start));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn has_ended(&self, timestamp: i64) -> bool {
+        timestamp >= self.end
+    }
+
+    pub fn on_break(&self, timestamp: i64) -> bool {
+        self.breaks
+            .iter()
+            .any(|period| (period.start..period.end).contains(&timestamp))
+    }
+
+    /// 在班且不在休息中
+    pub fn is_working(&self, timestamp: i64) -> bool {
+        (self.start..self.end).contains(&timestamp) && !self.on_break(timestamp)
+    }
+
+    /// 本段连续工作的开始时间：班次开始或最近一次休息结束
+    pub fn work_started_at(&self, timestamp: i64) -> i64 {
+        self.breaks
+            .iter()
+            .filter(|period| period.end <= timestamp)
+            .map(|period| period.end)
+            .fold(self.start, i64::max)
+    }
+
+    /// 本段连续工作的结束时间：下一次休息开始或班次结束
+    pub fn work_ends_at(&self, timestamp: i64) -> i64 {
+        self.breaks
+            .iter()
+            .filter(|period| period.start > timestamp)
+            .map(|period| period.start)
+            .fold(self.end, i64::min)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Driver {
     pub id: Uuid,
@@ -53,62 +179,156 @@ pub struct Driver {
     pub vehicle_type: VehicleType,
     pub current_location: Location,
     pub capacity: usize,
-    pub current_load: usize,
+    /// 当前承接的订单，负载由此集合推导
+    pub assigned_orders: BTreeSet<Uuid>,
+    /// 计划路线上尚未完成的停靠点
+    pub planned_stops: Vec<Stop>,
     pub rating: f64,
     pub total_orders: u32,
+    /// 最近一次变为空载的时间戳，有订单在身时为 None
+    pub idle_since: Option<i64>,
+    /// 所属服务区，未指定时按当前位置判断
+    #[serde(default)]
+    pub zone_id: Option<String>,
+    /// 班次安排，未设置时不受班次限制
+    #[serde(default)]
+    pub shift: Option<Shift>,
     pub metadata: HashMap<String, String>,
 }
 
 impl Driver {
     pub fn new(name: String, vehicle_type: VehicleType, current_location: Location) -> Self {
+        Self::new_with(
+            name,
+            vehicle_type,
+            current_location,
+            &SystemClock,
+            &RandomIdGenerator,
+        )
+    }
+
+    /// 使用指定的时钟和 ID 生成器创建配送员
+    pub fn new_with(
+        name: String,
+        vehicle_type: VehicleType,
+        current_location: Location,
+        clock: &dyn Clock,
+        ids: &dyn IdGenerator,
+    ) -> Self {
         let capacity = vehicle_type.capacity();
 
         Self {
-            id: Uuid::new_v4(),
+            id: ids.next_id(),
             name,
             status: DriverStatus::Idle,
             vehicle_type,
             current_location,
             capacity,
-            current_load: 0,
+            assigned_orders: BTreeSet::new(),
+            planned_stops: Vec::new(),
             rating: 5.0,
             total_orders: 0,
+            idle_since: Some(clock.now()),
+            zone_id: None,
+            shift: None,
             metadata: HashMap::new(),
         }
     }
 
+    pub fn with_zone(mut self, zone_id: impl Into<String>) -> Self {
+        self.zone_id = Some(zone_id.into());
+        self
+    }
+
+    pub fn with_shift(mut self, shift: Shift) -> Self {
+        self.shift = Some(shift);
+        self
+    }
+
+    pub fn current_load(&self) -> usize {
+        self.assigned_orders.len()
+    }
+
+    pub fn has_order(&self, order_id: &Uuid) -> bool {
+        self.assigned_orders.contains(order_id)
+    }
+
     pub fn is_available(&self) -> bool {
-        self.status != DriverStatus::Offline && self.current_load < self.capacity
+        self.status != DriverStatus::Offline && self.current_load() < self.capacity
     }
 
     pub fn load_ratio(&self) -> f64 {
         if self.capacity == 0 {
             1.0
         } else {
-            self.current_load as f64 / self.capacity as f64
+            self.current_load() as f64 / self.capacity as f64
         }
     }
 
-    pub fn assign_order(&mut self) -> bool {
-        if self.is_available() {
-            self.current_load += 1;
-            self.total_orders += 1;
-            if self.current_load >= self.capacity {
-                self.status = DriverStatus::Busy;
-            }
-            true
-        } else {
-            false
+    /// 承接订单，司机不可用或订单已在身上时返回 false
+    pub fn assign_order(&mut self, order_id: Uuid) -> bool {
+        if !self.is_available() || !self.assigned_orders.insert(order_id) {
+            return false;
+        }
+
+        self.total_orders += 1;
+        self.idle_since = None;
+        if self.current_load() >= self.capacity {
+            self.status = DriverStatus::Busy;
         }
+        true
     }
 
-    pub fn release_order(&mut self) {
-        if self.current_load > 0 {
-            self.current_load -= 1;
-            if self.current_load < self.capacity && self.status != DriverStatus::Offline {
-                self.status = DriverStatus::Idle;
-            }
+    /// 释放订单，订单不在司机身上时返回 false
+    pub fn release_order(&mut self, order_id: &Uuid) -> bool {
+        self.release_order_at(order_id, SystemClock.now())
+    }
+
+    /// 在 `now` 时刻释放订单，订单全部释放后从 `now` 开始计算空闲时长
+    pub fn release_order_at(&mut self, order_id: &Uuid, now: i64) -> bool {
+        if !self.assigned_orders.remove(order_id) {
+            return false;
+        }
+
+        self.planned_stops.retain(|stop| stop.order_id != *order_id);
+        if self.assigned_orders.is_empty() {
+            self.idle_since = Some(now);
         }
+        if self.current_load() < self.capacity && self.status != DriverStatus::Offline {
+            self.status = DriverStatus::Idle;
+        }
+        true
+    }
+
+    /// 以最小绕路的方式把订单的取货、送达点插入计划路线
+    pub fn plan_order(&mut self, order: &Order) {
+        self.plan_order_with(&ConstantSpeedModel::default(), order, SystemClock.now());
+    }
+
+    /// 按行程时间模型从 `now` 出发估算，以增加时间最少的方式插入订单
+    pub fn plan_order_with(&mut self, model: &dyn TravelTimeModel, order: &Order, now: i64) {
+        let insertion = best_insertion(
+            model,
+            self.current_location,
+            &self.planned_stops,
+            order,
+            &self.vehicle_type,
+            now,
+        );
+        self.planned_stops = apply_insertion(&self.planned_stops, order, &insertion);
+    }
+
+    /// 完成路线上的停靠点
+    pub fn complete_stop(&mut self, order_id: &Uuid, kind: StopKind) {
+        self.planned_stops
+            .retain(|stop| !(stop.order_id == *order_id && stop.kind == kind));
+    }
+
+    /// 空载时长（分钟），有订单在身时为 0
+    pub fn idle_minutes(&self, now: i64) -> f64 {
+        self.idle_since
+            .map(|since| (now - since).max(0) as f64 / 60.0)
+            .unwrap_or(0.0)
     }
 
     pub fn distance_to(&self, location: Location) -> f64 {
@@ -119,20 +339,29 @@ impl Driver {
 #[derive(Debug)]
 pub struct DriverPool {
     drivers: HashMap<Uuid, Driver>,
+    index: GridIndex,
 }
 
 impl DriverPool {
     pub fn new() -> Self {
+        Self::with_cell_size(0.01)
+    }
+
+    /// 指定空间索引网格边长（度）
+    pub fn with_cell_size(cell_size_deg: f64) -> Self {
         Self {
             drivers: HashMap::new(),
+            index: GridIndex::new(cell_size_deg),
         }
     }
 
     pub fn add_driver(&mut self, driver: Driver) {
+        self.index.insert(driver.id, driver.current_location);
         self.drivers.insert(driver.id, driver);
     }
 
     pub fn remove_driver(&mut self, driver_id: &Uuid) -> Option<Driver> {
+        self.index.remove(driver_id);
         self.drivers.remove(driver_id)
     }
 
@@ -140,10 +369,24 @@ impl DriverPool {
         self.drivers.get(driver_id)
     }
 
-    pub fn get_driver_mut(&mut self, driver_id: &Uuid) -> Option<&mut Driver> {
+    /// 获取可变引用，仅供引擎内部修改订单和状态。
+    /// 位置只能通过 `update_location` 修改，以保持空间索引同步
+    pub(crate) fn get_driver_mut(&mut self, driver_id: &Uuid) -> Option<&mut Driver> {
         self.drivers.get_mut(driver_id)
     }
 
+    /// 更新配送员位置并同步空间索引，配送员不存在时返回 false
+    pub fn update_location(&mut self, driver_id: &Uuid, location: Location) -> bool {
+        match self.drivers.get_mut(driver_id) {
+            Some(driver) => {
+                driver.current_location = location;
+                self.index.insert(*driver_id, location);
+                true
+            }
+            None => false,
+        }
+    }
+
     pub fn get_all_drivers(&self) -> Vec<&Driver> {
         self.drivers.values().collect()
     }
@@ -151,6 +394,32 @@ impl DriverPool {
     pub fn get_available_drivers(&self) -> Vec<&Driver> {
         self.drivers.values().filter(|d| d.is_available()).collect()
     }
+
+    /// 距离指定位置最近的 k 个配送员，按距离升序
+    pub fn nearest_k(&self, location: Location, k: usize) -> Vec<&Driver> {
+        self.index
+            .nearest_k(location, k)
+            .into_iter()
+            .map(|(id, _)| &self.drivers[&id])
+            .collect()
+    }
+
+    /// 指定半径（公里）内的配送员，按距离升序
+    pub fn within_radius(&self, location: Location, radius_km: f64) -> Vec<&Driver> {
+        self.index
+            .within_radius(location, radius_km)
+            .into_iter()
+            .map(|(id, _)| &self.drivers[&id])
+            .collect()
+    }
+
+    /// 指定半径内的可用配送员
+    pub fn get_available_drivers_within(&self, location: Location, radius_km: f64) -> Vec<&Driver> {
+        self.within_radius(location, radius_km)
+            .into_iter()
+            .filter(|d| d.is_available())
+            .collect()
+    }
 }
 
 impl Default for DriverPool {
@@ -1,11 +1,17 @@
 use super::*;
-use std::sync::{Arc, RwLock};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
 
 #[derive(Debug, thiserror::Error)]
 pub enum DispatchError {
+    /// 没有在线且未满载的配送员
     #[error("No available driver found")]
     NoDriverAvailable,
 
+    /// 有可用的配送员，但都被规则或策略拒绝
+    #[error("No eligible driver found: {0}")]
+    NoEligibleDriver(RejectionSummary),
+
     #[error("Driver not found: {0}")]
     DriverNotFound(Uuid),
 
@@ -17,6 +23,13 @@ pub enum DispatchError {
 
     #[error("Invalid operation: {0}")]
     InvalidOperation(String),
+
+    #[error("Order {0} is outside every service zone")]
+    OutsideServiceArea(Uuid),
+
+    /// 状态变更未能写入存储，本次操作没有生效
+    #[error("Failed to persist state change: {0}")]
+    Persistence(String),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,93 +40,1341 @@ pub struct DispatchResult {
     pub vehicle_type: String,
     pub distance_to_pickup_km: f64,
     pub estimated_arrival_minutes: u32,
+    /// 预计送达时间戳
+    pub estimated_delivery_at: i64,
+    /// 预计迟到分钟数，订单没有截止时间时为 None
+    pub predicted_lateness_minutes: Option<u32>,
     pub strategy_used: String,
     pub timestamp: i64,
+    /// 评分类策略给出的分项得分
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score_breakdown: Option<ScoreBreakdown>,
+}
+
+/// 配送员被拒绝的原因，`rejected_by` 为拒绝该配送员的规则名称；
+/// 通过了所有规则但被策略拒绝（例如拼单绕路过远）时为策略名称
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DriverRejection {
+    pub driver_id: Uuid,
+    pub driver_name: String,
+    pub rejected_by: Vec<String>,
+}
+
+/// 通过所有规则的配送员，按策略偏好排序
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RankedDriver {
+    pub rank: usize,
+    pub driver_id: Uuid,
+    pub driver_name: String,
+    pub distance_to_pickup_km: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<ScoreBreakdown>,
+}
+
+/// 派单决策解释
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DispatchExplanation {
+    pub order_id: Uuid,
+    pub strategy: String,
+    pub rejections: Vec<DriverRejection>,
+    pub ranked: Vec<RankedDriver>,
+    pub summary: RejectionSummary,
+}
+
+/// 各规则拒绝配送员的数量汇总
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RejectionSummary {
+    pub total_drivers: usize,
+    pub rejected_by: BTreeMap<String, usize>,
+}
+
+impl RejectionSummary {
+    fn from_rejections(total_drivers: usize, rejections: &[DriverRejection]) -> Self {
+        let mut rejected_by = BTreeMap::new();
+        for name in rejections.iter().flat_map(|r| &r.rejected_by) {
+            *rejected_by.entry(name.clone()).or_insert(0) += 1;
+        }
+        Self {
+            total_drivers,
+            rejected_by,
+        }
+    }
+}
+
+impl std::fmt::Display for RejectionSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.total_drivers == 0 {
+            return write!(f, "no drivers in pool");
+        }
+        write!(f, "{} drivers checked", self.total_drivers)?;
+        let reasons: Vec<String> = self
+            .rejected_by
+            .iter()
+            .map(|(name, count)| format!("{}={}", name, count))
+            .collect();
+        if !reasons.is_empty() {
+            write!(f, ", rejected by {}", reasons.join(", "))?;
+        }
+        Ok(())
+    }
 }
 
+impl DispatchError {
+    /// 错误类型名称，与枚举成员名一致，便于脚本按类型处理
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DispatchError::NoDriverAvailable => "NoDriverAvailable",
+            DispatchError::NoEligibleDriver(_) => "NoEligibleDriver",
+            DispatchError::DriverNotFound(_) => "DriverNotFound",
+            DispatchError::OrderNotFound(_) => "OrderNotFound",
+            DispatchError::CapacityExceeded => "CapacityExceeded",
+            DispatchError::InvalidOperation(_) => "InvalidOperation",
+            DispatchError::OutsideServiceArea(_) => "OutsideServiceArea",
+            DispatchError::Persistence(_) => "Persistence",
+        }
+    }
+}
+
+/// 等待队列默认超时时间（秒）
+pub const DEFAULT_PENDING_TIMEOUT_SECS: i64 = 600;
+
+/// 配送员不可用（离线或满载）时的拒绝原因名称
+pub const UNAVAILABLE_REASON: &str = "Unavailable";
+
 pub struct DispatchEngine {
+    /// 需要同时持有时，先取配送员池的锁，再取订单库的锁
     driver_pool: Arc<RwLock<DriverPool>>,
-    strategy: Arc<dyn DispatchStrategy>,
-    rules: Vec<Box<dyn DispatchRule>>,
+    orders: Arc<RwLock<OrderStore>>,
+    strategy: Arc<RwLock<Arc<dyn DispatchStrategy>>>,
+    batch_strategy: Arc<dyn BatchDispatchStrategy>,
+    rules: Arc<RwLock<Vec<Box<dyn DispatchRule>>>>,
+    pending: Arc<RwLock<PendingQueue>>,
+    pending_timeout_secs: i64,
+    vehicle_capacities: HashMap<VehicleType, usize>,
+    order_profiles: HashMap<OrderType, OrderTypeProfile>,
+    events: Arc<EventBus>,
+    store: Option<Arc<dyn StateStore>>,
+    persistence_error: Mutex<Option<String>>,
+    travel_model: Arc<dyn TravelTimeModel>,
+    pricing: Arc<dyn PricingModel>,
+    zones: Option<Arc<ZoneMap>>,
+    clock: Arc<dyn Clock>,
+    ids: Arc<dyn IdGenerator>,
 }
 
 impl DispatchEngine {
     pub fn new(strategy: Arc<dyn DispatchStrategy>) -> Self {
-        Self {
+        let engine = Self {
             driver_pool: Arc::new(RwLock::new(DriverPool::new())),
-            strategy,
-            rules: vec![Box::new(CapacityRule), Box::new(VehicleTypeRule)],
+            orders: Arc::new(RwLock::new(OrderStore::new())),
+            strategy: Arc::new(RwLock::new(strategy)),
+            batch_strategy: Arc::new(MinCostBatchStrategy::new()),
+            rules: Arc::new(RwLock::new(vec![
+                Box::new(CapacityRule),
+                Box::new(VehicleTypeRule),
+            ])),
+            pending: Arc::new(RwLock::new(PendingQueue::new())),
+            pending_timeout_secs: DEFAULT_PENDING_TIMEOUT_SECS,
+            vehicle_capacities: HashMap::new(),
+            order_profiles: HashMap::new(),
+            events: Arc::new(EventBus::default()),
+            store: None,
+            persistence_error: Mutex::new(None),
+            travel_model: Arc::new(ConstantSpeedModel::default()),
+            pricing: Arc::new(FixedPricing),
+            zones: None,
+            clock: Arc::new(SystemClock),
+            ids: Arc::new(RandomIdGenerator),
+        };
+        engine.rebind_strategy();
+        engine
+    }
+
+    pub fn with_rules(self, rules: Vec<Box<dyn DispatchRule>>) -> Self {
+        self.set_rules(rules);
+        self
+    }
+
+    /// 设置行程时间模型，用于接驾时间、配送时长和规则中的时效判断
+    pub fn with_travel_model(mut self, travel_model: Arc<dyn TravelTimeModel>) -> Self {
+        self.travel_model = travel_model;
+        self.rebind_rules();
+        self.rebind_strategy();
+        self.batch_strategy = self.bind_batch_strategy(self.batch_strategy.clone());
+        self
+    }
+
+    pub fn travel_model(&self) -> Arc<dyn TravelTimeModel> {
+        self.travel_model.clone()
+    }
+
+    /// 设置计价模型，订单进入引擎时由它定价
+    pub fn with_pricing_model(mut self, pricing: Arc<dyn PricingModel>) -> Self {
+        self.pricing = pricing;
+        self
+    }
+
+    pub fn pricing_model_name(&self) -> String {
+        self.pricing.name().to_string()
+    }
+
+    /// 设置服务区：取货点或送达点不在任何服务区内的订单会被拒绝
+    pub fn with_zones(mut self, zones: Arc<ZoneMap>) -> Self {
+        self.zones = Some(zones);
+        self.rebind_rules();
+        self
+    }
+
+    pub fn zones(&self) -> Option<Arc<ZoneMap>> {
+        self.zones.clone()
+    }
+
+    /// 配送员所属服务区，优先级见 `ZoneMap::driver_zone`；未设置服务区时只能取指定的服务区
+    pub fn driver_zone(&self, driver: &Driver) -> Option<String> {
+        match &self.zones {
+            Some(zones) => zones.driver_zone(driver),
+            None => driver.zone_id.as_deref(),
         }
+        .map(str::to_string)
+    }
+
+    /// 设置时钟，事件时间戳、等待队列超时、班次和时效判断都按它计时
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self.rebind_rules();
+        self.rebind_strategy();
+        self
+    }
+
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+
+    /// 设置 ID 生成器，通过 `new_order` 和 `new_driver` 创建的订单和配送员使用它分配 ID
+    pub fn with_id_generator(mut self, ids: Arc<dyn IdGenerator>) -> Self {
+        self.ids = ids;
+        self
+    }
+
+    pub fn id_generator(&self) -> Arc<dyn IdGenerator> {
+        self.ids.clone()
+    }
+
+    /// 按引擎的时钟和 ID 生成器创建订单
+    pub fn new_order(
+        &self,
+        order_type: OrderType,
+        pickup_location: Location,
+        delivery_location: Location,
+    ) -> Order {
+        Order::new_with(
+            order_type,
+            pickup_location,
+            delivery_location,
+            self.clock.as_ref(),
+            self.ids.as_ref(),
+        )
+    }
+
+    /// 按引擎的时钟和 ID 生成器创建配送员
+    pub fn new_driver(
+        &self,
+        name: String,
+        vehicle_type: VehicleType,
+        current_location: Location,
+    ) -> Driver {
+        Driver::new_with(
+            name,
+            vehicle_type,
+            current_location,
+            self.clock.as_ref(),
+            self.ids.as_ref(),
+        )
+    }
+
+    /// 向规则传入引擎的行程时间模型、服务区和时钟
+    fn bind_rule(&self, rule: &mut Box<dyn DispatchRule>) {
+        rule.set_travel_model(self.travel_model.clone());
+        rule.set_clock(self.clock.clone());
+        if let Some(zones) = &self.zones {
+            rule.set_zones(zones.clone());
+        }
+    }
+
+    fn rebind_rules(&self) {
+        for rule in self.rules.write().unwrap().iter_mut() {
+            self.bind_rule(rule);
+        }
+    }
+
+    /// 策略以 `Arc` 共享，绑定得到的是使用引擎行程时间模型和时钟的副本
+    fn bind_strategy(&self, strategy: Arc<dyn DispatchStrategy>) -> Arc<dyn DispatchStrategy> {
+        let strategy = strategy
+            .bind_travel_model(self.travel_model.clone())
+            .unwrap_or(strategy);
+        strategy.bind_clock(self.clock.clone()).unwrap_or(strategy)
+    }
+
+    fn rebind_strategy(&self) {
+        let mut strategy = self.strategy.write().unwrap();
+        *strategy = self.bind_strategy(strategy.clone());
+    }
+
+    fn bind_batch_strategy(
+        &self,
+        batch_strategy: Arc<dyn BatchDispatchStrategy>,
+    ) -> Arc<dyn BatchDispatchStrategy> {
+        batch_strategy
+            .bind_travel_model(self.travel_model.clone())
+            .unwrap_or(batch_strategy)
     }
 
-    pub fn with_rules(mut self, rules: Vec<Box<dyn DispatchRule>>) -> Self {
-        self.rules = rules;
+    pub fn with_batch_strategy(mut self, batch_strategy: Arc<dyn BatchDispatchStrategy>) -> Self {
+        self.batch_strategy = self.bind_batch_strategy(batch_strategy);
         self
     }
 
-    pub fn add_rule(&mut self, rule: Box<dyn DispatchRule>) {
-        self.rules.push(rule);
+    /// 设置订单在等待队列中的最长等待时间，超时后订单被取消
+    pub fn with_pending_timeout(mut self, timeout_secs: i64) -> Self {
+        self.pending_timeout_secs = timeout_secs;
+        self
     }
 
-    pub fn add_driver(&self, driver: Driver) {
+    /// 覆盖车型运力，之后加入的该车型配送员使用此运力；
+    /// 配送员已单独设置了不同于车型默认值的运力时保持不变
+    pub fn with_vehicle_capacity(mut self, vehicle_type: VehicleType, capacity: usize) -> Self {
+        self.vehicle_capacities.insert(vehicle_type, capacity);
+        self
+    }
+
+    /// 覆盖订单类型的速度与计价表，订单进入引擎时按此重新估算时长和价格
+    pub fn with_order_profile(mut self, order_type: OrderType, profile: OrderTypeProfile) -> Self {
+        self.order_profiles.insert(order_type, profile);
+        self
+    }
+
+    /// 挂载持久化存储：先用存储中的状态恢复配送员、订单和等待队列，
+    /// 之后的每次状态变更都会写入存储
+    pub fn with_store(mut self, store: Arc<dyn StateStore>) -> Result<Self, StoreError> {
+        let snapshot = store.load()?;
+
         let mut pool = self.driver_pool.write().unwrap();
+        for driver in snapshot.drivers.into_values() {
+            pool.add_driver(driver);
+        }
+        drop(pool);
+
+        let mut orders = self.orders.write().unwrap();
+        for order in snapshot.orders.into_values() {
+            orders.add_order(order);
+        }
+        drop(orders);
+
+        let mut pending = self.pending.write().unwrap();
+        for entry in snapshot.pending.into_values() {
+            pending.insert(entry);
+        }
+        drop(pending);
+
+        self.store = Some(store);
+        Ok(self)
+    }
+
+    /// 当前引擎状态的快照
+    pub fn snapshot(&self) -> EngineSnapshot {
+        let drivers = self
+            .get_all_drivers()
+            .into_iter()
+            .map(|driver| (driver.id, driver))
+            .collect();
+        let orders = self
+            .get_all_orders()
+            .into_iter()
+            .map(|order| (order.id, order))
+            .collect();
+        let pending = self
+            .pending
+            .read()
+            .unwrap()
+            .entries()
+            .map(|entry| (entry.order_id, entry.clone()))
+            .collect();
+        EngineSnapshot {
+            drivers,
+            orders,
+            pending,
+        }
+    }
+
+    /// 让存储将已记录的变更合并为快照，未挂载存储时不做任何事
+    pub fn compact_store(&self) -> Result<(), StoreError> {
+        match &self.store {
+            Some(store) => store.compact(),
+            None => Ok(()),
+        }
+    }
+
+    /// 记录状态变更。调用方应持有被修改数据的锁，保证日志顺序与内存修改顺序一致。
+    /// 返回 `Result` 的公开方法先写日志再修改内存，写入失败时返回错误且状态不变；
+    /// 等待队列、班次下线等内部记录的失败只通过 `persistence_error` 报告
+    fn persist(&self, change: impl FnOnce() -> StateChange) -> Result<(), DispatchError> {
+        let Some(store) = &self.store else {
+            return Ok(());
+        };
+        store.append(&change()).map_err(|e| {
+            let message = e.to_string();
+            *self.persistence_error.lock().unwrap() = Some(message.clone());
+            DispatchError::Persistence(message)
+        })
+    }
+
+    /// 先写日志再替换配送员，写入失败时配送员保持原样
+    fn commit_driver(&self, driver: &mut Driver, updated: Driver) -> Result<(), DispatchError> {
+        self.persist(|| StateChange::UpsertDriver {
+            driver: updated.clone(),
+        })?;
+        *driver = updated;
+        Ok(())
+    }
+
+    /// 最近一次写入存储失败的原因。出现失败后存储可能落后于内存状态，
+    /// 应检查存储并重启恢复；存储自身的维护错误（如自动快照失败）也在这里报告
+    pub fn persistence_error(&self) -> Option<String> {
+        self.persistence_error.lock().unwrap().clone().or_else(|| {
+            self.store
+                .as_ref()
+                .and_then(|store| store.maintenance_error())
+        })
+    }
+
+    /// 注册事件回调，在引擎状态变化后同步调用。
+    /// 回调执行时引擎不持有任何内部锁，可以在回调中调用引擎
+    pub fn subscribe(&self, subscriber: Arc<dyn EventSubscriber>) {
+        self.events.subscribe(subscriber);
+    }
+
+    /// 获取事件广播接收端，适合在异步任务中消费事件
+    pub fn event_receiver(&self) -> tokio::sync::broadcast::Receiver<DispatchEvent> {
+        self.events.receiver()
+    }
+
+    pub fn add_rule(&self, mut rule: Box<dyn DispatchRule>) {
+        self.bind_rule(&mut rule);
+        self.rules.write().unwrap().push(rule);
+    }
+
+    /// 运行时切换派单策略，配送员、订单和等待队列保持不变。
+    /// 正在进行的派单继续使用切换前的策略
+    pub fn set_strategy(&self, strategy: Arc<dyn DispatchStrategy>) {
+        let strategy = self.bind_strategy(strategy);
+        let name = strategy.name().to_string();
+        *self.strategy.write().unwrap() = strategy;
+        self.events.publish(DispatchEvent::StrategyChanged {
+            strategy: name,
+            timestamp: self.clock.now(),
+        });
+    }
+
+    /// 运行时整体替换规则集
+    pub fn set_rules(&self, mut rules: Vec<Box<dyn DispatchRule>>) {
+        for rule in rules.iter_mut() {
+            self.bind_rule(rule);
+        }
+        *self.rules.write().unwrap() = rules;
+    }
+
+    pub fn strategy_name(&self) -> String {
+        self.strategy.read().unwrap().name().to_string()
+    }
+
+    pub fn rule_names(&self) -> Vec<String> {
+        self.rules
+            .read()
+            .unwrap()
+            .iter()
+            .map(|rule| rule.name().to_string())
+            .collect()
+    }
+
+    fn current_strategy(&self) -> Arc<dyn DispatchStrategy> {
+        self.strategy.read().unwrap().clone()
+    }
+
+    pub fn add_driver(&self, mut driver: Driver) {
+        if driver.capacity == driver.vehicle_type.capacity()
+            && let Some(capacity) = self.vehicle_capacities.get(&driver.vehicle_type)
+        {
+            driver.capacity = *capacity;
+        }
+
+        let event = DispatchEvent::DriverAdded {
+            driver_id: driver.id,
+            driver_name: driver.name.clone(),
+            vehicle_type: driver.vehicle_type.clone(),
+            timestamp: self.clock.now(),
+        };
+
+        let mut pool = self.driver_pool.write().unwrap();
+        let _ = self.persist(|| StateChange::UpsertDriver {
+            driver: driver.clone(),
+        });
         pool.add_driver(driver);
+        drop(pool);
+
+        self.events.publish(event);
+        self.retry_pending();
     }
 
     pub fn remove_driver(&self, driver_id: &Uuid) -> Result<Driver, DispatchError> {
         let mut pool = self.driver_pool.write().unwrap();
-        pool.remove_driver(driver_id)
-            .ok_or(DispatchError::DriverNotFound(*driver_id))
+        if pool.get_driver(driver_id).is_none() {
+            return Err(DispatchError::DriverNotFound(*driver_id));
+        }
+        self.persist(|| StateChange::RemoveDriver {
+            driver_id: *driver_id,
+        })?;
+        let driver = pool.remove_driver(driver_id).expect("driver checked above");
+        drop(pool);
+
+        self.events.publish(DispatchEvent::DriverRemoved {
+            driver_id: *driver_id,
+            timestamp: self.clock.now(),
+        });
+        Ok(driver)
+    }
+
+    /// 提交订单到引擎，订单进入 Pending 状态等待派单
+    pub fn submit_order(&self, mut order: Order) -> Result<Uuid, DispatchError> {
+        if order.status != OrderStatus::Pending {
+            return Err(DispatchError::InvalidOperation(format!(
+                "order {} must be Pending to be submitted, got {:?}",
+                order.id, order.status
+            )));
+        }
+
+        self.ensure_in_service_area(&order)?;
+        // 计价需要读取配送员池，必须在持有订单库锁之前完成
+        self.price_order(&mut order);
+        let mut orders = self.orders.write().unwrap();
+        if orders.contains(&order.id) {
+            return Err(DispatchError::InvalidOperation(format!(
+                "order {} already submitted",
+                order.id
+            )));
+        }
+
+        let event = DispatchEvent::OrderSubmitted {
+            order_id: order.id,
+            order_type: order.order_type.clone(),
+            timestamp: self.clock.now(),
+        };
+        let order_id = order.id;
+        self.persist(|| StateChange::UpsertOrder {
+            order: order.clone(),
+        })?;
+        orders.add_order(order);
+        drop(orders);
+
+        self.events.publish(event);
+        Ok(order_id)
     }
 
+    /// 派发已提交的订单
+    pub fn dispatch_order(&self, order_id: &Uuid) -> Result<DispatchResult, DispatchError> {
+        let mut order = self.get_order(order_id)?;
+        self.dispatch(&mut order)
+    }
+
+    /// 派发订单，派单成功后订单会被记录到引擎的订单库中。
+    /// 失败时订单不会进入等待队列；需要排队重试请使用 `dispatch_or_enqueue`
     pub fn dispatch(&self, order: &mut Order) -> Result<DispatchResult, DispatchError> {
+        self.dispatch_with(order, false)
+    }
+
+    /// 派发订单，返回 `NoDriverAvailable` 或 `NoEligibleDriver` 时订单已进入等待队列，
+    /// 由 `tick` 或运力释放时重试；其他错误不会入队
+    pub fn dispatch_or_enqueue(&self, order: &mut Order) -> Result<DispatchResult, DispatchError> {
+        self.dispatch_with(order, true)
+    }
+
+    fn dispatch_with(
+        &self,
+        order: &mut Order,
+        enqueue: bool,
+    ) -> Result<DispatchResult, DispatchError> {
+        if !self.orders.read().unwrap().contains(&order.id) {
+            self.price_order(order);
+        }
+
+        let result = self.try_dispatch(order);
+        if let Err(e) = &result {
+            let queued = enqueue
+                && matches!(
+                    e,
+                    DispatchError::NoDriverAvailable | DispatchError::NoEligibleDriver(_)
+                );
+            if queued {
+                self.enqueue_pending(order);
+            }
+            self.publish_failure(order, e, queued);
+        }
+        result
+    }
+
+    fn publish_failure(&self, order: &Order, error: &DispatchError, queued: bool) {
+        self.events.publish(DispatchEvent::DispatchFailed {
+            order_id: order.id,
+            reason: error.to_string(),
+            queued,
+            timestamp: self.clock.now(),
+        });
+    }
+
+    /// 按订单类型的计价表计价，按行程时间模型估算配送时长，再由计价模型结合附近供需和时段定价
+    fn price_order(&self, order: &mut Order) {
+        let profile = self
+            .order_profiles
+            .get(&order.order_type)
+            .cloned()
+            .unwrap_or_else(|| order.order_type.default_profile());
+        order.apply_profile(&profile);
+        // 派单前车型未定，按订单类型的常用车型估算配送时长，派单后按实际车型重新估算
+        order.estimated_duration_minutes = self
+            .travel_model
+            .travel_minutes(
+                order.pickup_location,
+                order.delivery_location,
+                &order.order_type.typical_vehicle(),
+                self.clock.now(),
+            )
+            .ceil()
+            .max(5.0) as u32;
+
+        let context = self.pricing_context(order.pickup_location);
+        let breakdown = self.pricing.quote(order, &profile, &context);
+        order.price = breakdown.total;
+        order.price_breakdown = Some(breakdown);
+    }
+
+    /// 采集 `location` 附近的等待订单数和空闲配送员数，依次获取各把锁，不同时持有
+    fn pricing_context(&self, location: Location) -> PricingContext {
+        let now = self.clock.now();
+        let Some(radius_km) = self.pricing.area_radius_km() else {
+            return PricingContext {
+                now,
+                ..PricingContext::default()
+            };
+        };
+
+        let pending_ids: Vec<Uuid> = self
+            .pending
+            .read()
+            .unwrap()
+            .entries()
+            .map(|entry| entry.order_id)
+            .collect();
+        let orders = self.orders.read().unwrap();
+        let pending_orders = pending_ids
+            .iter()
+            .filter_map(|id| orders.get_order(id))
+            .filter(|order| distance_km(order.pickup_location, location) <= radius_km)
+            .count();
+        drop(orders);
+
+        let available_drivers = self
+            .driver_pool
+            .read()
+            .unwrap()
+            .get_available_drivers_within(location, radius_km)
+            .len();
+
+        PricingContext {
+            now,
+            pending_orders,
+            available_drivers,
+        }
+    }
+
+    fn try_dispatch(&self, order: &mut Order) -> Result<DispatchResult, DispatchError> {
+        self.ensure_dispatchable(order)?;
+
+        let strategy = self.current_strategy();
+        let rules = self.rules.read().unwrap();
         let pool = self.driver_pool.read().unwrap();
-        let available_drivers = pool.get_available_drivers();
+        let available_drivers = candidate_drivers(order, &pool, &rules);
 
-        let selected_driver = self
-            .strategy
-            .select_driver(order, available_drivers, &self.rules)
-            .ok_or(DispatchError::NoDriverAvailable)?;
+        let Some(selected_driver) = strategy.select_driver(order, available_drivers, &rules) else {
+            if pool.get_available_drivers().is_empty() {
+                return Err(DispatchError::NoDriverAvailable);
+            }
+            // 通过了所有规则却没被选中的配送员是被策略拒绝的
+            let (mut rejections, eligible) = evaluate_drivers(order, &pool, &rules);
+            rejections.extend(strategy_rejections(strategy.as_ref(), &eligible, &[]));
+            return Err(DispatchError::NoEligibleDriver(
+                RejectionSummary::from_rejections(pool.get_all_drivers().len(), &rejections),
+            ));
+        };
 
         let driver_id = selected_driver.id;
-        let driver_name = selected_driver.name.clone();
-        let vehicle_type = selected_driver.vehicle_type.display_name().to_string();
-        let distance_to_pickup_km = selected_driver.distance_to(order.pickup_location);
-        let estimated_arrival_minutes = (distance_to_pickup_km / 30.0 * 60.0) as u32; // 假设30km/h
+        let score_breakdown = strategy.score_breakdown(order, selected_driver);
+        drop(pool);
+        drop(rules);
+
+        self.assign_to_driver(order, driver_id, strategy.name(), score_breakdown)
+    }
+
+    /// 解释派单决策：列出每个配送员被哪些规则拒绝，以及合格配送员的排序。
+    /// 不会修改任何状态
+    pub fn dispatch_explain(&self, order: &Order) -> DispatchExplanation {
+        let strategy = self.current_strategy();
+        let rules = self.rules.read().unwrap();
+        let pool = self.driver_pool.read().unwrap();
+        let (mut rejections, eligible) = evaluate_drivers(order, &pool, &rules);
+        let preferred = strategy.rank_drivers(order, eligible.clone(), &rules);
+        rejections.extend(strategy_rejections(
+            strategy.as_ref(),
+            &eligible,
+            &preferred,
+        ));
+        rejections.sort_by_key(|rejection| rejection.driver_id);
 
+        let ranked = preferred
+            .iter()
+            .enumerate()
+            .map(|(i, driver)| RankedDriver {
+                rank: i + 1,
+                driver_id: driver.id,
+                driver_name: driver.name.clone(),
+                distance_to_pickup_km: self
+                    .travel_model
+                    .distance_km(driver.current_location, order.pickup_location),
+                score: strategy.score_breakdown(order, driver),
+            })
+            .collect();
+
+        DispatchExplanation {
+            order_id: order.id,
+            strategy: strategy.name().to_string(),
+            summary: RejectionSummary::from_rejections(pool.get_all_drivers().len(), &rejections),
+            rejections,
+            ranked,
+        }
+    }
+
+    /// 批量派单：由批量策略对整批订单做全局匹配，结果与 `orders` 一一对应。
+    /// 同一订单在批次中重复出现时，只派发第一次出现的那个。
+    /// 未派出的订单不会进入等待队列；需要排队重试请使用 `dispatch_batch_or_enqueue`
+    pub fn dispatch_batch(
+        &self,
+        orders: &mut [Order],
+    ) -> Vec<Result<DispatchResult, DispatchError>> {
+        self.dispatch_batch_with(orders, false)
+    }
+
+    /// 批量派单，返回 `NoDriverAvailable` 或 `NoEligibleDriver` 的订单进入等待队列
+    pub fn dispatch_batch_or_enqueue(
+        &self,
+        orders: &mut [Order],
+    ) -> Vec<Result<DispatchResult, DispatchError>> {
+        self.dispatch_batch_with(orders, true)
+    }
+
+    fn dispatch_batch_with(
+        &self,
+        orders: &mut [Order],
+        enqueue: bool,
+    ) -> Vec<Result<DispatchResult, DispatchError>> {
+        let mut results: Vec<Option<Result<DispatchResult, DispatchError>>> =
+            orders.iter().map(|_| None).collect();
+
+        let mut seen = HashSet::new();
+        let mut candidates = Vec::new();
+        for (i, order) in orders.iter_mut().enumerate() {
+            if !seen.insert(order.id) {
+                results[i] = Some(Err(DispatchError::InvalidOperation(format!(
+                    "order {} appears more than once in the batch",
+                    order.id
+                ))));
+                continue;
+            }
+            if !self.orders.read().unwrap().contains(&order.id) {
+                self.price_order(order);
+            }
+            match self.ensure_dispatchable(order) {
+                Ok(()) => candidates.push(i),
+                Err(e) => {
+                    self.publish_failure(order, &e, false);
+                    results[i] = Some(Err(e));
+                }
+            }
+        }
+
+        let rules = self.rules.read().unwrap();
+        let pool = self.driver_pool.read().unwrap();
+        let batch: Vec<&Order> = candidates.iter().map(|&i| &orders[i]).collect();
+        let assignment = self
+            .batch_strategy
+            .assign(&batch, pool.get_available_drivers(), &rules);
+        let assignment: Vec<Result<Uuid, DispatchError>> = batch
+            .iter()
+            .zip(assignment)
+            .map(|(order, driver_id)| {
+                driver_id.ok_or_else(|| unassigned_error(order, &pool, &rules))
+            })
+            .collect();
         drop(pool);
+        drop(rules);
 
+        for (&i, assigned) in candidates.iter().zip(assignment) {
+            results[i] = Some(match assigned {
+                Ok(driver_id) => self.assign_to_driver(
+                    &mut orders[i],
+                    driver_id,
+                    self.batch_strategy.name(),
+                    None,
+                ),
+                Err(error) => {
+                    let queued = enqueue;
+                    if queued {
+                        self.enqueue_pending(&orders[i]);
+                    }
+                    self.publish_failure(&orders[i], &error, queued);
+                    Err(error)
+                }
+            });
+        }
+
+        results
+            .into_iter()
+            .map(|r| r.expect("every order has a result"))
+            .collect()
+    }
+
+    /// 将订单分配给指定配送员并记录到订单库
+    fn assign_to_driver(
+        &self,
+        order: &mut Order,
+        driver_id: Uuid,
+        strategy_used: &str,
+        score_breakdown: Option<ScoreBreakdown>,
+    ) -> Result<DispatchResult, DispatchError> {
         let mut pool = self.driver_pool.write().unwrap();
         let driver = pool
             .get_driver_mut(&driver_id)
             .ok_or(DispatchError::DriverNotFound(driver_id))?;
 
-        if !driver.assign_order() {
+        let timestamp = self.clock.now();
+        let mut updated = driver.clone();
+        if !updated.assign_order(order.id) {
             return Err(DispatchError::CapacityExceeded);
         }
+        updated.plan_order_with(self.travel_model.as_ref(), order, timestamp);
 
-        order.status = OrderStatus::Dispatched;
-        order.assigned_to = Some(driver_id);
+        let estimate = estimate_delivery(self.travel_model.as_ref(), order, &updated, timestamp);
+        // 派单前按订单类型估算的配送时长，派单后按实际车型重新估算
+        let mut assigned = order.clone();
+        assigned.estimated_duration_minutes = estimate.trip_minutes;
+        assigned.status = OrderStatus::Dispatched;
+        assigned.assigned_to = Some(driver_id);
 
-        Ok(DispatchResult {
+        // 选出配送员后只持有过读锁，同一订单的并发派单可能已先完成，
+        // 在订单库写锁下重新检查，检查、写日志和修改内存之间不释放锁
+        let mut orders = self.orders.write().unwrap();
+        if let Some(current) = orders.get_order(&order.id)
+            && current.status != OrderStatus::Pending
+        {
+            return Err(DispatchError::InvalidOperation(format!(
+                "order {} cannot be dispatched in status {:?}",
+                order.id, current.status
+            )));
+        }
+
+        // 配送员和订单写成一条记录，写入失败时两者都不变
+        self.persist(|| StateChange::AssignOrder {
+            driver: updated.clone(),
+            order: Box::new(assigned.clone()),
+        })?;
+        let driver_name = updated.name.clone();
+        let vehicle_type = updated.vehicle_type.display_name().to_string();
+        let distance_to_pickup_km = self
+            .travel_model
+            .distance_km(updated.current_location, order.pickup_location);
+        *driver = updated;
+        *order = assigned;
+        orders.add_order(order.clone());
+        drop(orders);
+        drop(pool);
+
+        self.dequeue(&order.id);
+
+        let result = DispatchResult {
             order_id: order.id,
             driver_id,
             driver_name,
             vehicle_type,
             distance_to_pickup_km,
-            estimated_arrival_minutes,
-            strategy_used: self.strategy.name().to_string(),
-            timestamp: chrono::Utc::now().timestamp(),
-        })
+            estimated_arrival_minutes: estimate.pickup_eta_minutes,
+            estimated_delivery_at: estimate.delivered_at,
+            predicted_lateness_minutes: order.lateness_minutes(estimate.delivered_at),
+            strategy_used: strategy_used.to_string(),
+            timestamp,
+            score_breakdown,
+        };
+        let _ = self.persist(|| StateChange::RecordDispatch {
+            result: result.clone(),
+        });
+        self.events.publish(DispatchEvent::OrderDispatched {
+            result: result.clone(),
+        });
+        Ok(result)
+    }
+
+    fn enqueue_pending(&self, order: &Order) {
+        let mut orders = self.orders.write().unwrap();
+        if !orders.contains(&order.id) {
+            orders.add_order(order.clone());
+            let _ = self.persist(|| StateChange::UpsertOrder {
+                order: order.clone(),
+            });
+        }
+        drop(orders);
+
+        let now = self.clock.now();
+        let mut pending = self.pending.write().unwrap();
+        if !pending.contains(&order.id) {
+            pending.enqueue(order, now);
+            let _ = self.persist(|| StateChange::Enqueue {
+                entry: pending
+                    .get(&order.id)
+                    .cloned()
+                    .expect("order just enqueued"),
+            });
+        }
+    }
+
+    fn dequeue(&self, order_id: &Uuid) {
+        let mut pending = self.pending.write().unwrap();
+        if pending.remove(order_id).is_some() {
+            let _ = self.persist(|| StateChange::Dequeue {
+                order_id: *order_id,
+            });
+        }
+    }
+
+    /// 按优先级重试等待队列中的订单，返回本次派单成功的结果
+    pub fn retry_pending(&self) -> Vec<DispatchResult> {
+        let now = self.clock.now();
+        let entries = self.pending.read().unwrap().prioritized(now);
+
+        let mut dispatched = Vec::new();
+        for entry in entries {
+            if self
+                .driver_pool
+                .read()
+                .unwrap()
+                .get_available_drivers()
+                .is_empty()
+            {
+                break;
+            }
+
+            match self.get_order(&entry.order_id) {
+                Ok(mut order) if order.status == OrderStatus::Pending => {
+                    if let Ok(result) = self.try_dispatch(&mut order) {
+                        dispatched.push(result);
+                    }
+                }
+                // 订单已被取消或在队列外派出
+                _ => self.dequeue(&entry.order_id),
+            }
+        }
+        dispatched
+    }
+
+    /// 班次已结束的配送员转为离线，手上的订单仍可继续完成
+    fn end_expired_shifts(&self, now: i64) -> Vec<Uuid> {
+        let mut pool = self.driver_pool.write().unwrap();
+        let expired: Vec<Uuid> = pool
+            .get_all_drivers()
+            .into_iter()
+            .filter(|driver| {
+                driver.status != DriverStatus::Offline
+                    && driver
+                        .shift
+                        .as_ref()
+                        .is_some_and(|shift| shift.has_ended(now))
+            })
+            .map(|driver| driver.id)
+            .collect();
+        for driver_id in &expired {
+            if let Some(driver) = pool.get_driver_mut(driver_id) {
+                driver.status = DriverStatus::Offline;
+                let _ = self.persist(|| StateChange::UpsertDriver {
+                    driver: driver.clone(),
+                });
+            }
+        }
+        drop(pool);
+
+        for driver_id in &expired {
+            self.events.publish(DispatchEvent::DriverShiftEnded {
+                driver_id: *driver_id,
+                timestamp: now,
+            });
+        }
+        expired
+    }
+
+    /// 周期性调用：班次结束的配送员下线，取消等待超时的订单，并重试其余等待中的订单
+    pub fn tick(&self) -> TickReport {
+        let now = self.clock.now();
+        let off_shift = self.end_expired_shifts(now);
+        let mut pending = self.pending.write().unwrap();
+        let expired = pending.take_expired(now, self.pending_timeout_secs);
+        for entry in &expired {
+            let _ = self.persist(|| StateChange::Dequeue {
+                order_id: entry.order_id,
+            });
+        }
+        drop(pending);
+
+        let cancelled = expired
+            .into_iter()
+            .filter(|entry| {
+                self.transition_order(&entry.order_id, OrderStatus::Cancelled)
+                    .is_ok()
+            })
+            .map(|entry| entry.order_id)
+            .collect();
+
+        let dispatched = self.retry_pending();
+        TickReport {
+            dispatched,
+            cancelled,
+            still_pending: self.pending.read().unwrap().len(),
+            off_shift,
+        }
+    }
+
+    /// 等待队列中的订单，按优先级从高到低
+    pub fn pending_orders(&self) -> Vec<PendingEntry> {
+        let now = self.clock.now();
+        self.pending.read().unwrap().prioritized(now)
+    }
+
+    pub fn is_pending(&self, order_id: &Uuid) -> bool {
+        self.pending.read().unwrap().contains(order_id)
+    }
+
+    fn ensure_in_service_area(&self, order: &Order) -> Result<(), DispatchError> {
+        match &self.zones {
+            Some(zones)
+                if zones.zone_at(order.pickup_location).is_none()
+                    || zones.zone_at(order.delivery_location).is_none() =>
+            {
+                Err(DispatchError::OutsideServiceArea(order.id))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 各服务区的配送员和订单统计，未配置服务区时为空
+    pub fn zone_stats(&self) -> Vec<ZoneStats> {
+        let Some(zones) = &self.zones else {
+            return Vec::new();
+        };
+        let mut stats: Vec<ZoneStats> = zones
+            .zones()
+            .iter()
+            .map(|zone| ZoneStats {
+                zone_id: zone.id.clone(),
+                name: zone.name.clone(),
+                ..ZoneStats::default()
+            })
+            .collect();
+        let positions: HashMap<&str, usize> = zones
+            .zones()
+            .iter()
+            .enumerate()
+            .map(|(i, zone)| (zone.id.as_str(), i))
+            .collect();
+
+        let pool = self.driver_pool.read().unwrap();
+        for driver in pool.get_all_drivers() {
+            if let Some(&i) = zones.driver_zone(driver).and_then(|id| positions.get(id)) {
+                stats[i].drivers += 1;
+                if driver.is_available() {
+                    stats[i].available_drivers += 1;
+                }
+            }
+        }
+        drop(pool);
+
+        let pending = self.pending.read().unwrap();
+        let pending_ids: HashSet<Uuid> = pending.entries().map(|entry| entry.order_id).collect();
+        drop(pending);
+
+        let orders = self.orders.read().unwrap();
+        for order in orders.get_all_orders() {
+            let Some(&i) = zones
+                .zone_at(order.pickup_location)
+                .and_then(|zone| positions.get(zone.id.as_str()))
+            else {
+                continue;
+            };
+            match order.status {
+                OrderStatus::Pending if pending_ids.contains(&order.id) => {
+                    stats[i].pending_orders += 1
+                }
+                OrderStatus::Dispatched | OrderStatus::PickedUp | OrderStatus::InTransit => {
+                    stats[i].active_orders += 1
+                }
+                OrderStatus::Delivered => stats[i].delivered_orders += 1,
+                OrderStatus::Cancelled => stats[i].cancelled_orders += 1,
+                OrderStatus::Pending => {}
+            }
+        }
+        stats
+    }
+
+    fn ensure_dispatchable(&self, order: &Order) -> Result<(), DispatchError> {
+        self.ensure_in_service_area(order)?;
+        let orders = self.orders.read().unwrap();
+        let status = orders
+            .get_order(&order.id)
+            .map(|o| &o.status)
+            .unwrap_or(&order.status);
+
+        if *status != OrderStatus::Pending {
+            return Err(DispatchError::InvalidOperation(format!(
+                "order {} cannot be dispatched in status {:?}",
+                order.id, status
+            )));
+        }
+        Ok(())
+    }
+
+    /// 配送员已取货
+    pub fn mark_picked_up(&self, order_id: &Uuid) -> Result<(), DispatchError> {
+        self.transition_with_driver(order_id, OrderStatus::PickedUp, None, |_, driver| {
+            driver.complete_stop(order_id, StopKind::Pickup);
+            true
+        })?;
+        Ok(())
+    }
+
+    /// 订单配送中
+    pub fn mark_in_transit(&self, order_id: &Uuid) -> Result<(), DispatchError> {
+        self.transition_order(order_id, OrderStatus::InTransit)?;
+        Ok(())
+    }
+
+    /// 订单已送达，释放配送员负载
+    pub fn mark_delivered(&self, order_id: &Uuid) -> Result<(), DispatchError> {
+        let now = self.clock.now();
+        self.transition_with_driver(order_id, OrderStatus::Delivered, None, |_, driver| {
+            driver.release_order_at(order_id, now)
+        })?;
+        self.retry_pending();
+        Ok(())
     }
 
-    pub fn release_order(&self, driver_id: &Uuid) -> Result<(), DispatchError> {
+    /// 取消订单，已派单的订单会释放配送员负载
+    pub fn cancel_order(&self, order_id: &Uuid) -> Result<(), DispatchError> {
+        let now = self.clock.now();
+        let (_, previous) = self.transition_with_driver(
+            order_id,
+            OrderStatus::Cancelled,
+            None,
+            |previous, driver| {
+                *previous == OrderStatus::Dispatched && driver.release_order_at(order_id, now)
+            },
+        )?;
+        self.dequeue(order_id);
+        if previous == OrderStatus::Dispatched {
+            self.retry_pending();
+        }
+        Ok(())
+    }
+
+    /// 执行状态转换并修改承接订单的配送员（默认为 `order.assigned_to`）。
+    /// 两项检查都通过后把订单和配送员写成一条记录再修改内存，写入失败时两者都不变。
+    /// `update` 收到转换前的状态，返回 false 表示配送员无需修改
+    fn transition_with_driver(
+        &self,
+        order_id: &Uuid,
+        next: OrderStatus,
+        driver_id: Option<Uuid>,
+        update: impl FnOnce(&OrderStatus, &mut Driver) -> bool,
+    ) -> Result<(Order, OrderStatus), DispatchError> {
         let mut pool = self.driver_pool.write().unwrap();
+        let mut orders = self.orders.write().unwrap();
+        let order = orders
+            .get_order_mut(order_id)
+            .ok_or(DispatchError::OrderNotFound(*order_id))?;
+        if !order.status.can_transition_to(&next) {
+            return Err(DispatchError::InvalidOperation(format!(
+                "order {} cannot transition from {:?} to {:?}",
+                order_id, order.status, next
+            )));
+        }
+        let mut updated = order.clone();
+        let previous = std::mem::replace(&mut updated.status, next.clone());
+
+        // 配送员可能已被移除，此时只转换订单状态
+        let driver_id = driver_id.or(order.assigned_to);
+        let driver = driver_id.and_then(|id| pool.get_driver_mut(&id));
+        let updated_driver = driver.as_ref().and_then(|driver| {
+            let mut updated_driver = (*driver).clone();
+            update(&previous, &mut updated_driver).then_some(updated_driver)
+        });
+
+        match &updated_driver {
+            Some(updated_driver) => self.persist(|| StateChange::AssignOrder {
+                driver: updated_driver.clone(),
+                order: Box::new(updated.clone()),
+            })?,
+            None => self.persist(|| StateChange::UpsertOrder {
+                order: updated.clone(),
+            })?,
+        }
+        *order = updated.clone();
+        let released = match (driver, updated_driver) {
+            (Some(driver), Some(updated_driver)) => {
+                let released = driver.has_order(order_id) && !updated_driver.has_order(order_id);
+                *driver = updated_driver;
+                released.then_some(driver.id)
+            }
+            _ => None,
+        };
+        drop(orders);
+        drop(pool);
+
+        self.publish_status_change(order_id, previous.clone(), next);
+        if let Some(driver_id) = released {
+            self.publish_release(driver_id, *order_id);
+        }
+        Ok((updated, previous))
+    }
+
+    /// 执行状态转换，返回转换后的订单及转换前的状态
+    fn transition_order(
+        &self,
+        order_id: &Uuid,
+        next: OrderStatus,
+    ) -> Result<(Order, OrderStatus), DispatchError> {
+        let mut orders = self.orders.write().unwrap();
+        let order = orders
+            .get_order_mut(order_id)
+            .ok_or(DispatchError::OrderNotFound(*order_id))?;
+
+        if !order.status.can_transition_to(&next) {
+            return Err(DispatchError::InvalidOperation(format!(
+                "order {} cannot transition from {:?} to {:?}",
+                order_id, order.status, next
+            )));
+        }
+
+        let mut updated = order.clone();
+        let previous = std::mem::replace(&mut updated.status, next.clone());
+        self.persist(|| StateChange::UpsertOrder {
+            order: updated.clone(),
+        })?;
+        *order = updated.clone();
+        drop(orders);
+
+        self.publish_status_change(order_id, previous.clone(), next);
+        Ok((updated, previous))
+    }
+
+    fn publish_status_change(&self, order_id: &Uuid, from: OrderStatus, to: OrderStatus) {
+        self.events.publish(DispatchEvent::OrderStatusChanged {
+            order_id: *order_id,
+            from,
+            to,
+            timestamp: self.clock.now(),
+        });
+    }
+
+    fn publish_release(&self, driver_id: Uuid, order_id: Uuid) {
+        self.events.publish(DispatchEvent::OrderReleased {
+            driver_id,
+            order_id,
+            timestamp: self.clock.now(),
+        });
+    }
+
+    pub fn get_order(&self, order_id: &Uuid) -> Result<Order, DispatchError> {
+        let orders = self.orders.read().unwrap();
+        orders
+            .get_order(order_id)
+            .cloned()
+            .ok_or(DispatchError::OrderNotFound(*order_id))
+    }
+
+    pub fn get_all_orders(&self) -> Vec<Order> {
+        let orders = self.orders.read().unwrap();
+        orders.get_all_orders().into_iter().cloned().collect()
+    }
+
+    /// 配送员完成订单：解除订单与配送员的绑定，
+    /// 引擎中记录的已取货订单会被标记为已送达，尚未取货的订单不能释放
+    pub fn release_order(&self, driver_id: &Uuid, order_id: &Uuid) -> Result<(), DispatchError> {
+        let pool = self.driver_pool.read().unwrap();
         let driver = pool
-            .get_driver_mut(driver_id)
+            .get_driver(driver_id)
             .ok_or(DispatchError::DriverNotFound(*driver_id))?;
+        if !driver.has_order(order_id) {
+            return Err(DispatchError::InvalidOperation(format!(
+                "order {} is not assigned to driver {}",
+                order_id, driver_id
+            )));
+        }
+        drop(pool);
+
+        let status = self
+            .orders
+            .read()
+            .unwrap()
+            .get_order(order_id)
+            .map(|order| order.status.clone());
+        match status {
+            Some(OrderStatus::Dispatched) => {
+                return Err(DispatchError::InvalidOperation(format!(
+                    "order {} has not been picked up",
+                    order_id
+                )));
+            }
+            Some(OrderStatus::PickedUp | OrderStatus::InTransit) => {
+                let now = self.clock.now();
+                self.transition_with_driver(
+                    order_id,
+                    OrderStatus::Delivered,
+                    Some(*driver_id),
+                    |_, driver| driver.release_order_at(order_id, now),
+                )?;
+                self.retry_pending();
+                return Ok(());
+            }
+            // 不在订单库中或已结束的订单只解除绑定
+            _ => {}
+        }
+
+        let mut pool = self.driver_pool.write().unwrap();
+        let mut released = false;
+        if let Some(driver) = pool.get_driver_mut(driver_id) {
+            let mut updated = driver.clone();
+            if updated.release_order_at(order_id, self.clock.now()) {
+                self.commit_driver(driver, updated)?;
+                released = true;
+            }
+        }
+        drop(pool);
 
-        driver.release_order();
+        if released {
+            self.publish_release(*driver_id, *order_id);
+        }
+        self.retry_pending();
         Ok(())
     }
 
@@ -129,17 +1390,124 @@ impl DispatchEngine {
         pool.get_all_drivers().into_iter().cloned().collect()
     }
 
+    /// 更新配送员位置。位置上报很频繁，这里不重试等待队列，留给 `tick` 处理
     pub fn update_driver_location(
         &self,
         driver_id: &Uuid,
         new_location: Location,
     ) -> Result<(), DispatchError> {
+        // 持有配送员池的写锁写日志，与并发的派单记录按修改内存的顺序落盘
         let mut pool = self.driver_pool.write().unwrap();
-        let driver = pool
-            .get_driver_mut(driver_id)
-            .ok_or(DispatchError::DriverNotFound(*driver_id))?;
+        if pool.get_driver(driver_id).is_none() {
+            return Err(DispatchError::DriverNotFound(*driver_id));
+        }
+        self.persist(|| StateChange::MoveDriver {
+            driver_id: *driver_id,
+            location: new_location,
+        })?;
+        pool.update_location(driver_id, new_location);
+        drop(pool);
 
-        driver.current_location = new_location;
+        self.events.publish(DispatchEvent::DriverMoved {
+            driver_id: *driver_id,
+            location: new_location,
+            timestamp: self.clock.now(),
+        });
         Ok(())
     }
 }
+
+/// 可用配送员候选集。规则限制了接单距离时，通过空间索引只取范围内的配送员
+fn candidate_drivers<'a>(
+    order: &Order,
+    pool: &'a DriverPool,
+    rules: &[Box<dyn DispatchRule>],
+) -> Vec<&'a Driver> {
+    let max_distance_km = rules
+        .iter()
+        .filter_map(|rule| rule.max_pickup_distance_km())
+        .reduce(f64::min);
+
+    let mut drivers = match max_distance_km {
+        Some(radius_km) => pool.get_available_drivers_within(order.pickup_location, radius_km),
+        None => pool.get_available_drivers(),
+    };
+    // 固定候选顺序，得分相同时每次都选中同一个配送员
+    drivers.sort_by_key(|d| d.id);
+    drivers
+}
+
+/// 批量匹配没有分到配送员的原因：所有配送员都被规则拒绝时与逐单派单一样返回
+/// `NoEligibleDriver`，合格的配送员都分给了批次中的其他订单时返回 `NoDriverAvailable`
+fn unassigned_error(
+    order: &Order,
+    pool: &DriverPool,
+    rules: &[Box<dyn DispatchRule>],
+) -> DispatchError {
+    if pool.get_available_drivers().is_empty() {
+        return DispatchError::NoDriverAvailable;
+    }
+    let (rejections, eligible) = evaluate_drivers(order, pool, rules);
+    if eligible.is_empty() {
+        DispatchError::NoEligibleDriver(RejectionSummary::from_rejections(
+            pool.get_all_drivers().len(),
+            &rejections,
+        ))
+    } else {
+        DispatchError::NoDriverAvailable
+    }
+}
+
+/// 将配送员划分为被拒绝的和合格的两组
+fn evaluate_drivers<'a>(
+    order: &Order,
+    pool: &'a DriverPool,
+    rules: &[Box<dyn DispatchRule>],
+) -> (Vec<DriverRejection>, Vec<&'a Driver>) {
+    let mut drivers = pool.get_all_drivers();
+    drivers.sort_by_key(|d| d.id);
+
+    let mut rejections = Vec::new();
+    let mut eligible = Vec::new();
+    for driver in drivers {
+        let mut rejected_by = Vec::new();
+        if !driver.is_available() {
+            rejected_by.push(UNAVAILABLE_REASON.to_string());
+        }
+        rejected_by.extend(
+            rules
+                .iter()
+                .filter(|rule| !rule.evaluate(order, driver))
+                .map(|rule| rule.name().to_string()),
+        );
+
+        if rejected_by.is_empty() {
+            eligible.push(driver);
+        } else {
+            rejections.push(DriverRejection {
+                driver_id: driver.id,
+                driver_name: driver.name.clone(),
+                rejected_by,
+            });
+        }
+    }
+    (rejections, eligible)
+}
+
+/// 通过了所有规则但不在策略排序结果中的配送员，拒绝原因为策略名称
+fn strategy_rejections(
+    strategy: &dyn DispatchStrategy,
+    eligible: &[&Driver],
+    ranked: &[&Driver],
+) -> Vec<DriverRejection> {
+    let ranked: HashSet<Uuid> = ranked.iter().map(|driver| driver.id).collect();
+    eligible
+        .iter()
+        .filter(|driver| !ranked.contains(&driver.id))
+        .map(|driver| DriverRejection {
+            driver_id: driver.id,
+            driver_name: driver.name.clone(),
+            rejected_by: vec![strategy.name().to_string()],
+        })
+        .collect()
+}
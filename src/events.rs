@@ -0,0 +1,137 @@
+use super::*;
+use std::sync::{Arc, RwLock};
+use tokio::sync::broadcast;
+
+/// 事件通道默认缓冲区大小，消费过慢的接收端会丢失最旧的事件
+pub const DEFAULT_EVENT_CAPACITY: usize = 1024;
+
+/// 引擎对外发布的事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum DispatchEvent {
+    OrderSubmitted {
+        order_id: Uuid,
+        order_type: OrderType,
+        timestamp: i64,
+    },
+    OrderDispatched {
+        result: DispatchResult,
+    },
+    /// 派单失败，`queued` 表示订单是否已进入等待队列
+    DispatchFailed {
+        order_id: Uuid,
+        reason: String,
+        queued: bool,
+        timestamp: i64,
+    },
+    DriverAdded {
+        driver_id: Uuid,
+        driver_name: String,
+        vehicle_type: VehicleType,
+        timestamp: i64,
+    },
+    DriverRemoved {
+        driver_id: Uuid,
+        timestamp: i64,
+    },
+    DriverMoved {
+        driver_id: Uuid,
+        location: Location,
+        timestamp: i64,
+    },
+    /// 订单状态变化（取货、配送、送达、取消），派单成功见 `OrderDispatched`
+    OrderStatusChanged {
+        order_id: Uuid,
+        from: OrderStatus,
+        to: OrderStatus,
+        timestamp: i64,
+    },
+    /// 订单从配送员身上释放（送达、取消或手动释放）
+    OrderReleased {
+        driver_id: Uuid,
+        order_id: Uuid,
+        timestamp: i64,
+    },
+    StrategyChanged {
+        strategy: String,
+        timestamp: i64,
+    },
+    /// 配送员班次结束，已转为离线
+    DriverShiftEnded {
+        driver_id: Uuid,
+        timestamp: i64,
+    },
+}
+
+impl DispatchEvent {
+    /// 事件类型名称，与序列化后的 `type` 字段一致
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DispatchEvent::OrderSubmitted { .. } => "OrderSubmitted",
+            DispatchEvent::OrderDispatched { .. } => "OrderDispatched",
+            DispatchEvent::DispatchFailed { .. } => "DispatchFailed",
+            DispatchEvent::DriverAdded { .. } => "DriverAdded",
+            DispatchEvent::DriverRemoved { .. } => "DriverRemoved",
+            DispatchEvent::DriverMoved { .. } => "DriverMoved",
+            DispatchEvent::OrderStatusChanged { .. } => "OrderStatusChanged",
+            DispatchEvent::OrderReleased { .. } => "OrderReleased",
+            DispatchEvent::StrategyChanged { .. } => "StrategyChanged",
+            DispatchEvent::DriverShiftEnded { .. } => "DriverShiftEnded",
+        }
+    }
+}
+
+/// 事件回调，在发布事件的线程上同步调用
+pub trait EventSubscriber: Send + Sync {
+    fn on_event(&self, event: &DispatchEvent);
+}
+
+impl<F> EventSubscriber for F
+where
+    F: Fn(&DispatchEvent) + Send + Sync,
+{
+    fn on_event(&self, event: &DispatchEvent) {
+        self(event)
+    }
+}
+
+/// 事件总线：同步通知回调订阅者，同时广播给异步接收端
+pub struct EventBus {
+    subscribers: RwLock<Vec<Arc<dyn EventSubscriber>>>,
+    sender: broadcast::Sender<DispatchEvent>,
+}
+
+impl EventBus {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self {
+            subscribers: RwLock::new(Vec::new()),
+            sender,
+        }
+    }
+
+    pub fn subscribe(&self, subscriber: Arc<dyn EventSubscriber>) {
+        self.subscribers.write().unwrap().push(subscriber);
+    }
+
+    /// 创建新的广播接收端，只能收到创建之后发布的事件
+    pub fn receiver(&self) -> broadcast::Receiver<DispatchEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn publish(&self, event: DispatchEvent) {
+        // 复制订阅者列表，回调中可以继续订阅而不会死锁
+        let subscribers = self.subscribers.read().unwrap().clone();
+        for subscriber in subscribers {
+            subscriber.on_event(&event);
+        }
+        // 没有接收端时发送失败，直接忽略
+        let _ = self.sender.send(event);
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_EVENT_CAPACITY)
+    }
+}
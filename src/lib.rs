@@ -2,19 +2,82 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
 
+pub mod api;
+pub mod batch;
+pub mod benchmark;
+pub mod clock;
+pub mod config;
 pub mod drivers;
 pub mod engine;
+pub mod events;
 pub mod location;
 pub mod orders;
+pub mod pricing;
+pub mod queue;
+pub mod replay;
+pub mod routes;
+pub mod routing;
 pub mod rules;
+pub mod simulation;
+pub mod spatial;
+pub mod sqlite_store;
+pub mod store;
 pub mod strategies;
+pub mod travel;
+pub mod zones;
 
-pub use drivers::{Driver, DriverPool, DriverStatus, VehicleType};
-pub use engine::{DispatchEngine, DispatchError, DispatchResult};
+pub use api::{
+    AddDriverRequest, DispatchRequest, ReleaseRequest, RequestError, UpdateLocationRequest,
+    parse_order_type, parse_vehicle_type,
+};
+pub use batch::{BatchDispatchStrategy, MinCostBatchStrategy, solve_assignment};
+pub use benchmark::{
+    Benchmark, BenchmarkConfig, BenchmarkError, BenchmarkReport, BenchmarkRow, BenchmarkVariant,
+};
+pub use clock::{
+    Clock, FixedClock, IdGenerator, RandomIdGenerator, SeededIdGenerator, SystemClock,
+};
+pub use config::{
+    ConfigError, DispatchConfig, OrderTypeConfig, PricingConfig, RuleConfig, StrategyConfig,
+    TravelModelConfig, VehicleConfig,
+};
+pub use drivers::{BreakPeriod, Driver, DriverPool, DriverStatus, Shift, ShiftError, VehicleType};
+pub use engine::{
+    DEFAULT_PENDING_TIMEOUT_SECS, DispatchEngine, DispatchError, DispatchExplanation,
+    DispatchResult, DriverRejection, RankedDriver, RejectionSummary, UNAVAILABLE_REASON,
+};
+pub use events::{DEFAULT_EVENT_CAPACITY, DispatchEvent, EventBus, EventSubscriber};
 pub use location::{Location, distance_km};
-pub use orders::{Order, OrderStatus, OrderType};
-pub use rules::{CapacityRule, DispatchRule, DistanceRule, RatingRule, VehicleTypeRule};
+pub use orders::{Order, OrderStatus, OrderStore, OrderType, OrderTypeProfile};
+pub use pricing::{FixedPricing, PriceBreakdown, PricingContext, PricingModel, SurgePricing};
+pub use queue::{PendingEntry, PendingQueue, TickReport};
+pub use replay::{Replay, ReplayError, ReplayOutcome, ReplayRecord, ReplayRequest};
+pub use routes::{
+    Insertion, Stop, StopKind, apply_insertion, best_insertion, route_distance_km, route_minutes,
+};
+pub use routing::{
+    GraphFile, GraphFileEdge, GraphFileNode, GraphTravelModel, MAX_SNAP_DISTANCE_KM, RoadGraph,
+    Route, RouteMetric, RoutingError,
+};
+pub use rules::{
+    CapacityRule, DeadlineRule, DispatchRule, DistanceRule, RatingRule, ShiftRule, VehicleTypeRule,
+    ZoneRule,
+};
+pub use simulation::{
+    FleetSpec, Scenario, ScheduledOrder, Simulation, SimulationConfig, SimulationError,
+    SimulationReport, ZoneDemand,
+};
+pub use spatial::GridIndex;
+pub use sqlite_store::SqliteStateStore;
+pub use store::{
+    DEFAULT_SNAPSHOT_INTERVAL, EngineSnapshot, FileStateStore, StateChange, StateStore, StoreError,
+};
 pub use strategies::{
     CapacityMatchStrategy, DispatchStrategy, LoadBalancedStrategy, NearestFirstStrategy,
-    RatingPriorityStrategy,
+    PoolingStrategy, RatingPriorityStrategy, ScoreBreakdown, ScoreWeights, WeightedScoreStrategy,
+};
+pub use travel::{
+    CongestionCurve, ConstantSpeedModel, DeliveryEstimate, RoadTravelModel, TravelTimeModel,
+    estimate_delivery,
 };
+pub use zones::{Polygon, Zone, ZoneError, ZoneMap, ZoneStats};
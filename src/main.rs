@@ -1,8 +1,72 @@
 use dispatcher::*;
-use std::io::{self, Write};
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
 use std::sync::Arc;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+const USAGE: &str = "\
+usage: dispatcher <command> [options]
+
+commands:
+  demo             start the interactive demo menu
+  dispatch         dispatch one order and print the DispatchResult as JSON
+                   [--config file] [--drivers drivers.json] [--request request.json|-]
+                   [--order-type type] [--pickup lat,lng] [--delivery lat,lng] [--within minutes]
+  simulate         run the benchmark simulation against one engine config, print the report as JSON
+                   [benchmark.toml] [--variant name | --config file] [--scenario file] [--save-scenario file]
+  replay           replay recorded requests and write one JSON outcome per line
+                   <records.jsonl> [--config file] [--output file] [--seed n]
+  bench            compare every variant of a benchmark config
+                   [benchmark.toml] [--scenario file] [--format json|csv|table]
+  validate-config  check a dispatcher config (or a benchmark config with --benchmark)
+                   <file> [--benchmark]
+  serve            start the HTTP server, remaining options are passed to the server binary
+
+exit codes: 0 success, 1 invalid arguments, config or I/O errors,
+            2 dispatch failed (replay: at least one record failed)";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let rest = args.get(1..).unwrap_or_default();
+    let result = match args.first().map(String::as_str) {
+        None => {
+            eprintln!("{}", USAGE);
+            Ok(ExitCode::FAILURE)
+        }
+        Some("demo") => interactive().map(|_| ExitCode::SUCCESS),
+        Some("dispatch") => dispatch(rest),
+        Some("simulate") => simulate(rest),
+        Some("replay") => replay(rest),
+        Some("bench") => bench(rest),
+        Some("validate-config") => validate_config(rest),
+        Some("serve") => serve(rest),
+        Some("help" | "--help" | "-h") => {
+            println!("{}", USAGE);
+            Ok(ExitCode::SUCCESS)
+        }
+        Some(other) => Err(format!("unknown command: {} (run dispatcher help)", other).into()),
+    };
+
+    // 错误以 JSON 输出到 stderr，派单失败使用单独的退出码
+    match result {
+        Ok(code) => code,
+        Err(e) => match e.downcast_ref::<DispatchError>() {
+            Some(error) => {
+                let kind = error.kind();
+                eprintln!(
+                    "{}",
+                    serde_json::json!({ "error": error.to_string(), "kind": kind })
+                );
+                ExitCode::from(2)
+            }
+            None => {
+                eprintln!("{}", serde_json::json!({ "error": e.to_string() }));
+                ExitCode::FAILURE
+            }
+        },
+    }
+}
+
+fn interactive() -> Result<(), Box<dyn std::error::Error>> {
     println!("╔════════════════════════════════════════╗");
     println!("       Dispatcher 配送分单引擎演示系统       ");
     println!("╚════════════════════════════════════════╝\n");
@@ -18,7 +82,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         io::stdout().flush()?;
 
         let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
+        // 标准输入结束时退出，避免无限打印菜单
+        if io::stdin().read_line(&mut input)? == 0 {
+            println!();
+            break;
+        }
         let choice = input.trim();
 
         match choice {
@@ -247,7 +315,9 @@ fn demo_strategy_comparison() -> Result<(), Box<dyn std::error::Error>> {
         for (name, vehicle, location, rating, load) in &test_drivers {
             let mut driver = Driver::new(name.to_string(), vehicle.clone(), *location);
             driver.rating = *rating;
-            driver.current_load = *load;
+            for _ in 0..*load {
+                driver.assign_order(uuid::Uuid::new_v4());
+            }
             engine.add_driver(driver);
         }
 
@@ -274,3 +344,325 @@ fn demo_strategy_comparison() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+/// 命令行选项的取值，缺少取值时报错
+fn flag_value<'a>(args: &mut std::slice::Iter<'a, String>, flag: &str) -> Result<&'a str, String> {
+    args.next()
+        .map(String::as_str)
+        .ok_or_else(|| format!("{} requires a value", flag))
+}
+
+/// 解析 `lat,lng` 格式的坐标
+fn parse_location(value: &str) -> Result<Location, String> {
+    let invalid = || format!("invalid location {} (expected lat,lng)", value);
+    let (lat, lng) = value.split_once(',').ok_or_else(invalid)?;
+    let lat = lat.trim().parse().map_err(|_| invalid())?;
+    let lng = lng.trim().parse().map_err(|_| invalid())?;
+    Ok(Location::new(lat, lng))
+}
+
+fn load_config(path: Option<&str>) -> Result<DispatchConfig, ConfigError> {
+    match path {
+        Some(path) => DispatchConfig::from_file(path),
+        None => Ok(DispatchConfig::default()),
+    }
+}
+
+/// 读取 JSON 文件，路径为 `-` 时读取标准输入
+fn read_json<T: serde::de::DeserializeOwned>(path: &str) -> Result<T, Box<dyn std::error::Error>> {
+    let content = if path == "-" {
+        let mut content = String::new();
+        io::stdin().read_to_string(&mut content)?;
+        content
+    } else {
+        std::fs::read_to_string(path)?
+    };
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// dispatcher dispatch [--config file] [--drivers drivers.json] [--request request.json|-]
+///     [--order-type type] [--pickup lat,lng] [--delivery lat,lng] [--within minutes]
+///
+/// 配送员列表和订单的格式与服务端的请求体一致，命令行选项覆盖请求文件中的字段
+fn dispatch(args: &[String]) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let mut config = None;
+    let mut drivers = None;
+    let mut request = None;
+    let mut order_type = None;
+    let mut pickup = None;
+    let mut delivery = None;
+    let mut within = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => config = Some(flag_value(&mut args, arg)?),
+            "--drivers" => drivers = Some(flag_value(&mut args, arg)?),
+            "--request" => request = Some(flag_value(&mut args, arg)?),
+            "--order-type" => order_type = Some(flag_value(&mut args, arg)?),
+            "--pickup" => pickup = Some(parse_location(flag_value(&mut args, arg)?)?),
+            "--delivery" => delivery = Some(parse_location(flag_value(&mut args, arg)?)?),
+            "--within" => within = Some(flag_value(&mut args, arg)?.parse()?),
+            other => return Err(format!("unknown option for dispatch: {}", other).into()),
+        }
+    }
+
+    let mut request = match request {
+        Some(path) => read_json::<DispatchRequest>(path)?,
+        None => {
+            let (Some(pickup), Some(delivery)) = (pickup, delivery) else {
+                return Err("dispatch requires --request or both --pickup and --delivery".into());
+            };
+            DispatchRequest {
+                order_type: "Food".to_string(),
+                pickup_lat: pickup.latitude,
+                pickup_lng: pickup.longitude,
+                delivery_lat: delivery.latitude,
+                delivery_lng: delivery.longitude,
+                deliver_within_minutes: None,
+            }
+        }
+    };
+    if let Some(order_type) = order_type {
+        request.order_type = order_type.to_string();
+    }
+    if let Some(pickup) = pickup {
+        request.pickup_lat = pickup.latitude;
+        request.pickup_lng = pickup.longitude;
+    }
+    if let Some(delivery) = delivery {
+        request.delivery_lat = delivery.latitude;
+        request.delivery_lng = delivery.longitude;
+    }
+    if within.is_some() {
+        request.deliver_within_minutes = within;
+    }
+
+    let engine = DispatchEngine::from_config(&load_config(config)?)?;
+    if let Some(path) = drivers {
+        for driver in read_json::<Vec<AddDriverRequest>>(path)? {
+            engine.add_driver(driver.to_driver()?);
+        }
+    }
+
+    let result = engine.dispatch(&mut request.to_order()?)?;
+    println!("{}", serde_json::to_string(&result)?);
+    Ok(ExitCode::SUCCESS)
+}
+
+/// dispatcher simulate [benchmark.toml] [--variant name | --config file]
+///     [--scenario recorded.json] [--save-scenario out.json]
+///
+/// 使用对比配置中的服务区和仿真参数，只运行一个引擎配置，未指定时使用默认配置
+fn simulate(args: &[String]) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let mut benchmark_path = "config/benchmark.toml";
+    let mut variant = None;
+    let mut config = None;
+    let mut scenario_path = None;
+    let mut save_scenario = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--variant" => variant = Some(flag_value(&mut args, arg)?),
+            "--config" => config = Some(flag_value(&mut args, arg)?),
+            "--scenario" => scenario_path = Some(flag_value(&mut args, arg)?),
+            "--save-scenario" => save_scenario = Some(flag_value(&mut args, arg)?),
+            other if other.starts_with('-') => {
+                return Err(format!("unknown option for simulate: {}", other).into());
+            }
+            _ => benchmark_path = arg,
+        }
+    }
+
+    let benchmark = BenchmarkConfig::from_file(benchmark_path)?;
+    let config = match variant {
+        Some(name) => benchmark
+            .variants
+            .iter()
+            .find(|v| v.name == name)
+            .map(|v| v.config.clone())
+            .ok_or_else(|| format!("unknown variant: {}", name))?,
+        None => load_config(config)?,
+    };
+
+    let zones = Arc::new(ZoneMap::from_geojson_file(&benchmark.zones)?);
+    let simulation = Simulation::new(benchmark.simulation.clone(), zones);
+    let scenario = match scenario_path {
+        Some(path) => Scenario::from_json_file(path)?,
+        None => simulation.scenario()?,
+    };
+    if let Some(path) = save_scenario {
+        scenario.save_json(path)?;
+    }
+
+    let engine = DispatchEngine::from_config_with_clock(&config, simulation.clock())?;
+    let report = simulation.replay(&engine, &scenario)?;
+    println!("{}", serde_json::to_string(&report)?);
+    Ok(ExitCode::SUCCESS)
+}
+
+/// dispatcher bench [benchmark.toml] [--scenario recorded.json] [--format json|csv|table]
+fn bench(args: &[String]) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let mut benchmark_path = "config/benchmark.toml";
+    let mut scenario_path = None;
+    let mut format = "json";
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--scenario" => scenario_path = Some(flag_value(&mut args, arg)?),
+            "--format" => format = flag_value(&mut args, arg)?,
+            other if other.starts_with('-') => {
+                return Err(format!("unknown option for bench: {}", other).into());
+            }
+            _ => benchmark_path = arg,
+        }
+    }
+
+    let benchmark = BenchmarkConfig::from_file(benchmark_path)?.build()?;
+    let scenario = match scenario_path {
+        Some(path) => Scenario::from_json_file(path)?,
+        None => benchmark.scenario()?,
+    };
+    let report = benchmark.run_scenario(&scenario)?;
+    match format {
+        "json" => println!("{}", serde_json::to_string(&report)?),
+        "csv" => print!("{}", report.to_csv()),
+        "table" => print!("{}", report.to_table()),
+        other => {
+            return Err(format!("unknown format: {} (expected json, csv or table)", other).into());
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// dispatcher validate-config <file> [--benchmark]
+///
+/// 除了字段校验，还会加载配置引用的服务区和路网文件。结果以 JSON 输出，配置无效时退出码为 1
+fn validate_config(args: &[String]) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let mut path = None;
+    let mut benchmark = false;
+    for arg in args {
+        match arg.as_str() {
+            "--benchmark" => benchmark = true,
+            other if other.starts_with('-') => {
+                return Err(format!("unknown option for validate-config: {}", other).into());
+            }
+            _ => path = Some(arg.as_str()),
+        }
+    }
+    let Some(path) = path else {
+        return Err("usage: dispatcher validate-config <file> [--benchmark]".into());
+    };
+
+    let checked: Result<(), Box<dyn std::error::Error>> = if benchmark {
+        BenchmarkConfig::from_file(path)
+            .and_then(|config| config.build())
+            .map(|_| ())
+            .map_err(Into::into)
+    } else {
+        DispatchConfig::from_file(path)
+            .and_then(|config| DispatchEngine::from_config(&config))
+            .map(|_| ())
+            .map_err(Into::into)
+    };
+    let kind = if benchmark { "benchmark" } else { "dispatch" };
+    match checked {
+        Ok(()) => {
+            println!(
+                "{}",
+                serde_json::json!({ "path": path, "kind": kind, "valid": true })
+            );
+            Ok(ExitCode::SUCCESS)
+        }
+        Err(e) => {
+            println!(
+                "{}",
+                serde_json::json!({ "path": path, "kind": kind, "valid": false, "error": e.to_string() })
+            );
+            Ok(ExitCode::FAILURE)
+        }
+    }
+}
+
+/// dispatcher serve [--config file] [--data-dir dir] [--store file|sqlite]
+///
+/// 启动与 dispatcher 位于同一目录的 server 程序，选项原样传给它
+fn serve(args: &[String]) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let server =
+        std::env::current_exe()?.with_file_name(format!("server{}", std::env::consts::EXE_SUFFIX));
+    if !server.exists() {
+        return Err(format!(
+            "server binary not found at {} (build it with cargo build --bin server)",
+            server.display()
+        )
+        .into());
+    }
+    let status = std::process::Command::new(server).args(args).status()?;
+    Ok(match status.code() {
+        Some(0) => ExitCode::SUCCESS,
+        Some(code) => ExitCode::from(u8::try_from(code).unwrap_or(1)),
+        None => ExitCode::FAILURE,
+    })
+}
+
+/// dispatcher replay <records.jsonl> [--config dispatcher.toml] [--output outcomes.jsonl] [--seed n]
+///
+/// 引擎时钟跟随记录的时间戳，未录制的 ID 按种子生成，同一份记录每次回放输出相同
+fn replay(args: &[String]) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let mut records = None;
+    let mut config = None;
+    let mut output = None;
+    let mut seed = 0;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => config = Some(flag_value(&mut args, arg)?),
+            "--output" => output = Some(flag_value(&mut args, arg)?),
+            "--seed" => seed = flag_value(&mut args, arg)?.parse()?,
+            other if other.starts_with('-') => {
+                return Err(format!("unknown option for replay: {}", other).into());
+            }
+            _ => records = Some(arg),
+        }
+    }
+    let Some(records) = records else {
+        return Err(
+            "usage: dispatcher replay <records.jsonl> [--config file] [--output file] [--seed n]"
+                .into(),
+        );
+    };
+
+    let config = load_config(config)?;
+    let clock = Arc::new(FixedClock::default());
+    let engine = DispatchEngine::from_config_with_clock(&config, clock.clone())?
+        .with_id_generator(Arc::new(SeededIdGenerator::new(seed)));
+    let replay = Replay::from_file(records)?.with_clock(clock);
+
+    let outcomes = match output {
+        Some(path) => {
+            replay.run_to_writer(&engine, io::BufWriter::new(std::fs::File::create(path)?))?
+        }
+        None => replay.run_to_writer(&engine, io::stdout().lock())?,
+    };
+    let dispatched: usize = outcomes
+        .iter()
+        .map(|outcome| outcome.dispatched.len())
+        .sum();
+    let failed = outcomes
+        .iter()
+        .filter(|outcome| outcome.error.is_some())
+        .count();
+    eprintln!(
+        "{}",
+        serde_json::json!({
+            "records": outcomes.len(),
+            "dispatched": dispatched,
+            "failed": failed,
+        })
+    );
+    // 与 dispatch 一致，有记录失败时退出码为 2
+    Ok(if failed > 0 {
+        ExitCode::from(2)
+    } else {
+        ExitCode::SUCCESS
+    })
+}
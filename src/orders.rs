@@ -1,6 +1,6 @@
 use super::*;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum OrderType {
     Food,        // 外卖
     Express,     // 快递
@@ -20,10 +20,41 @@ impl OrderType {
         }
     }
 
+    /// 默认的配送速度与计价表
+    pub fn default_profile(&self) -> OrderTypeProfile {
+        let (speed_kmh, base_price, per_km_price) = match self {
+            OrderType::Food => (20.0, 5.0, 2.0),
+            OrderType::Express => (30.0, 8.0, 3.0),
+            OrderType::FreshFood => (20.0, 6.0, 2.5),
+            OrderType::RideHailing => (40.0, 10.0, 2.8),
+            OrderType::Cargo => (35.0, 15.0, 5.0),
+        };
+
+        OrderTypeProfile {
+            speed_kmh,
+            base_price,
+            per_km_price,
+        }
+    }
+
+    /// 派单前估算配送时长使用的常用车型
+    pub fn typical_vehicle(&self) -> VehicleType {
+        match self {
+            OrderType::Food | OrderType::FreshFood => VehicleType::ElectricBike,
+            OrderType::Express | OrderType::RideHailing => VehicleType::Car,
+            OrderType::Cargo => VehicleType::Truck,
+        }
+    }
+
     pub fn requires_large_vehicle(&self) -> bool {
         matches!(self, OrderType::Express | OrderType::Cargo)
     }
 
+    /// 是否允许与其他订单拼单配送
+    pub fn is_poolable(&self) -> bool {
+        matches!(self, OrderType::Food | OrderType::Express)
+    }
+
     pub fn is_time_sensitive(&self) -> bool {
         matches!(
             self,
@@ -32,6 +63,27 @@ impl OrderType {
     }
 }
 
+/// 订单类型的配送速度与计价参数。
+/// `speed_kmh` 只用于未加入引擎的订单，引擎按行程时间模型估算配送时长
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OrderTypeProfile {
+    pub speed_kmh: f64,
+    pub base_price: f64,
+    pub per_km_price: f64,
+}
+
+impl OrderTypeProfile {
+    pub fn estimate_duration(&self, distance_km: f64) -> u32 {
+        let hours = distance_km / self.speed_kmh;
+        let minutes = (hours * 60.0) as u32;
+        minutes.max(5) // 至少 5 分钟
+    }
+
+    pub fn calculate_price(&self, distance_km: f64) -> f64 {
+        (self.base_price + distance_km * self.per_km_price).round()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum OrderStatus {
     Pending,
@@ -42,6 +94,27 @@ pub enum OrderStatus {
     Cancelled,
 }
 
+impl OrderStatus {
+    /// 订单状态机：Pending → Dispatched → PickedUp → InTransit → Delivered，
+    /// 取货前的订单可以取消
+    pub fn can_transition_to(&self, next: &OrderStatus) -> bool {
+        matches!(
+            (self, next),
+            (OrderStatus::Pending, OrderStatus::Dispatched)
+                | (OrderStatus::Pending, OrderStatus::Cancelled)
+                | (OrderStatus::Dispatched, OrderStatus::PickedUp)
+                | (OrderStatus::Dispatched, OrderStatus::Cancelled)
+                | (OrderStatus::PickedUp, OrderStatus::InTransit)
+                | (OrderStatus::PickedUp, OrderStatus::Delivered)
+                | (OrderStatus::InTransit, OrderStatus::Delivered)
+        )
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, OrderStatus::Delivered | OrderStatus::Cancelled)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Order {
     pub id: Uuid,
@@ -52,8 +125,15 @@ pub struct Order {
     pub distance_km: f64,
     pub estimated_duration_minutes: u32,
     pub price: f64,
+    /// 引擎计价时的价格组成
+    #[serde(default)]
+    pub price_breakdown: Option<PriceBreakdown>,
     pub created_at: i64,
     pub assigned_to: Option<Uuid>,
+    /// 商家出餐 / 货物可取的时间戳
+    pub pickup_ready_at: Option<i64>,
+    /// 承诺送达的截止时间戳
+    pub deliver_by: Option<i64>,
     pub metadata: HashMap<String, String>,
 }
 
@@ -62,13 +142,31 @@ impl Order {
         order_type: OrderType,
         pickup_location: Location,
         delivery_location: Location,
+    ) -> Self {
+        Self::new_with(
+            order_type,
+            pickup_location,
+            delivery_location,
+            &SystemClock,
+            &RandomIdGenerator,
+        )
+    }
+
+    /// 使用指定的时钟和 ID 生成器创建订单
+    pub fn new_with(
+        order_type: OrderType,
+        pickup_location: Location,
+        delivery_location: Location,
+        clock: &dyn Clock,
+        ids: &dyn IdGenerator,
     ) -> Self {
         let distance_km = distance_km(pickup_location, delivery_location);
-        let estimated_duration_minutes = Self::estimate_duration(distance_km, &order_type);
-        let price = Self::calculate_price(distance_km, &order_type);
+        let profile = order_type.default_profile();
+        let estimated_duration_minutes = profile.estimate_duration(distance_km);
+        let price = profile.calculate_price(distance_km);
 
         Self {
-            id: Uuid::new_v4(),
+            id: ids.next_id(),
             order_type,
             status: OrderStatus::Pending,
             pickup_location,
@@ -76,43 +174,47 @@ impl Order {
             distance_km,
             estimated_duration_minutes,
             price,
-            created_at: chrono::Utc::now().timestamp(),
+            price_breakdown: None,
+            created_at: clock.now(),
             assigned_to: None,
+            pickup_ready_at: None,
+            deliver_by: None,
             metadata: HashMap::new(),
         }
     }
 
-    fn estimate_duration(distance_km: f64, order_type: &OrderType) -> u32 {
-        let base_speed = match order_type {
-            OrderType::Food | OrderType::FreshFood => 20.0, // 20 km/h
-            OrderType::Express => 30.0,                     // 30 km/h
-            OrderType::RideHailing => 40.0,                 // 40 km/h
-            OrderType::Cargo => 35.0,                       // 35 km/h
-        };
+    /// 按指定的速度与计价表重新估算配送时长和价格
+    pub fn apply_profile(&mut self, profile: &OrderTypeProfile) {
+        self.estimated_duration_minutes = profile.estimate_duration(self.distance_km);
+        self.price = profile.calculate_price(self.distance_km);
+    }
 
-        let hours = distance_km / base_speed;
-        let minutes = (hours * 60.0) as u32;
-        minutes.max(5) // 至少 5 分钟
+    pub fn with_pickup_ready_at(mut self, pickup_ready_at: i64) -> Self {
+        self.pickup_ready_at = Some(pickup_ready_at);
+        self
     }
 
-    fn calculate_price(distance_km: f64, order_type: &OrderType) -> f64 {
-        let base_price = match order_type {
-            OrderType::Food => 5.0,
-            OrderType::Express => 8.0,
-            OrderType::FreshFood => 6.0,
-            OrderType::RideHailing => 10.0,
-            OrderType::Cargo => 15.0,
-        };
+    pub fn with_deadline(mut self, deliver_by: i64) -> Self {
+        self.deliver_by = Some(deliver_by);
+        self
+    }
 
-        let per_km_price = match order_type {
-            OrderType::Food => 2.0,
-            OrderType::Express => 3.0,
-            OrderType::FreshFood => 2.5,
-            OrderType::RideHailing => 2.8,
-            OrderType::Cargo => 5.0,
-        };
+    /// 实际取货时间：配送员到达取货点后还需等待出餐
+    pub fn pickup_at(&self, now: i64, pickup_eta_minutes: u32) -> i64 {
+        let arrival = now + pickup_eta_minutes as i64 * 60;
+        self.pickup_ready_at
+            .map_or(arrival, |ready| ready.max(arrival))
+    }
+
+    /// 预计送达时间：取货时间加上配送时长
+    pub fn predicted_delivery_at(&self, now: i64, pickup_eta_minutes: u32) -> i64 {
+        self.pickup_at(now, pickup_eta_minutes) + self.estimated_duration_minutes as i64 * 60
+    }
 
-        (base_price + distance_km * per_km_price).round()
+    /// 预计迟到分钟数，没有截止时间时为 None，准时为 0
+    pub fn lateness_minutes(&self, delivered_at: i64) -> Option<u32> {
+        self.deliver_by
+            .map(|deadline| ((delivered_at - deadline).max(0) as f64 / 60.0).ceil() as u32)
     }
 
     pub fn with_metadata(mut self, key: String, value: String) -> Self {
@@ -120,3 +222,49 @@ impl Order {
         self
     }
 }
+
+#[derive(Debug)]
+pub struct OrderStore {
+    orders: HashMap<Uuid, Order>,
+}
+
+impl OrderStore {
+    pub fn new() -> Self {
+        Self {
+            orders: HashMap::new(),
+        }
+    }
+
+    pub fn add_order(&mut self, order: Order) {
+        self.orders.insert(order.id, order);
+    }
+
+    pub fn contains(&self, order_id: &Uuid) -> bool {
+        self.orders.contains_key(order_id)
+    }
+
+    pub fn get_order(&self, order_id: &Uuid) -> Option<&Order> {
+        self.orders.get(order_id)
+    }
+
+    pub fn get_order_mut(&mut self, order_id: &Uuid) -> Option<&mut Order> {
+        self.orders.get_mut(order_id)
+    }
+
+    pub fn get_all_orders(&self) -> Vec<&Order> {
+        self.orders.values().collect()
+    }
+
+    pub fn get_orders_by_status(&self, status: &OrderStatus) -> Vec<&Order> {
+        self.orders
+            .values()
+            .filter(|o| &o.status == status)
+            .collect()
+    }
+}
+
+impl Default for OrderStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,210 @@
+use super::*;
+
+/// 计价时的供需与时间信息，由引擎在订单进入时采集
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PricingContext {
+    pub now: i64,
+    /// 取货点附近正在等待派单的订单数（不含当前订单）
+    pub pending_orders: usize,
+    /// 取货点附近的空闲配送员数
+    pub available_drivers: usize,
+}
+
+/// 订单价格的组成，`total = (base_price + distance_price) × 倍率 + 夜间附加费`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PriceBreakdown {
+    pub pricing_model: String,
+    pub base_price: f64,
+    pub distance_price: f64,
+    /// 供需倍率，1.0 表示不加价
+    pub surge_multiplier: f64,
+    /// 时段倍率
+    pub time_of_day_multiplier: f64,
+    pub night_surcharge: f64,
+    pub total: f64,
+}
+
+pub trait PricingModel: Send + Sync {
+    fn quote(
+        &self,
+        order: &Order,
+        profile: &OrderTypeProfile,
+        context: &PricingContext,
+    ) -> PriceBreakdown;
+
+    fn name(&self) -> &str;
+
+    /// 统计供需的范围（公里），返回 None 时引擎不采集供需信息
+    fn area_radius_km(&self) -> Option<f64> {
+        None
+    }
+}
+
+/// 固定计价：起步价加里程费，与 `OrderTypeProfile::calculate_price` 一致
+#[derive(Debug, Clone, Default)]
+pub struct FixedPricing;
+
+impl PricingModel for FixedPricing {
+    fn quote(
+        &self,
+        order: &Order,
+        profile: &OrderTypeProfile,
+        _context: &PricingContext,
+    ) -> PriceBreakdown {
+        PriceBreakdown {
+            pricing_model: self.name().to_string(),
+            base_price: profile.base_price,
+            distance_price: order.distance_km * profile.per_km_price,
+            surge_multiplier: 1.0,
+            time_of_day_multiplier: 1.0,
+            night_surcharge: 0.0,
+            total: profile.calculate_price(order.distance_km),
+        }
+    }
+
+    fn name(&self) -> &str {
+        "Fixed"
+    }
+}
+
+/// 动态计价：附近等待订单多于空闲配送员时加价，并叠加时段倍率和夜间附加费
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SurgePricing {
+    pub area_radius_km: f64,
+    /// 需求与运力之比每超出 1，倍率增加的幅度
+    pub sensitivity: f64,
+    pub max_multiplier: f64,
+    /// 当地时间相对 UTC 的小时偏移
+    pub utc_offset_hours: i32,
+    /// 0-23 点的时段倍率
+    pub hourly_multipliers: [f64; 24],
+    pub night_surcharge: f64,
+    /// 夜间时段 [night_start_hour, night_end_hour)，可以跨越零点
+    pub night_start_hour: u32,
+    pub night_end_hour: u32,
+}
+
+impl SurgePricing {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_area_radius_km(mut self, area_radius_km: f64) -> Self {
+        self.area_radius_km = area_radius_km;
+        self
+    }
+
+    pub fn with_sensitivity(mut self, sensitivity: f64) -> Self {
+        self.sensitivity = sensitivity;
+        self
+    }
+
+    pub fn with_max_multiplier(mut self, max_multiplier: f64) -> Self {
+        self.max_multiplier = max_multiplier;
+        self
+    }
+
+    pub fn with_hourly_multipliers(
+        mut self,
+        utc_offset_hours: i32,
+        hourly_multipliers: [f64; 24],
+    ) -> Self {
+        self.utc_offset_hours = utc_offset_hours;
+        self.hourly_multipliers = hourly_multipliers;
+        self
+    }
+
+    pub fn with_night_surcharge(mut self, amount: f64, start_hour: u32, end_hour: u32) -> Self {
+        self.night_surcharge = amount;
+        self.night_start_hour = start_hour;
+        self.night_end_hour = end_hour;
+        self
+    }
+
+    /// 供需倍率，保留一位小数
+    pub fn surge_multiplier(&self, context: &PricingContext) -> f64 {
+        // 当前订单也计入需求
+        let demand = (context.pending_orders + 1) as f64;
+        let ratio = if context.available_drivers == 0 {
+            f64::INFINITY
+        } else {
+            demand / context.available_drivers as f64
+        };
+        let multiplier = (1.0 + self.sensitivity * (ratio - 1.0)).clamp(1.0, self.max_multiplier);
+        (multiplier * 10.0).round() / 10.0
+    }
+
+    pub fn local_hour(&self, timestamp: i64) -> u32 {
+        let local_secs = timestamp + self.utc_offset_hours as i64 * 3600;
+        local_secs.div_euclid(3600).rem_euclid(24) as u32
+    }
+
+    pub fn is_night(&self, timestamp: i64) -> bool {
+        let hour = self.local_hour(timestamp);
+        if self.night_start_hour <= self.night_end_hour {
+            (self.night_start_hour..self.night_end_hour).contains(&hour)
+        } else {
+            hour >= self.night_start_hour || hour < self.night_end_hour
+        }
+    }
+}
+
+impl Default for SurgePricing {
+    /// 北京时间午晚高峰加价 20%，23 点到 6 点加收夜间费
+    fn default() -> Self {
+        Self {
+            area_radius_km: 3.0,
+            sensitivity: 0.5,
+            max_multiplier: 2.0,
+            utc_offset_hours: 8,
+            hourly_multipliers: std::array::from_fn(|hour| match hour {
+                11 | 12 | 17..=19 => 1.2,
+                _ => 1.0,
+            }),
+            night_surcharge: 5.0,
+            night_start_hour: 23,
+            night_end_hour: 6,
+        }
+    }
+}
+
+impl PricingModel for SurgePricing {
+    fn quote(
+        &self,
+        order: &Order,
+        profile: &OrderTypeProfile,
+        context: &PricingContext,
+    ) -> PriceBreakdown {
+        let base_price = profile.base_price;
+        let distance_price = order.distance_km * profile.per_km_price;
+        let surge_multiplier = self.surge_multiplier(context);
+        let time_of_day_multiplier = self.hourly_multipliers[self.local_hour(context.now) as usize];
+        let night_surcharge = if self.is_night(context.now) {
+            self.night_surcharge
+        } else {
+            0.0
+        };
+        let total = ((base_price + distance_price) * surge_multiplier * time_of_day_multiplier
+            + night_surcharge)
+            .round();
+
+        PriceBreakdown {
+            pricing_model: self.name().to_string(),
+            base_price,
+            distance_price,
+            surge_multiplier,
+            time_of_day_multiplier,
+            night_surcharge,
+            total,
+        }
+    }
+
+    fn name(&self) -> &str {
+        "Surge"
+    }
+
+    fn area_radius_km(&self) -> Option<f64> {
+        Some(self.area_radius_km)
+    }
+}
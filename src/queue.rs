@@ -0,0 +1,119 @@
+use super::*;
+
+/// 等待队列中的订单
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingEntry {
+    pub order_id: Uuid,
+    pub order_type: OrderType,
+    pub enqueued_at: i64,
+}
+
+impl PendingEntry {
+    /// 排队优先级：等待越久越优先，时效性强的订单额外加权
+    pub fn priority(&self, now: i64) -> f64 {
+        let waited_minutes = (now - self.enqueued_at).max(0) as f64 / 60.0;
+        waited_minutes + type_bonus_minutes(&self.order_type)
+    }
+}
+
+/// 订单类型带来的优先级加成，相当于多等待的分钟数
+fn type_bonus_minutes(order_type: &OrderType) -> f64 {
+    match order_type {
+        OrderType::RideHailing => 10.0,
+        OrderType::Food | OrderType::FreshFood => 5.0,
+        OrderType::Express | OrderType::Cargo => 0.0,
+    }
+}
+
+/// 派单失败订单的等待队列
+#[derive(Debug, Default)]
+pub struct PendingQueue {
+    entries: HashMap<Uuid, PendingEntry>,
+}
+
+impl PendingQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 加入队列，已在队列中的订单保留原入队时间
+    pub fn enqueue(&mut self, order: &Order, now: i64) {
+        self.entries
+            .entry(order.id)
+            .or_insert_with(|| PendingEntry {
+                order_id: order.id,
+                order_type: order.order_type.clone(),
+                enqueued_at: now,
+            });
+    }
+
+    /// 直接放入已有的队列项，用于从持久化状态恢复
+    pub fn insert(&mut self, entry: PendingEntry) {
+        self.entries.insert(entry.order_id, entry);
+    }
+
+    pub fn get(&self, order_id: &Uuid) -> Option<&PendingEntry> {
+        self.entries.get(order_id)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &PendingEntry> {
+        self.entries.values()
+    }
+
+    pub fn remove(&mut self, order_id: &Uuid) -> Option<PendingEntry> {
+        self.entries.remove(order_id)
+    }
+
+    pub fn contains(&self, order_id: &Uuid) -> bool {
+        self.entries.contains_key(order_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 按优先级从高到低返回队列中的订单
+    pub fn prioritized(&self, now: i64) -> Vec<PendingEntry> {
+        let mut entries: Vec<_> = self.entries.values().cloned().collect();
+        entries.sort_by(|a, b| {
+            b.priority(now)
+                .partial_cmp(&a.priority(now))
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.enqueued_at.cmp(&b.enqueued_at))
+                .then_with(|| a.order_id.cmp(&b.order_id))
+        });
+        entries
+    }
+
+    /// 移出等待时间达到 `timeout_secs` 的订单
+    pub fn take_expired(&mut self, now: i64, timeout_secs: i64) -> Vec<PendingEntry> {
+        let expired: Vec<Uuid> = self
+            .entries
+            .values()
+            .filter(|e| now - e.enqueued_at >= timeout_secs)
+            .map(|e| e.order_id)
+            .collect();
+
+        let mut taken: Vec<_> = expired
+            .iter()
+            .filter_map(|id| self.entries.remove(id))
+            .collect();
+        taken.sort_by_key(|e| (e.enqueued_at, e.order_id));
+        taken
+    }
+}
+
+/// 一次 `tick` 的处理结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TickReport {
+    pub dispatched: Vec<DispatchResult>,
+    pub cancelled: Vec<Uuid>,
+    pub still_pending: usize,
+    /// 班次结束、被转为离线的配送员
+    #[serde(default)]
+    pub off_shift: Vec<Uuid>,
+}
@@ -0,0 +1,234 @@
+use super::*;
+use std::io::{BufRead, Write};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReplayError {
+    #[error("Failed to read or write records: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid record on line {line}: {source}")]
+    Parse {
+        line: usize,
+        source: serde_json::Error,
+    },
+
+    #[error("Failed to write outcome: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// 录制的一条请求，`type` 字段区分请求类型，其余字段与服务端的请求结构一致
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReplayRequest {
+    /// `driver_id` 为线上分配的 ID，后续记录用它引用配送员
+    AddDriver {
+        #[serde(default)]
+        driver_id: Option<Uuid>,
+        #[serde(flatten)]
+        request: AddDriverRequest,
+    },
+    UpdateLocation(UpdateLocationRequest),
+    /// `order_id` 为线上分配的 ID，后续的释放记录用它引用订单
+    Dispatch {
+        #[serde(default)]
+        order_id: Option<Uuid>,
+        #[serde(flatten)]
+        request: DispatchRequest,
+    },
+    Release(ReleaseRequest),
+}
+
+impl ReplayRequest {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ReplayRequest::AddDriver { .. } => "add_driver",
+            ReplayRequest::UpdateLocation(_) => "update_location",
+            ReplayRequest::Dispatch { .. } => "dispatch",
+            ReplayRequest::Release(_) => "release",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReplayRecord {
+    pub timestamp: i64,
+    #[serde(flatten)]
+    pub request: ReplayRequest,
+}
+
+/// 一条记录的回放结果。`dispatched` 包含这条请求触发的所有派单，
+/// 例如新配送员上线后派出的等待中订单
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayOutcome {
+    /// 记录在输入文件中的行号，从 1 开始
+    pub line: usize,
+    pub timestamp: i64,
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order_id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub dispatched: Vec<DispatchResult>,
+}
+
+/// 按时间戳顺序回放录制的请求，时间戳相同的记录保持文件中的顺序
+#[derive(Debug, Clone, Default)]
+pub struct Replay {
+    records: Vec<(usize, ReplayRecord)>,
+    clock: Option<Arc<FixedClock>>,
+}
+
+impl Replay {
+    /// 读取 JSONL，跳过空行和 `#` 开头的注释行
+    pub fn from_reader(reader: impl BufRead) -> Result<Self, ReplayError> {
+        let mut records = Vec::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let record = serde_json::from_str(trimmed).map_err(|source| ReplayError::Parse {
+                line: i + 1,
+                source,
+            })?;
+            records.push((i + 1, record));
+        }
+        records.sort_by_key(|(_, record): &(usize, ReplayRecord)| record.timestamp);
+        Ok(Self {
+            records,
+            clock: None,
+        })
+    }
+
+    /// 回放每条记录前把 `clock` 拨到记录的时间戳。引擎使用同一个时钟时，
+    /// 事件时间、截止时间和班次判断都按录制时的时间进行
+    pub fn with_clock(mut self, clock: Arc<FixedClock>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    pub fn from_jsonl_str(content: &str) -> Result<Self, ReplayError> {
+        Self::from_reader(content.as_bytes())
+    }
+
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self, ReplayError> {
+        Self::from_reader(std::io::BufReader::new(std::fs::File::open(path)?))
+    }
+
+    pub fn records(&self) -> impl Iterator<Item = &ReplayRecord> {
+        self.records.iter().map(|(_, record)| record)
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn run(&self, engine: &DispatchEngine) -> Vec<ReplayOutcome> {
+        let dispatched = Arc::new(Mutex::new(Vec::new()));
+        let sink = dispatched.clone();
+        engine.subscribe(Arc::new(move |event: &DispatchEvent| {
+            if let DispatchEvent::OrderDispatched { result } = event {
+                sink.lock().unwrap().push(result.clone());
+            }
+        }));
+
+        self.records
+            .iter()
+            .map(|(line, record)| {
+                if let Some(clock) = &self.clock {
+                    clock.set(record.timestamp);
+                }
+                let (order_id, result) = apply(engine, &record.request);
+                ReplayOutcome {
+                    line: *line,
+                    timestamp: record.timestamp,
+                    kind: record.request.kind().to_string(),
+                    order_id,
+                    error: result.err().map(|e| e.to_string()),
+                    dispatched: std::mem::take(&mut *dispatched.lock().unwrap()),
+                }
+            })
+            .collect()
+    }
+
+    /// 回放并把结果逐行写成 JSONL
+    pub fn run_to_writer(
+        &self,
+        engine: &DispatchEngine,
+        mut writer: impl Write,
+    ) -> Result<Vec<ReplayOutcome>, ReplayError> {
+        let outcomes = self.run(engine);
+        for outcome in &outcomes {
+            serde_json::to_writer(&mut writer, outcome)?;
+            writeln!(writer)?;
+        }
+        writer.flush()?;
+        Ok(outcomes)
+    }
+}
+
+/// 执行一条请求，返回涉及的订单 ID。未录制 ID 的订单和配送员由引擎的 ID 生成器分配
+fn apply(
+    engine: &DispatchEngine,
+    request: &ReplayRequest,
+) -> (Option<Uuid>, Result<(), DispatchError>) {
+    match request {
+        ReplayRequest::AddDriver { driver_id, request } => {
+            let driver =
+                request.to_driver_with(engine.clock().as_ref(), engine.id_generator().as_ref());
+            let mut driver = match driver {
+                Ok(driver) => driver,
+                Err(e) => return (None, Err(invalid_request(e))),
+            };
+            if let Some(id) = driver_id {
+                driver.id = *id;
+            }
+            engine.add_driver(driver);
+            (None, Ok(()))
+        }
+        ReplayRequest::UpdateLocation(request) => {
+            let result = parse_id(&request.driver_id).and_then(|driver_id| {
+                let location = Location::new(request.latitude, request.longitude);
+                engine.update_driver_location(&driver_id, location)
+            });
+            (None, result)
+        }
+        ReplayRequest::Dispatch { order_id, request } => {
+            let order =
+                request.to_order_with(engine.clock().as_ref(), engine.id_generator().as_ref());
+            let mut order = match order {
+                Ok(order) => order,
+                Err(e) => return (*order_id, Err(invalid_request(e))),
+            };
+            if let Some(id) = order_id {
+                order.id = *id;
+            }
+            let result = engine.dispatch_or_enqueue(&mut order).map(|_| ());
+            (Some(order.id), result)
+        }
+        ReplayRequest::Release(request) => {
+            match (parse_id(&request.driver_id), parse_id(&request.order_id)) {
+                (Ok(driver_id), Ok(order_id)) => {
+                    (Some(order_id), engine.release_order(&driver_id, &order_id))
+                }
+                (Err(e), _) | (_, Err(e)) => (None, Err(e)),
+            }
+        }
+    }
+}
+
+fn parse_id(id: &str) -> Result<Uuid, DispatchError> {
+    Uuid::parse_str(id).map_err(|_| DispatchError::InvalidOperation(format!("invalid id {}", id)))
+}
+
+fn invalid_request(error: RequestError) -> DispatchError {
+    DispatchError::InvalidOperation(error.to_string())
+}
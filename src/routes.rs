@@ -0,0 +1,119 @@
+use super::*;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum StopKind {
+    Pickup,   // 取货
+    Delivery, // 送达
+}
+
+/// 配送员路线上的一个停靠点
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Stop {
+    pub order_id: Uuid,
+    pub kind: StopKind,
+    pub location: Location,
+}
+
+impl Stop {
+    pub fn pickup(order: &Order) -> Self {
+        Self {
+            order_id: order.id,
+            kind: StopKind::Pickup,
+            location: order.pickup_location,
+        }
+    }
+
+    pub fn delivery(order: &Order) -> Self {
+        Self {
+            order_id: order.id,
+            kind: StopKind::Delivery,
+            location: order.delivery_location,
+        }
+    }
+}
+
+/// 新订单插入路线的位置，`pickup_index` / `delivery_index` 为插入前路线中的下标
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Insertion {
+    pub pickup_index: usize,
+    pub delivery_index: usize,
+    /// 插入后路线增加的行驶时间（分钟）
+    pub added_minutes: f64,
+}
+
+/// 从起点依次经过所有停靠点的总里程
+pub fn route_distance_km(start: Location, stops: &[Stop]) -> f64 {
+    let mut total = 0.0;
+    let mut current = start;
+    for stop in stops {
+        total += distance_km(current, stop.location);
+        current = stop.location;
+    }
+    total
+}
+
+/// 按行程时间模型从起点依次经过所有停靠点所需的时间（分钟）
+pub fn route_minutes(
+    model: &dyn TravelTimeModel,
+    start: Location,
+    stops: &[Stop],
+    vehicle: &VehicleType,
+    departure_at: i64,
+) -> f64 {
+    let mut total = 0.0;
+    let mut current = start;
+    for stop in stops {
+        let at = departure_at + (total * 60.0) as i64;
+        total += model.travel_minutes(current, stop.location, vehicle, at);
+        current = stop.location;
+    }
+    total
+}
+
+/// 枚举所有满足"先取后送"的插入位置，按行程时间模型返回增加时间最少的方案
+pub fn best_insertion(
+    model: &dyn TravelTimeModel,
+    start: Location,
+    stops: &[Stop],
+    order: &Order,
+    vehicle: &VehicleType,
+    departure_at: i64,
+) -> Insertion {
+    let base_minutes = route_minutes(model, start, stops, vehicle, departure_at);
+    let mut best = Insertion {
+        pickup_index: stops.len(),
+        delivery_index: stops.len(),
+        added_minutes: f64::INFINITY,
+    };
+
+    for pickup_index in 0..=stops.len() {
+        for delivery_index in pickup_index..=stops.len() {
+            let candidate = Insertion {
+                pickup_index,
+                delivery_index,
+                added_minutes: 0.0,
+            };
+            let route = apply_insertion(stops, order, &candidate);
+            let added_minutes =
+                route_minutes(model, start, &route, vehicle, departure_at) - base_minutes;
+            if added_minutes < best.added_minutes {
+                best = Insertion {
+                    added_minutes,
+                    ..candidate
+                };
+            }
+        }
+    }
+    best
+}
+
+/// 按插入方案生成新路线
+pub fn apply_insertion(stops: &[Stop], order: &Order, insertion: &Insertion) -> Vec<Stop> {
+    let mut route = Vec::with_capacity(stops.len() + 2);
+    route.extend_from_slice(&stops[..insertion.pickup_index]);
+    route.push(Stop::pickup(order));
+    route.extend_from_slice(&stops[insertion.pickup_index..insertion.delivery_index]);
+    route.push(Stop::delivery(order));
+    route.extend_from_slice(&stops[insertion.delivery_index..]);
+    route
+}
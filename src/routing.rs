@@ -0,0 +1,589 @@
+use super::*;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+
+/// 每纬度对应的公里数
+const KM_PER_DEGREE: f64 = 111.32;
+
+/// 路网节点空间索引的网格边长（度），约 1.1 公里
+const NODE_CELL_DEG: f64 = 0.01;
+
+/// 位置与最近路网节点相距超过该距离时视为不在路网覆盖范围内
+pub const MAX_SNAP_DISTANCE_KM: f64 = 2.0;
+
+/// 从位置步行或骑行进出路网的速度
+const ACCESS_SPEED_KMH: f64 = 10.0;
+
+/// 路网文件中未标注速度的道路默认速度
+const DEFAULT_ROAD_SPEED_KMH: f64 = 30.0;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RoutingError {
+    #[error("Failed to read road graph: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid road graph file: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Invalid OSM extract: {0}")]
+    Osm(String),
+
+    #[error("Edge references unknown node {0}")]
+    UnknownNode(i64),
+
+    #[error("Edge {from} -> {to} must have a positive speed, got {speed_kmh}")]
+    InvalidSpeed { from: i64, to: i64, speed_kmh: f64 },
+
+    #[error("Unsupported road graph format: {0}")]
+    UnsupportedFormat(String),
+}
+
+/// 简化路网文件格式，可由 OSM 数据预处理得到
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GraphFile {
+    pub nodes: Vec<GraphFileNode>,
+    pub edges: Vec<GraphFileEdge>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphFileNode {
+    pub id: i64,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphFileEdge {
+    pub from: i64,
+    pub to: i64,
+    /// 缺省时按两端节点的直线距离计算
+    #[serde(default)]
+    pub length_km: Option<f64>,
+    #[serde(default)]
+    pub speed_kmh: Option<f64>,
+    #[serde(default)]
+    pub oneway: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    to: usize,
+    length_km: f64,
+    speed_kmh: f64,
+}
+
+/// 路径规划的优化目标
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RouteMetric {
+    /// 最短距离
+    Distance,
+    /// 最短时间，车速不超过给定上限
+    Time { max_speed_kmh: f64 },
+}
+
+/// 一次路径规划的结果，包含从起点进入路网和离开路网到终点的两段
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    pub distance_km: f64,
+    pub minutes: f64,
+    /// 途经的路网节点位置
+    pub path: Vec<Location>,
+}
+
+/// 有向路网图，支持按位置吸附到最近节点并用 A* 搜索路径
+#[derive(Debug, Clone, Default)]
+pub struct RoadGraph {
+    nodes: Vec<Location>,
+    edges: Vec<Vec<Edge>>,
+    max_speed_kmh: f64,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl RoadGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.iter().map(Vec::len).sum()
+    }
+
+    pub fn node_location(&self, node: usize) -> Option<Location> {
+        self.nodes.get(node).copied()
+    }
+
+    /// 添加节点，返回节点编号
+    pub fn add_node(&mut self, location: Location) -> usize {
+        let node = self.nodes.len();
+        self.nodes.push(location);
+        self.edges.push(Vec::new());
+        self.cells.entry(cell_of(location)).or_default().push(node);
+        node
+    }
+
+    /// 添加一条单向道路，长度取两端节点的直线距离
+    pub fn add_edge(&mut self, from: usize, to: usize, speed_kmh: f64) {
+        let length_km = distance_km(self.nodes[from], self.nodes[to]);
+        self.add_edge_with_length(from, to, length_km, speed_kmh);
+    }
+
+    /// 添加一条单向道路。长度不会短于直线距离，保证 A* 的启发函数不高估
+    pub fn add_edge_with_length(&mut self, from: usize, to: usize, length_km: f64, speed_kmh: f64) {
+        let length_km = length_km.max(distance_km(self.nodes[from], self.nodes[to]));
+        self.max_speed_kmh = self.max_speed_kmh.max(speed_kmh);
+        self.edges[from].push(Edge {
+            to,
+            length_km,
+            speed_kmh,
+        });
+    }
+
+    /// 添加一条双向道路
+    pub fn add_road(&mut self, a: usize, b: usize, speed_kmh: f64) {
+        self.add_edge(a, b, speed_kmh);
+        self.add_edge(b, a, speed_kmh);
+    }
+
+    pub fn from_graph_file(file: &GraphFile) -> Result<Self, RoutingError> {
+        let mut graph = Self::new();
+        let mut ids = HashMap::new();
+        for node in &file.nodes {
+            let index = graph.add_node(Location::new(node.latitude, node.longitude));
+            ids.insert(node.id, index);
+        }
+        for edge in &file.edges {
+            let from = *ids
+                .get(&edge.from)
+                .ok_or(RoutingError::UnknownNode(edge.from))?;
+            let to = *ids
+                .get(&edge.to)
+                .ok_or(RoutingError::UnknownNode(edge.to))?;
+            let length_km = edge
+                .length_km
+                .unwrap_or_else(|| distance_km(graph.nodes[from], graph.nodes[to]));
+            let speed_kmh = edge.speed_kmh.unwrap_or(DEFAULT_ROAD_SPEED_KMH);
+            // 速度为 0 或负数的边会让行程时间变成无穷大或负数
+            if speed_kmh <= 0.0 || speed_kmh.is_nan() {
+                return Err(RoutingError::InvalidSpeed {
+                    from: edge.from,
+                    to: edge.to,
+                    speed_kmh,
+                });
+            }
+            graph.add_edge_with_length(from, to, length_km, speed_kmh);
+            if !edge.oneway {
+                graph.add_edge_with_length(to, from, length_km, speed_kmh);
+            }
+        }
+        Ok(graph)
+    }
+
+    /// 读取简化路网 JSON 文件
+    pub fn from_json_file(path: impl AsRef<Path>) -> Result<Self, RoutingError> {
+        let file: GraphFile = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        Self::from_graph_file(&file)
+    }
+
+    /// 按扩展名读取路网文件：`.json` 为简化格式，`.osm` 为 OSM XML（需要 `osm` feature）
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, RoutingError> {
+        let path = path.as_ref();
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::from_json_file(path),
+            #[cfg(feature = "osm")]
+            Some("osm") => Self::from_osm_xml_file(path),
+            other => Err(RoutingError::UnsupportedFormat(
+                other.unwrap_or_default().to_string(),
+            )),
+        }
+    }
+
+    /// 距离位置最近的节点及其距离，超出 `MAX_SNAP_DISTANCE_KM` 时返回 None
+    pub fn nearest_node(&self, location: Location) -> Option<(usize, f64)> {
+        let (row, col) = cell_of(location);
+        // 经度方向的网格边长随纬度缩短，按较短的一边估算每圈的最小距离
+        let ring_km = NODE_CELL_DEG * KM_PER_DEGREE * location.latitude.to_radians().cos().abs();
+        let max_ring = (MAX_SNAP_DISTANCE_KM / ring_km.max(1e-6)).ceil() as i64 + 1;
+
+        let mut best: Option<(usize, f64)> = None;
+        for ring in 0..=max_ring {
+            if let Some((_, best_km)) = best
+                && (ring - 1) as f64 * ring_km > best_km
+            {
+                break;
+            }
+            for dr in -ring..=ring {
+                for dc in -ring..=ring {
+                    if dr.abs() != ring && dc.abs() != ring {
+                        continue;
+                    }
+                    let Some(nodes) = self.cells.get(&(row + dr, col + dc)) else {
+                        continue;
+                    };
+                    for &node in nodes {
+                        let km = distance_km(location, self.nodes[node]);
+                        if best.is_none_or(|(_, best_km)| km < best_km) {
+                            best = Some((node, km));
+                        }
+                    }
+                }
+            }
+        }
+        best.filter(|(_, km)| *km <= MAX_SNAP_DISTANCE_KM)
+    }
+
+    /// 两个位置之间的路径，任一端不在路网覆盖范围内或不连通时返回 None
+    pub fn route(&self, from: Location, to: Location, metric: RouteMetric) -> Option<Route> {
+        let (start, start_km) = self.nearest_node(from)?;
+        let (goal, goal_km) = self.nearest_node(to)?;
+        let access_minutes = (start_km + goal_km) / ACCESS_SPEED_KMH * 60.0;
+
+        if start == goal {
+            let km = distance_km(from, to);
+            return Some(Route {
+                distance_km: km,
+                minutes: km / ACCESS_SPEED_KMH * 60.0,
+                path: vec![self.nodes[start]],
+            });
+        }
+
+        let (nodes, distance_km, minutes) = self.astar(start, goal, metric)?;
+        Some(Route {
+            distance_km: distance_km + start_km + goal_km,
+            minutes: minutes + access_minutes,
+            path: nodes.into_iter().map(|node| self.nodes[node]).collect(),
+        })
+    }
+
+    /// 返回途经节点、路网内距离和时间
+    fn astar(
+        &self,
+        start: usize,
+        goal: usize,
+        metric: RouteMetric,
+    ) -> Option<(Vec<usize>, f64, f64)> {
+        let edge_speed = |edge: &Edge| match metric {
+            RouteMetric::Distance => edge.speed_kmh,
+            RouteMetric::Time { max_speed_kmh } => edge.speed_kmh.min(max_speed_kmh),
+        };
+        let edge_cost = |edge: &Edge| match metric {
+            RouteMetric::Distance => edge.length_km,
+            RouteMetric::Time { .. } => edge.length_km / edge_speed(edge) * 60.0,
+        };
+        let heuristic_speed = match metric {
+            RouteMetric::Distance => 0.0,
+            RouteMetric::Time { max_speed_kmh } => self.max_speed_kmh.min(max_speed_kmh),
+        };
+        let target = self.nodes[goal];
+        let heuristic = |node: usize| {
+            let km = distance_km(self.nodes[node], target);
+            match metric {
+                RouteMetric::Distance => km,
+                RouteMetric::Time { .. } => km / heuristic_speed * 60.0,
+            }
+        };
+
+        // 只记录访问过的节点，单次查询的开销与搜索范围而不是整张路网成正比
+        let mut best_cost: HashMap<usize, f64> = HashMap::new();
+        let mut came_from: HashMap<usize, (usize, Edge)> = HashMap::new();
+        let cost_of = |best_cost: &HashMap<usize, f64>, node: usize| {
+            best_cost.get(&node).copied().unwrap_or(f64::INFINITY)
+        };
+        let mut queue = BinaryHeap::new();
+        best_cost.insert(start, 0.0);
+        queue.push(QueueEntry {
+            estimate: heuristic(start),
+            node: start,
+        });
+
+        while let Some(QueueEntry { estimate, node }) = queue.pop() {
+            if node == goal {
+                break;
+            }
+            // 队列中可能存在同一节点的过期记录
+            let node_cost = cost_of(&best_cost, node);
+            if estimate - heuristic(node) > node_cost + 1e-9 {
+                continue;
+            }
+            for edge in &self.edges[node] {
+                let cost = node_cost + edge_cost(edge);
+                if cost < cost_of(&best_cost, edge.to) {
+                    best_cost.insert(edge.to, cost);
+                    came_from.insert(edge.to, (node, *edge));
+                    queue.push(QueueEntry {
+                        estimate: cost + heuristic(edge.to),
+                        node: edge.to,
+                    });
+                }
+            }
+        }
+
+        if cost_of(&best_cost, goal).is_infinite() {
+            return None;
+        }
+
+        let mut path = vec![goal];
+        let mut distance_km = 0.0;
+        let mut minutes = 0.0;
+        let mut current = goal;
+        while let Some(&(previous, edge)) = came_from.get(&current) {
+            distance_km += edge.length_km;
+            minutes += edge.length_km / edge_speed(&edge) * 60.0;
+            path.push(previous);
+            current = previous;
+        }
+        path.reverse();
+        Some((path, distance_km, minutes))
+    }
+}
+
+#[cfg(feature = "osm")]
+impl RoadGraph {
+    /// 读取 OSM XML 导出文件，只保留机动车可通行的道路。
+    /// PBF 文件可先用 `osmium cat extract.osm.pbf -o extract.osm` 转换
+    pub fn from_osm_xml_file(path: impl AsRef<Path>) -> Result<Self, RoutingError> {
+        use quick_xml::events::{BytesStart, Event};
+
+        fn attribute(element: &BytesStart, name: &[u8]) -> Result<Option<String>, RoutingError> {
+            for attr in element.attributes() {
+                let attr = attr.map_err(|e| RoutingError::Osm(e.to_string()))?;
+                if attr.key.as_ref() == name {
+                    let value = attr
+                        .unescape_value()
+                        .map_err(|e| RoutingError::Osm(e.to_string()))?;
+                    return Ok(Some(value.into_owned()));
+                }
+            }
+            Ok(None)
+        }
+
+        fn parse<T: std::str::FromStr>(value: Option<String>) -> Result<T, RoutingError> {
+            value
+                .as_deref()
+                .and_then(|v| v.parse().ok())
+                .ok_or_else(|| RoutingError::Osm(format!("invalid attribute {:?}", value)))
+        }
+
+        let mut reader =
+            quick_xml::Reader::from_file(path).map_err(|e| RoutingError::Osm(e.to_string()))?;
+        let mut buf = Vec::new();
+        let mut node_locations: HashMap<i64, Location> = HashMap::new();
+        let mut ways: Vec<OsmWay> = Vec::new();
+        let mut current_way: Option<OsmWay> = None;
+
+        loop {
+            let event = reader
+                .read_event_into(&mut buf)
+                .map_err(|e| RoutingError::Osm(e.to_string()))?;
+            match event {
+                Event::Start(ref e) | Event::Empty(ref e) => match e.name().as_ref() {
+                    b"node" => {
+                        let id = parse(attribute(e, b"id")?)?;
+                        let lat = parse(attribute(e, b"lat")?)?;
+                        let lon = parse(attribute(e, b"lon")?)?;
+                        node_locations.insert(id, Location::new(lat, lon));
+                    }
+                    b"way" => current_way = Some(OsmWay::default()),
+                    b"nd" => {
+                        if let Some(way) = current_way.as_mut() {
+                            way.nodes.push(parse(attribute(e, b"ref")?)?);
+                        }
+                    }
+                    b"tag" => {
+                        if let (Some(way), Some(key), Some(value)) = (
+                            current_way.as_mut(),
+                            attribute(e, b"k")?,
+                            attribute(e, b"v")?,
+                        ) {
+                            way.tags.insert(key, value);
+                        }
+                    }
+                    _ => {}
+                },
+                Event::End(ref e) if e.name().as_ref() == b"way" => {
+                    if let Some(way) = current_way.take() {
+                        ways.push(way);
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+            buf.clear();
+        }
+
+        let mut graph = Self::new();
+        let mut ids = HashMap::new();
+        for way in &ways {
+            let Some(speed_kmh) = way.speed_kmh() else {
+                continue;
+            };
+            let oneway = way.tags.get("oneway").map(String::as_str);
+            for pair in way.nodes.windows(2) {
+                let mut endpoints = [0; 2];
+                for (slot, id) in endpoints.iter_mut().zip(pair) {
+                    let location = *node_locations
+                        .get(id)
+                        .ok_or(RoutingError::UnknownNode(*id))?;
+                    *slot = *ids.entry(*id).or_insert_with(|| graph.add_node(location));
+                }
+                let [a, b] = endpoints;
+                match oneway {
+                    Some("yes" | "1" | "true") => graph.add_edge(a, b, speed_kmh),
+                    Some("-1" | "reverse") => graph.add_edge(b, a, speed_kmh),
+                    _ => graph.add_road(a, b, speed_kmh),
+                }
+            }
+        }
+        Ok(graph)
+    }
+}
+
+#[cfg(feature = "osm")]
+#[derive(Debug, Default)]
+struct OsmWay {
+    nodes: Vec<i64>,
+    tags: HashMap<String, String>,
+}
+
+#[cfg(feature = "osm")]
+impl OsmWay {
+    /// 机动车可通行道路的速度，优先使用 `maxspeed` 标签，不可通行的返回 None
+    fn speed_kmh(&self) -> Option<f64> {
+        let highway = self.tags.get("highway")?;
+        let default_speed = match highway.trim_end_matches("_link") {
+            "motorway" => 90.0,
+            "trunk" => 70.0,
+            "primary" => 50.0,
+            "secondary" => 40.0,
+            "tertiary" => 30.0,
+            "unclassified" | "residential" => 25.0,
+            "service" | "living_street" => 10.0,
+            _ => return None,
+        };
+        let max_speed = self
+            .tags
+            .get("maxspeed")
+            .and_then(|value| {
+                let number = value.split_whitespace().next()?.parse::<f64>().ok()?;
+                Some(if value.ends_with("mph") {
+                    number * 1.609
+                } else {
+                    number
+                })
+            })
+            .filter(|speed| *speed > 0.0);
+        Some(max_speed.unwrap_or(default_speed))
+    }
+}
+
+fn cell_of(location: Location) -> (i64, i64) {
+    (
+        (location.latitude / NODE_CELL_DEG).floor() as i64,
+        (location.longitude / NODE_CELL_DEG).floor() as i64,
+    )
+}
+
+/// A* 优先队列元素，按估计总代价从小到大出队
+#[derive(Debug, Clone, Copy)]
+struct QueueEntry {
+    estimate: f64,
+    node: usize,
+}
+
+impl PartialEq for QueueEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for QueueEntry {}
+
+impl PartialOrd for QueueEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueueEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimate
+            .total_cmp(&self.estimate)
+            .then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+/// 基于路网的行程时间模型：沿路网最短路径计算距离和时间，
+/// 位置不在路网覆盖范围内或不连通时退回 `RoadTravelModel`
+#[derive(Debug, Clone)]
+pub struct GraphTravelModel {
+    graph: Arc<RoadGraph>,
+    pub congestion: CongestionCurve,
+    fallback: RoadTravelModel,
+}
+
+impl GraphTravelModel {
+    pub fn new(graph: Arc<RoadGraph>) -> Self {
+        Self {
+            graph,
+            congestion: CongestionCurve::default(),
+            fallback: RoadTravelModel::default(),
+        }
+    }
+
+    pub fn with_congestion(mut self, congestion: CongestionCurve) -> Self {
+        self.fallback = self.fallback.with_congestion(congestion.clone());
+        self.congestion = congestion;
+        self
+    }
+
+    /// 设置路网无法覆盖时使用的模型，车型速度也从该模型读取
+    pub fn with_fallback(mut self, fallback: RoadTravelModel) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    pub fn graph(&self) -> &RoadGraph {
+        &self.graph
+    }
+}
+
+impl TravelTimeModel for GraphTravelModel {
+    fn distance_km(&self, from: Location, to: Location) -> f64 {
+        match self.graph.route(from, to, RouteMetric::Distance) {
+            Some(route) => route.distance_km,
+            None => self.fallback.distance_km(from, to),
+        }
+    }
+
+    fn travel_minutes(
+        &self,
+        from: Location,
+        to: Location,
+        vehicle: &VehicleType,
+        departure_at: i64,
+    ) -> f64 {
+        let metric = RouteMetric::Time {
+            max_speed_kmh: self.fallback.vehicle_speed_kmh(vehicle),
+        };
+        match self.graph.route(from, to, metric) {
+            Some(route) => {
+                route.minutes / self.congestion.vehicle_speed_factor(vehicle, departure_at)
+            }
+            None => self
+                .fallback
+                .travel_minutes(from, to, vehicle, departure_at),
+        }
+    }
+
+    fn name(&self) -> &str {
+        "Graph"
+    }
+}
@@ -1,8 +1,23 @@
 use super::*;
+use std::sync::Arc;
 
 pub trait DispatchRule: Send + Sync {
     fn evaluate(&self, order: &Order, driver: &Driver) -> bool;
     fn name(&self) -> &str;
+
+    /// 规则允许的最大接单距离（公里），引擎据此通过空间索引预先筛选配送员
+    fn max_pickup_distance_km(&self) -> Option<f64> {
+        None
+    }
+
+    /// 引擎在加入规则时传入自身的行程时间模型，依赖时间预估的规则应使用它
+    fn set_travel_model(&mut self, _model: Arc<dyn TravelTimeModel>) {}
+
+    /// 引擎在加入规则时传入自身的服务区，依赖服务区的规则应使用它
+    fn set_zones(&mut self, _zones: Arc<ZoneMap>) {}
+
+    /// 引擎在加入规则时传入自身的时钟，依赖当前时间的规则应使用它
+    fn set_clock(&mut self, _clock: Arc<dyn Clock>) {}
 }
 
 #[derive(Debug)]
@@ -18,25 +33,52 @@ impl DispatchRule for CapacityRule {
     }
 }
 
-#[derive(Debug)]
+/// 接单距离规则，默认按直线距离判断
 pub struct DistanceRule {
     pub max_distance_km: f64,
+    /// 为 true 时按引擎行程时间模型给出的道路距离判断
+    pub road_distance: bool,
+    travel_model: Arc<dyn TravelTimeModel>,
 }
 
 impl DistanceRule {
     pub fn new(max_distance_km: f64) -> Self {
-        Self { max_distance_km }
+        Self {
+            max_distance_km,
+            road_distance: false,
+            travel_model: Arc::new(RoadTravelModel::default()),
+        }
+    }
+
+    pub fn with_road_distance(mut self) -> Self {
+        self.road_distance = true;
+        self
     }
 }
 
 impl DispatchRule for DistanceRule {
     fn evaluate(&self, order: &Order, driver: &Driver) -> bool {
-        driver.distance_to(order.pickup_location) <= self.max_distance_km
+        let distance = if self.road_distance {
+            self.travel_model
+                .distance_km(driver.current_location, order.pickup_location)
+        } else {
+            driver.distance_to(order.pickup_location)
+        };
+        distance <= self.max_distance_km
     }
 
     fn name(&self) -> &str {
         "DistanceRule"
     }
+
+    /// 道路距离不短于直线距离，按直线距离预筛选不会漏掉配送员
+    fn max_pickup_distance_km(&self) -> Option<f64> {
+        Some(self.max_distance_km)
+    }
+
+    fn set_travel_model(&mut self, model: Arc<dyn TravelTimeModel>) {
+        self.travel_model = model;
+    }
 }
 
 #[derive(Debug)]
@@ -80,3 +122,150 @@ impl DispatchRule for RatingRule {
         "RatingRule"
     }
 }
+
+/// 服务区规则：只派给订单取货点所在服务区或相邻服务区的配送员。
+/// 没有配置服务区时不做限制
+pub struct ZoneRule {
+    zones: Arc<ZoneMap>,
+}
+
+impl ZoneRule {
+    pub fn new() -> Self {
+        Self {
+            zones: Arc::new(ZoneMap::default()),
+        }
+    }
+}
+
+impl Default for ZoneRule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DispatchRule for ZoneRule {
+    fn evaluate(&self, order: &Order, driver: &Driver) -> bool {
+        if self.zones.is_empty() {
+            return true;
+        }
+        match (
+            self.zones.zone_at(order.pickup_location),
+            self.zones.driver_zone(driver),
+        ) {
+            (Some(order_zone), Some(driver_zone)) => order_zone.covers_zone(driver_zone),
+            _ => false,
+        }
+    }
+
+    fn name(&self) -> &str {
+        "ZoneRule"
+    }
+
+    fn set_zones(&mut self, zones: Arc<ZoneMap>) {
+        self.zones = zones;
+    }
+}
+
+/// 时效规则：配送员到达取货点并完成配送后会超过订单截止时间的，不予派单
+pub struct DeadlineRule {
+    travel_model: Arc<dyn TravelTimeModel>,
+    clock: Arc<dyn Clock>,
+}
+
+impl DeadlineRule {
+    pub fn new() -> Self {
+        Self {
+            travel_model: Arc::new(RoadTravelModel::default()),
+            clock: Arc::new(SystemClock),
+        }
+    }
+}
+
+impl Default for DeadlineRule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DispatchRule for DeadlineRule {
+    fn evaluate(&self, order: &Order, driver: &Driver) -> bool {
+        match order.deliver_by {
+            Some(deadline) => {
+                let now = self.clock.now();
+                let estimate = estimate_delivery(self.travel_model.as_ref(), order, driver, now);
+                estimate.delivered_at <= deadline
+            }
+            None => true,
+        }
+    }
+
+    fn name(&self) -> &str {
+        "DeadlineRule"
+    }
+
+    fn set_travel_model(&mut self, model: Arc<dyn TravelTimeModel>) {
+        self.travel_model = model;
+    }
+
+    fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+}
+
+/// 班次规则：配送员须在班且不在休息中，预计完成时间不能晚于下一次休息或下班，
+/// 连续工作时长（从上班或上次休息结束算起）不能超过上限。没有班次的配送员不受限制
+pub struct ShiftRule {
+    pub max_continuous_work_minutes: u32,
+    travel_model: Arc<dyn TravelTimeModel>,
+    clock: Arc<dyn Clock>,
+}
+
+impl ShiftRule {
+    /// 默认连续工作不超过 4 小时
+    pub fn new() -> Self {
+        Self {
+            max_continuous_work_minutes: 240,
+            travel_model: Arc::new(RoadTravelModel::default()),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn with_max_continuous_work_minutes(mut self, minutes: u32) -> Self {
+        self.max_continuous_work_minutes = minutes;
+        self
+    }
+}
+
+impl Default for ShiftRule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DispatchRule for ShiftRule {
+    fn evaluate(&self, order: &Order, driver: &Driver) -> bool {
+        let Some(shift) = &driver.shift else {
+            return true;
+        };
+        let now = self.clock.now();
+        if !shift.is_working(now) {
+            return false;
+        }
+
+        let estimate = estimate_delivery(self.travel_model.as_ref(), order, driver, now);
+        let work_limit = shift.work_started_at(now) + self.max_continuous_work_minutes as i64 * 60;
+        estimate.delivered_at <= shift.work_ends_at(now) && estimate.delivered_at <= work_limit
+    }
+
+    fn name(&self) -> &str {
+        "ShiftRule"
+    }
+
+    fn set_travel_model(&mut self, model: Arc<dyn TravelTimeModel>) {
+        self.travel_model = model;
+    }
+
+    fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+}
@@ -1,20 +1,13 @@
-use actix_web::{web, App, HttpResponse, HttpServer, middleware};
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer, middleware};
 use actix_cors::Cors;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use dispatcher::*;
+use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
 
 // ============ API 请求/响应结构 ============
 
-#[derive(Debug, Serialize, Deserialize)]
-struct DispatchRequest {
-    order_type: String,
-    pickup_lat: f64,
-    pickup_lng: f64,
-    delivery_lat: f64,
-    delivery_lng: f64,
-}
-
 #[derive(Debug, Serialize, Deserialize)]
 struct DispatchResponse {
     success: bool,
@@ -30,8 +23,14 @@ struct DispatchResultData {
     vehicle_type: String,
     distance_to_pickup_km: f64,
     estimated_arrival_minutes: u32,
+    estimated_delivery_at: i64,
+    predicted_lateness_minutes: Option<u32>,
     order_distance_km: f64,
     order_price: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    price_breakdown: Option<PriceBreakdown>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    score_breakdown: Option<ScoreBreakdown>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -43,41 +42,82 @@ struct DriverInfo {
     current_location: LocationData,
     capacity: usize,
     current_load: usize,
+    assigned_orders: Vec<String>,
+    planned_stops: Vec<Stop>,
     rating: f64,
     total_orders: u32,
+    zone_id: Option<String>,
+    shift: Option<Shift>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct LocationData {
-    latitude: f64,
-    longitude: f64,
+struct OrderInfo {
+    id: String,
+    order_type: String,
+    status: String,
+    pickup_location: LocationData,
+    delivery_location: LocationData,
+    distance_km: f64,
+    estimated_duration_minutes: u32,
+    price: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    price_breakdown: Option<PriceBreakdown>,
+    created_at: i64,
+    assigned_to: Option<String>,
+}
+
+impl From<&Order> for OrderInfo {
+    fn from(o: &Order) -> Self {
+        Self {
+            id: o.id.to_string(),
+            order_type: o.order_type.display_name().to_string(),
+            status: format!("{:?}", o.status),
+            pickup_location: LocationData {
+                latitude: o.pickup_location.latitude,
+                longitude: o.pickup_location.longitude,
+            },
+            delivery_location: LocationData {
+                latitude: o.delivery_location.latitude,
+                longitude: o.delivery_location.longitude,
+            },
+            distance_km: o.distance_km,
+            estimated_duration_minutes: o.estimated_duration_minutes,
+            price: o.price,
+            price_breakdown: o.price_breakdown.clone(),
+            created_at: o.created_at,
+            assigned_to: o.assigned_to.map(|id| id.to_string()),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct AddDriverRequest {
-    name: String,
-    vehicle_type: String,
+struct LocationData {
     latitude: f64,
     longitude: f64,
 }
 
+/// 切换策略的请求体：只给策略名时沿用配置文件中该策略的参数，其余策略使用默认参数；
+/// 也可以直接提交与配置文件 `[strategy]` 格式相同的完整配置
 #[derive(Debug, Serialize, Deserialize)]
-struct UpdateLocationRequest {
-    driver_id: String,
-    latitude: f64,
-    longitude: f64,
+#[serde(untagged)]
+enum StrategyRequest {
+    Name { strategy: String },
+    Config(StrategyConfig),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct StrategyRequest {
-    strategy: String,
+struct RulesRequest {
+    /// 与配置文件中的 `[[rules]]` 格式相同
+    rules: Vec<RuleConfig>,
 }
 
 // ============ 应用状态 ============
 
+/// 引擎内部按组件加锁，请求之间不再共用一把锁
 struct AppState {
-    engine: Arc<Mutex<DispatchEngine>>,
-    current_strategy: Arc<Mutex<String>>,
+    engine: Arc<DispatchEngine>,
+    /// 配置文件中的策略，按名称切换回该策略时沿用其参数
+    configured_strategy: StrategyConfig,
 }
 
 // ============ API 路由处理器 ============
@@ -87,22 +127,18 @@ async fn dispatch_order(
     data: web::Data<AppState>,
     req: web::Json<DispatchRequest>,
 ) -> HttpResponse {
-    let order_type = match req.order_type.as_str() {
-        "外卖订单" | "Food" => OrderType::Food,
-        "快递订单" | "Express" => OrderType::Express,
-        "生鲜订单" | "FreshFood" => OrderType::FreshFood,
-        "打车订单" | "RideHailing" => OrderType::RideHailing,
-        "货运订单" | "Cargo" => OrderType::Cargo,
-        _ => OrderType::Food,
+    let mut order = match req.to_order() {
+        Ok(order) => order,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": format!("无效的请求: {}", e)
+            }))
+        }
     };
 
-    let pickup = Location::new(req.pickup_lat, req.pickup_lng);
-    let delivery = Location::new(req.delivery_lat, req.delivery_lng);
-
-    let mut order = Order::new(order_type, pickup, delivery);
-
-    let engine = data.engine.lock().unwrap();
-    match engine.dispatch(&mut order) {
+    let engine = &data.engine;
+    match engine.dispatch_or_enqueue(&mut order) {
         Ok(result) => {
             let response = DispatchResponse {
                 success: true,
@@ -114,16 +150,25 @@ async fn dispatch_order(
                     vehicle_type: result.vehicle_type.clone(),
                     distance_to_pickup_km: result.distance_to_pickup_km,
                     estimated_arrival_minutes: result.estimated_arrival_minutes,
+                    estimated_delivery_at: result.estimated_delivery_at,
+                    predicted_lateness_minutes: result.predicted_lateness_minutes,
                     order_distance_km: order.distance_km,
                     order_price: order.price,
+                    price_breakdown: order.price_breakdown.clone(),
+                    score_breakdown: result.score_breakdown.clone(),
                 }),
             };
             HttpResponse::Ok().json(response)
         }
         Err(e) => {
+            let message = if engine.is_pending(&order.id) {
+                format!("派单失败: {}，订单已进入等待队列", e)
+            } else {
+                format!("派单失败: {}", e)
+            };
             let response = DispatchResponse {
                 success: false,
-                message: format!("派单失败: {}", e),
+                message,
                 data: None,
             };
             HttpResponse::Ok().json(response)
@@ -131,9 +176,27 @@ async fn dispatch_order(
     }
 }
 
+/// 派单解释接口：返回每个配送员被哪些规则拒绝，以及合格配送员的排序，不会真正派单
+async fn explain_dispatch(
+    data: web::Data<AppState>,
+    req: web::Json<DispatchRequest>,
+) -> HttpResponse {
+    let order = match req.to_order() {
+        Ok(order) => order,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": format!("无效的请求: {}", e)
+            }))
+        }
+    };
+    let engine = &data.engine;
+    HttpResponse::Ok().json(engine.dispatch_explain(&order))
+}
+
 /// 获取所有配送员信息
 async fn get_drivers(data: web::Data<AppState>) -> HttpResponse {
-    let engine = data.engine.lock().unwrap();
+    let engine = &data.engine;
     let drivers = engine.get_all_drivers();
 
     let driver_infos: Vec<DriverInfo> = drivers
@@ -148,9 +211,13 @@ async fn get_drivers(data: web::Data<AppState>) -> HttpResponse {
                 longitude: d.current_location.longitude,
             },
             capacity: d.capacity,
-            current_load: d.current_load,
+            current_load: d.current_load(),
+            assigned_orders: d.assigned_orders.iter().map(|id| id.to_string()).collect(),
+            planned_stops: d.planned_stops.clone(),
             rating: d.rating,
             total_orders: d.total_orders,
+            zone_id: engine.driver_zone(d),
+            shift: d.shift.clone(),
         })
         .collect();
 
@@ -162,20 +229,18 @@ async fn add_driver(
     data: web::Data<AppState>,
     req: web::Json<AddDriverRequest>,
 ) -> HttpResponse {
-    let vehicle_type = match req.vehicle_type.as_str() {
-        "电动车" | "ElectricBike" => VehicleType::ElectricBike,
-        "摩托车" | "Motorcycle" => VehicleType::Motorcycle,
-        "汽车" | "Car" => VehicleType::Car,
-        "面包车" | "Van" => VehicleType::Van,
-        "卡车" | "Truck" => VehicleType::Truck,
-        _ => VehicleType::ElectricBike,
+    let driver = match req.to_driver() {
+        Ok(driver) => driver,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": format!("无效的请求: {}", e)
+            }))
+        }
     };
-
-    let location = Location::new(req.latitude, req.longitude);
-    let driver = Driver::new(req.name.clone(), vehicle_type, location);
     let driver_id = driver.id.to_string();
 
-    let engine = data.engine.lock().unwrap();
+    let engine = &data.engine;
     engine.add_driver(driver);
 
     HttpResponse::Ok().json(serde_json::json!({
@@ -201,7 +266,7 @@ async fn update_driver_location(
     };
 
     let new_location = Location::new(req.latitude, req.longitude);
-    let engine = data.engine.lock().unwrap();
+    let engine = &data.engine;
 
     match engine.update_driver_location(&driver_id, new_location) {
         Ok(_) => HttpResponse::Ok().json(serde_json::json!({
@@ -218,20 +283,24 @@ async fn update_driver_location(
 /// 释放订单（配送员完成订单）
 async fn release_order(
     data: web::Data<AppState>,
-    driver_id: web::Path<String>,
+    path: web::Path<(String, String)>,
 ) -> HttpResponse {
-    let driver_id = match uuid::Uuid::parse_str(&driver_id) {
-        Ok(id) => id,
-        Err(_) => {
+    let (driver_id, order_id) = path.into_inner();
+    let (driver_id, order_id) = match (
+        uuid::Uuid::parse_str(&driver_id),
+        uuid::Uuid::parse_str(&order_id),
+    ) {
+        (Ok(driver_id), Ok(order_id)) => (driver_id, order_id),
+        _ => {
             return HttpResponse::BadRequest().json(serde_json::json!({
                 "success": false,
-                "message": "无效的配送员ID"
+                "message": "无效的配送员ID或订单ID"
             }))
         }
     };
 
-    let engine = data.engine.lock().unwrap();
-    match engine.release_order(&driver_id) {
+    let engine = &data.engine;
+    match engine.release_order(&driver_id, &order_id) {
         Ok(_) => HttpResponse::Ok().json(serde_json::json!({
             "success": true,
             "message": "订单释放成功"
@@ -243,81 +312,336 @@ async fn release_order(
     }
 }
 
+/// 获取所有订单
+async fn get_orders(data: web::Data<AppState>) -> HttpResponse {
+    let engine = &data.engine;
+    let orders: Vec<OrderInfo> = engine.get_all_orders().iter().map(OrderInfo::from).collect();
+    HttpResponse::Ok().json(orders)
+}
+
+/// 获取等待队列中的订单
+async fn get_pending_orders(data: web::Data<AppState>) -> HttpResponse {
+    let engine = &data.engine;
+    HttpResponse::Ok().json(engine.pending_orders())
+}
+
+/// 查询订单
+async fn get_order(data: web::Data<AppState>, order_id: web::Path<String>) -> HttpResponse {
+    let order_id = match uuid::Uuid::parse_str(&order_id) {
+        Ok(id) => id,
+        Err(_) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": "无效的订单ID"
+            }))
+        }
+    };
+
+    let engine = &data.engine;
+    match engine.get_order(&order_id) {
+        Ok(order) => HttpResponse::Ok().json(OrderInfo::from(&order)),
+        Err(e) => HttpResponse::NotFound().json(serde_json::json!({
+            "success": false,
+            "message": format!("查询订单失败: {}", e)
+        })),
+    }
+}
+
+/// 更新订单状态（取货 / 配送中 / 送达 / 取消）
+async fn update_order_status(
+    data: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    let (order_id, action) = path.into_inner();
+    let order_id = match uuid::Uuid::parse_str(&order_id) {
+        Ok(id) => id,
+        Err(_) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": "无效的订单ID"
+            }))
+        }
+    };
+
+    let engine = &data.engine;
+    let result = match action.as_str() {
+        "pickup" => engine.mark_picked_up(&order_id),
+        "transit" => engine.mark_in_transit(&order_id),
+        "deliver" => engine.mark_delivered(&order_id),
+        "cancel" => engine.cancel_order(&order_id),
+        _ => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": format!("未知操作: {}", action)
+            }))
+        }
+    };
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "message": "订单状态更新成功"
+        })),
+        Err(e) => HttpResponse::Ok().json(serde_json::json!({
+            "success": false,
+            "message": format!("订单状态更新失败: {}", e)
+        })),
+    }
+}
+
 /// 切换派单策略
 async fn change_strategy(
     data: web::Data<AppState>,
     req: web::Json<StrategyRequest>,
 ) -> HttpResponse {
-    let new_strategy: Arc<dyn DispatchStrategy> = match req.strategy.as_str() {
-        "nearest_first" => Arc::new(NearestFirstStrategy::new()),
-        "load_balanced" => Arc::new(LoadBalancedStrategy::new()),
-        "rating_priority" => Arc::new(RatingPriorityStrategy::new()),
-        "capacity_match" => Arc::new(CapacityMatchStrategy::new()),
-        _ => Arc::new(NearestFirstStrategy::new()),
+    let config = match req.into_inner() {
+        StrategyRequest::Name { strategy } if strategy == data.configured_strategy.name() => {
+            data.configured_strategy.clone()
+        }
+        StrategyRequest::Name { strategy } => match StrategyConfig::from_name(&strategy) {
+            Some(config) => config,
+            None => {
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "success": false,
+                    "message": format!("未知策略: {}", strategy)
+                }))
+            }
+        },
+        StrategyRequest::Config(config) => config,
     };
-
-    // 创建新引擎（保留配送员）
-    let old_engine = data.engine.lock().unwrap();
-    let drivers = old_engine.get_all_drivers();
-    drop(old_engine);
-
-    let new_engine = DispatchEngine::new(new_strategy)
-        .with_rules(vec![
-            Box::new(CapacityRule),
-            Box::new(DistanceRule::new(10.0)),
-            Box::new(VehicleTypeRule),
-            Box::new(RatingRule::new(4.0)),
-        ]);
-
-    for driver in drivers {
-        new_engine.add_driver(driver);
+    if let Err(e) = config.validate() {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": e.to_string()
+        }));
     }
 
-    let mut engine = data.engine.lock().unwrap();
-    *engine = new_engine;
+    // 原地切换策略，配送员、订单和规则保持不变，引擎为新策略绑定自身的时钟和行程时间模型
+    data.engine.set_strategy(config.build());
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": format!("策略已切换至: {}", config.name()),
+        "strategy": data.engine.strategy_name()
+    }))
+}
+
+/// 替换派单规则
+async fn change_rules(data: web::Data<AppState>, req: web::Json<RulesRequest>) -> HttpResponse {
+    if let Err(e) = req.rules.iter().try_for_each(RuleConfig::validate) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": e.to_string()
+        }));
+    }
+    let rules = req.rules.iter().map(RuleConfig::build).collect();
 
-    let mut current_strategy = data.current_strategy.lock().unwrap();
-    *current_strategy = req.strategy.clone();
+    let engine = &data.engine;
+    engine.set_rules(rules);
 
     HttpResponse::Ok().json(serde_json::json!({
         "success": true,
-        "message": format!("策略已切换至: {}", req.strategy)
+        "message": "规则已更新",
+        "rules": engine.rule_names()
     }))
 }
 
-/// 健康检查
-async fn health_check() -> HttpResponse {
+/// 获取当前规则
+async fn get_rules(data: web::Data<AppState>) -> HttpResponse {
+    let engine = &data.engine;
+    HttpResponse::Ok().json(serde_json::json!({
+        "rules": engine.rule_names()
+    }))
+}
+
+/// 获取服务区及各服务区的配送员、订单统计
+async fn get_zones(data: web::Data<AppState>) -> HttpResponse {
+    let engine = &data.engine;
+    let zones = engine
+        .zones()
+        .map(|zones| zones.zones().to_vec())
+        .unwrap_or_default();
     HttpResponse::Ok().json(serde_json::json!({
-        "status": "ok",
-        "message": "Dispatcher API is running"
+        "zones": zones,
+        "stats": engine.zone_stats()
     }))
 }
 
+/// 健康检查
+async fn health_check(data: web::Data<AppState>) -> HttpResponse {
+    let engine = &data.engine;
+    match engine.persistence_error() {
+        None => HttpResponse::Ok().json(serde_json::json!({
+            "status": "ok",
+            "message": "Dispatcher API is running"
+        })),
+        Some(error) => HttpResponse::ServiceUnavailable().json(serde_json::json!({
+            "status": "degraded",
+            "message": format!("状态持久化失败: {}", error)
+        })),
+    }
+}
+
 /// 获取当前策略
 async fn get_current_strategy(data: web::Data<AppState>) -> HttpResponse {
-    let strategy = data.current_strategy.lock().unwrap();
     HttpResponse::Ok().json(serde_json::json!({
-        "strategy": *strategy
+        "strategy": data.engine.strategy_name()
     }))
 }
 
+// ============ 实时事件推送 ============
+
+/// WebSocket 实时事件推送，每条消息为一个 JSON 格式的引擎事件
+async fn events_ws(
+    req: HttpRequest,
+    body: web::Payload,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (response, mut session, mut messages) = actix_ws::handle(&req, body)?;
+    let mut events = data.engine.event_receiver();
+
+    actix_web::rt::spawn(async move {
+        loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Ok(event) => {
+                        let text = serde_json::to_string(&event).unwrap_or_default();
+                        if session.text(text).await.is_err() {
+                            break;
+                        }
+                    }
+                    // 客户端消费过慢，跳过丢失的事件继续推送
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                },
+                message = messages.next() => match message {
+                    Some(Ok(actix_ws::Message::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(Ok(actix_ws::Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                },
+            }
+        }
+        let _ = session.close(None).await;
+    });
+
+    Ok(response)
+}
+
+/// SSE 实时事件推送，供不支持 WebSocket 的客户端使用
+async fn events_sse(data: web::Data<AppState>) -> HttpResponse {
+    let events = data.engine.event_receiver();
+    let stream = futures_util::stream::unfold(events, |mut events| async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    let chunk = format!(
+                        "event: {}\ndata: {}\n\n",
+                        event.kind(),
+                        serde_json::to_string(&event).unwrap_or_default()
+                    );
+                    return Some((Ok::<_, actix_web::Error>(web::Bytes::from(chunk)), events));
+                }
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(stream)
+}
+
 // ============ 主函数 ============
 
+/// 读取 `--name value` 或 `--name=value` 形式的命令行参数
+fn arg_value(name: &str) -> Option<String> {
+    let flag = format!("--{}", name);
+    let prefix = format!("--{}=", name);
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == flag {
+            return args.next();
+        }
+        if let Some(value) = arg.strip_prefix(&prefix) {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+/// 未指定配置文件时使用的默认配置
+fn default_config() -> DispatchConfig {
+    DispatchConfig {
+        rules: vec![
+            RuleConfig::Capacity,
+            RuleConfig::Distance {
+                max_distance_km: 10.0,
+                road_distance: false,
+            },
+            RuleConfig::VehicleType,
+            RuleConfig::Rating { min_rating: 4.0 },
+            RuleConfig::Deadline,
+        ],
+        ..DispatchConfig::default()
+    }
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     println!("🚀 启动 Dispatcher Web Server...\n");
 
-    // 初始化引擎
-    let strategy = Arc::new(NearestFirstStrategy::new());
-    let engine = DispatchEngine::new(strategy)
-        .with_rules(vec![
-            Box::new(CapacityRule),
-            Box::new(DistanceRule::new(10.0)),
-            Box::new(VehicleTypeRule),
-            Box::new(RatingRule::new(4.0)),
-        ]);
-
-    // 添加初始配送员
+    // 初始化引擎，可通过 --config <path> 指定配置文件
+    let config = match arg_value("config") {
+        Some(path) => {
+            println!("⚙️  加载配置: {}", path);
+            DispatchConfig::from_file(&path)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?
+        }
+        None => default_config(),
+    };
+    let mut engine = DispatchEngine::from_config(&config)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+
+    // 可通过 --data-dir <dir> 持久化引擎状态，重启后自动恢复；
+    // --store sqlite 使用 <dir>/dispatcher.db，默认使用文件日志
+    if let Some(dir) = arg_value("data-dir") {
+        let backend = arg_value("store").unwrap_or_else(|| "file".to_string());
+        let store: Arc<dyn StateStore> = match backend.as_str() {
+            "file" => Arc::new(
+                FileStateStore::open(&dir).map_err(|e| std::io::Error::other(e.to_string()))?,
+            ),
+            "sqlite" => {
+                std::fs::create_dir_all(&dir)?;
+                Arc::new(
+                    SqliteStateStore::open(std::path::Path::new(&dir).join("dispatcher.db"))
+                        .map_err(|e| std::io::Error::other(e.to_string()))?,
+                )
+            }
+            other => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("unknown store backend: {} (expected file or sqlite)", other),
+                ))
+            }
+        };
+        engine = engine
+            .with_store(store)
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        println!("💾 数据目录: {} [{}] (恢复 {} 名配送员, {} 个订单)",
+            dir,
+            backend,
+            engine.get_all_drivers().len(),
+            engine.get_all_orders().len()
+        );
+    }
+
+    // 添加初始配送员，已从数据目录恢复配送员时跳过
     println!("📍 初始化配送员...");
     let initial_drivers = vec![
         Driver::new(
@@ -342,15 +666,35 @@ async fn main() -> std::io::Result<()> {
         ),
     ];
 
-    for driver in initial_drivers {
-        println!("  + {} ({})", driver.name, driver.vehicle_type.display_name());
-        engine.add_driver(driver);
+    if engine.get_all_drivers().is_empty() {
+        for driver in initial_drivers {
+            println!("  + {} ({})", driver.name, driver.vehicle_type.display_name());
+            engine.add_driver(driver);
+        }
     }
 
     // 创建应用状态
     let app_state = web::Data::new(AppState {
-        engine: Arc::new(Mutex::new(engine)),
-        current_strategy: Arc::new(Mutex::new("nearest_first".to_string())),
+        engine: Arc::new(engine),
+        configured_strategy: config.strategy.clone(),
+    });
+
+    // 定时重试等待队列中的订单
+    let tick_engine = app_state.engine.clone();
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(std::time::Duration::from_secs(5));
+        loop {
+            interval.tick().await;
+            let report = tick_engine.tick();
+            if !report.dispatched.is_empty() || !report.cancelled.is_empty() {
+                println!(
+                    "⏱  等待队列: 派出 {} 单, 超时取消 {} 单, 仍在等待 {} 单",
+                    report.dispatched.len(),
+                    report.cancelled.len(),
+                    report.still_pending
+                );
+            }
+        }
     });
 
     println!("\n✅ 服务器启动成功!");
@@ -358,12 +702,22 @@ async fn main() -> std::io::Result<()> {
     println!("🌐 Web 界面: 请将 web/app.js 中的 API 地址设置为 http://127.0.0.1:8080\n");
     println!("API 端点:");
     println!("  POST   /api/dispatch          - 派单");
+    println!("  POST   /api/dispatch/explain  - 派单解释");
     println!("  GET    /api/drivers           - 获取配送员列表");
     println!("  POST   /api/drivers           - 添加配送员");
     println!("  PUT    /api/drivers/location  - 更新配送员位置");
-    println!("  POST   /api/drivers/:id/release - 释放订单");
+    println!("  POST   /api/drivers/:id/release/:order_id - 释放订单");
+    println!("  GET    /api/orders            - 获取订单列表");
+    println!("  GET    /api/orders/pending    - 获取等待队列");
+    println!("  GET    /api/orders/:id        - 查询订单");
+    println!("  POST   /api/orders/:id/:action - 更新订单状态 (pickup/transit/deliver/cancel)");
     println!("  POST   /api/strategy          - 切换策略");
     println!("  GET    /api/strategy          - 获取当前策略");
+    println!("  POST   /api/rules             - 替换派单规则");
+    println!("  GET    /api/rules             - 获取当前规则");
+    println!("  GET    /api/zones             - 服务区及统计");
+    println!("  GET    /api/events            - 实时事件推送 (SSE)");
+    println!("  GET    /api/events/ws         - 实时事件推送 (WebSocket)");
     println!("  GET    /health                - 健康检查\n");
 
     // 启动 HTTP 服务器
@@ -382,12 +736,25 @@ async fn main() -> std::io::Result<()> {
             // API 路由
             .route("/health", web::get().to(health_check))
             .route("/api/dispatch", web::post().to(dispatch_order))
+            .route("/api/dispatch/explain", web::post().to(explain_dispatch))
             .route("/api/drivers", web::get().to(get_drivers))
             .route("/api/drivers", web::post().to(add_driver))
             .route("/api/drivers/location", web::put().to(update_driver_location))
-            .route("/api/drivers/{id}/release", web::post().to(release_order))
+            .route(
+                "/api/drivers/{id}/release/{order_id}",
+                web::post().to(release_order),
+            )
+            .route("/api/orders", web::get().to(get_orders))
+            .route("/api/orders/pending", web::get().to(get_pending_orders))
+            .route("/api/orders/{id}", web::get().to(get_order))
+            .route("/api/orders/{id}/{action}", web::post().to(update_order_status))
             .route("/api/strategy", web::post().to(change_strategy))
             .route("/api/strategy", web::get().to(get_current_strategy))
+            .route("/api/rules", web::post().to(change_rules))
+            .route("/api/rules", web::get().to(get_rules))
+            .route("/api/zones", web::get().to(get_zones))
+            .route("/api/events", web::get().to(events_sse))
+            .route("/api/events/ws", web::get().to(events_ws))
     })
     .bind("127.0.0.1:8080")?
     .run()
@@ -0,0 +1,659 @@
+use super::*;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, thiserror::Error)]
+pub enum SimulationError {
+    #[error("Unknown zone: {0}")]
+    UnknownZone(String),
+
+    #[error("Invalid simulation config: {0}")]
+    Invalid(String),
+
+    #[error("Failed to read or write scenario: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid scenario: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// 某个服务区的订单需求，每小时到达率为 `orders_per_hour × hourly_factors[当地小时]`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ZoneDemand {
+    pub zone_id: String,
+    pub orders_per_hour: f64,
+    #[serde(default = "default_hourly_factors")]
+    pub hourly_factors: [f64; 24],
+}
+
+impl ZoneDemand {
+    pub fn new(zone_id: impl Into<String>, orders_per_hour: f64) -> Self {
+        Self {
+            zone_id: zone_id.into(),
+            orders_per_hour,
+            hourly_factors: default_hourly_factors(),
+        }
+    }
+
+    pub fn with_hourly_factors(mut self, hourly_factors: [f64; 24]) -> Self {
+        self.hourly_factors = hourly_factors;
+        self
+    }
+}
+
+fn default_hourly_factors() -> [f64; 24] {
+    [1.0; 24]
+}
+
+/// 开始时在某个服务区内随机投放的配送员
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FleetSpec {
+    pub zone_id: String,
+    pub vehicle_type: VehicleType,
+    pub count: usize,
+}
+
+impl FleetSpec {
+    pub fn new(zone_id: impl Into<String>, vehicle_type: VehicleType, count: usize) -> Self {
+        Self {
+            zone_id: zone_id.into(),
+            vehicle_type,
+            count,
+        }
+    }
+}
+
+/// 仿真参数，时间均为仿真时间
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SimulationConfig {
+    /// 仿真开始的 Unix 时间戳
+    pub start_time: i64,
+    pub duration_minutes: u32,
+    pub step_secs: u32,
+    /// 随机种子，相同种子得到相同的订单序列
+    pub seed: u64,
+    /// 当地时间相对 UTC 的小时偏移，用于按小时取到达率
+    pub utc_offset_hours: i32,
+    pub order_type: OrderType,
+    /// 订单等待派单超过该时长即取消，计为未派出
+    pub max_wait_minutes: u32,
+    /// 订单从下单起的配送时限，用于统计迟到
+    pub delivery_sla_minutes: Option<u32>,
+    pub demand: Vec<ZoneDemand>,
+    pub fleet: Vec<FleetSpec>,
+}
+
+impl SimulationConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_duration_minutes(mut self, duration_minutes: u32) -> Self {
+        self.duration_minutes = duration_minutes;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_demand(mut self, demand: ZoneDemand) -> Self {
+        self.demand.push(demand);
+        self
+    }
+
+    pub fn with_fleet(mut self, fleet: FleetSpec) -> Self {
+        self.fleet.push(fleet);
+        self
+    }
+
+    fn validate(&self, zones: &ZoneMap) -> Result<(), SimulationError> {
+        if self.step_secs == 0 {
+            return Err(SimulationError::Invalid(
+                "step_secs must be positive".to_string(),
+            ));
+        }
+        let zone_ids = self
+            .demand
+            .iter()
+            .map(|demand| &demand.zone_id)
+            .chain(self.fleet.iter().map(|fleet| &fleet.zone_id));
+        for zone_id in zone_ids {
+            if zones.get(zone_id).is_none() {
+                return Err(SimulationError::UnknownZone(zone_id.clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for SimulationConfig {
+    /// 北京时间 2024-01-01 11:00 开始，仿真两小时
+    fn default() -> Self {
+        Self {
+            start_time: 1_704_078_000,
+            duration_minutes: 120,
+            step_secs: 30,
+            seed: 42,
+            utc_offset_hours: 8,
+            order_type: OrderType::Food,
+            max_wait_minutes: 15,
+            delivery_sla_minutes: Some(45),
+            demand: Vec::new(),
+            fleet: Vec::new(),
+        }
+    }
+}
+
+/// 一个订单的到达时间（仿真时间）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledOrder {
+    pub at: i64,
+    pub order: Order,
+}
+
+/// 一次仿真的输入：初始车队和按时间排列的订单流。
+/// 可以保存为 JSON，用同一份需求回放对比不同的策略
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Scenario {
+    pub drivers: Vec<Driver>,
+    pub orders: Vec<ScheduledOrder>,
+}
+
+impl Scenario {
+    pub fn from_json_file(path: impl AsRef<Path>) -> Result<Self, SimulationError> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    pub fn save_json(&self, path: impl AsRef<Path>) -> Result<(), SimulationError> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// 仿真结果指标
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SimulationReport {
+    pub strategy: String,
+    pub orders_generated: usize,
+    pub orders_dispatched: usize,
+    pub orders_delivered: usize,
+    /// 超时取消、派单失败或仿真结束时仍在等待的订单
+    pub orders_unassigned: usize,
+    pub unassigned_rate: f64,
+    /// 派单时预估的取货时间
+    pub avg_pickup_eta_minutes: f64,
+    /// 从下单到实际取货的时间
+    pub avg_pickup_wait_minutes: f64,
+    /// 从下单到送达的时间
+    pub avg_delivery_minutes: f64,
+    /// 已送达订单超过配送时限的平均分钟数，准时计为 0
+    pub avg_lateness_minutes: f64,
+    /// 已送达订单中超时的比例
+    pub late_rate: f64,
+    /// 所有配送员的行驶里程
+    pub total_distance_km: f64,
+    /// 配送员有订单在身的时间占在线时间的比例
+    pub utilization: f64,
+    /// 各配送员送达单量的 Jain 公平指数，1.0 表示完全平均
+    pub fairness_index: f64,
+}
+
+/// 单个订单在仿真中的时间线
+#[derive(Debug, Clone, Default)]
+struct OrderTrack {
+    created_at: i64,
+    deadline: Option<i64>,
+    pickup_eta_minutes: Option<u32>,
+    picked_up_at: Option<i64>,
+    delivered_at: Option<i64>,
+    unassigned: bool,
+}
+
+/// 一次回放过程中累积的统计
+#[derive(Debug, Default)]
+struct RunState {
+    tracks: BTreeMap<Uuid, OrderTrack>,
+    distance_km: BTreeMap<Uuid, f64>,
+    delivered: BTreeMap<Uuid, usize>,
+    busy_secs: i64,
+    online_secs: i64,
+}
+
+/// 离散事件仿真：按步长推进仿真时间，生成订单交给引擎派单，
+/// 配送员沿计划路线行驶并完成取货和送达
+pub struct Simulation {
+    config: SimulationConfig,
+    zones: Arc<ZoneMap>,
+    clock: Arc<FixedClock>,
+}
+
+impl Simulation {
+    pub fn new(config: SimulationConfig, zones: Arc<ZoneMap>) -> Self {
+        let clock = Arc::new(FixedClock::new(config.start_time));
+        Self {
+            config,
+            zones,
+            clock,
+        }
+    }
+
+    pub fn config(&self) -> &SimulationConfig {
+        &self.config
+    }
+
+    pub fn zones(&self) -> Arc<ZoneMap> {
+        self.zones.clone()
+    }
+
+    /// 仿真时钟，回放时随仿真时间前进。引擎使用它时（`DispatchEngine::with_clock`），
+    /// 相同的场景和配置总是得到相同的报告
+    pub fn clock(&self) -> Arc<FixedClock> {
+        self.clock.clone()
+    }
+
+    /// 生成随机场景后在 `engine` 上回放
+    pub fn run(&self, engine: &DispatchEngine) -> Result<SimulationReport, SimulationError> {
+        self.replay(engine, &self.scenario()?)
+    }
+
+    /// 按随机种子生成车队和订单流，相同配置总是得到相同的场景
+    pub fn scenario(&self) -> Result<Scenario, SimulationError> {
+        self.config.validate(&self.zones)?;
+        let mut rng = SimRng::new(self.config.seed);
+        let clock = FixedClock::new(self.config.start_time);
+        let ids = SeededIdGenerator::new(self.config.seed);
+        let mut scenario = Scenario::default();
+
+        for fleet in &self.config.fleet {
+            let zone = self.zone(&fleet.zone_id)?;
+            for i in 0..fleet.count {
+                let location = random_point(zone, &mut rng);
+                let name = format!("{}-{:?}-{}", zone.id, fleet.vehicle_type, i + 1);
+                let driver =
+                    Driver::new_with(name, fleet.vehicle_type.clone(), location, &clock, &ids);
+                scenario.drivers.push(driver.with_zone(&zone.id));
+            }
+        }
+
+        let step = self.config.step_secs as i64;
+        let mut now = self.config.start_time;
+        while now < self.end_time() {
+            clock.set(now);
+            self.generate_orders(now, &mut rng, &clock, &ids, &mut scenario.orders);
+            now += step;
+        }
+        Ok(scenario)
+    }
+
+    /// 在 `engine` 上回放场景。引擎中已有的配送员也会参与
+    pub fn replay(
+        &self,
+        engine: &DispatchEngine,
+        scenario: &Scenario,
+    ) -> Result<SimulationReport, SimulationError> {
+        self.config.validate(&self.zones)?;
+        let step = self.config.step_secs as i64;
+        let max_wait = self.config.max_wait_minutes as i64 * 60;
+        self.clock.set(self.config.start_time);
+        for driver in &scenario.drivers {
+            engine.add_driver(driver.clone());
+        }
+
+        // 等待队列中的订单可能在任何引擎调用中被派出，通过事件统一记录
+        let dispatched = Arc::new(Mutex::new(Vec::new()));
+        let sink = dispatched.clone();
+        engine.subscribe(Arc::new(move |event: &DispatchEvent| {
+            if let DispatchEvent::OrderDispatched { result } = event {
+                sink.lock()
+                    .unwrap()
+                    .push((result.order_id, result.estimated_arrival_minutes));
+            }
+        }));
+
+        let mut state = RunState::default();
+        let mut arrivals = scenario.orders.iter().peekable();
+        let mut now = self.config.start_time;
+        while now < self.end_time() {
+            self.clock.set(now);
+            while let Some(scheduled) = arrivals.next_if(|scheduled| scheduled.at < now + step) {
+                self.submit(engine, scheduled, now, &mut state);
+            }
+            let mut drivers = engine.get_all_drivers();
+            drivers.sort_by(|a, b| a.name.cmp(&b.name));
+            for driver in drivers {
+                self.advance_driver(engine, driver.id, now, now + step, &mut state);
+            }
+            now += step;
+            self.clock.set(now);
+
+            for driver in engine.get_all_drivers() {
+                if driver.status != DriverStatus::Offline {
+                    state.online_secs += step;
+                    if driver.current_load() > 0 {
+                        state.busy_secs += step;
+                    }
+                }
+            }
+            for (order_id, eta) in dispatched.lock().unwrap().drain(..) {
+                if let Some(track) = state.tracks.get_mut(&order_id) {
+                    track.pickup_eta_minutes = Some(eta);
+                }
+            }
+            for (order_id, track) in state.tracks.iter_mut() {
+                if !track.unassigned
+                    && now - track.created_at > max_wait
+                    && engine.is_pending(order_id)
+                    && engine.cancel_order(order_id).is_ok()
+                {
+                    track.unassigned = true;
+                }
+            }
+        }
+
+        Ok(self.report(engine, scenario, &state))
+    }
+
+    fn end_time(&self) -> i64 {
+        self.config.start_time + self.config.duration_minutes as i64 * 60
+    }
+
+    fn zone(&self, zone_id: &str) -> Result<&Zone, SimulationError> {
+        self.zones
+            .get(zone_id)
+            .ok_or_else(|| SimulationError::UnknownZone(zone_id.to_string()))
+    }
+
+    fn local_hour(&self, timestamp: i64) -> usize {
+        let local_secs = timestamp + self.config.utc_offset_hours as i64 * 3600;
+        local_secs.div_euclid(3600).rem_euclid(24) as usize
+    }
+
+    /// 按泊松过程生成本步到达的订单，送达点在取货服务区或其相邻服务区内
+    fn generate_orders(
+        &self,
+        now: i64,
+        rng: &mut SimRng,
+        clock: &dyn Clock,
+        ids: &dyn IdGenerator,
+        orders: &mut Vec<ScheduledOrder>,
+    ) {
+        let hour = self.local_hour(now);
+        for demand in &self.config.demand {
+            let Some(zone) = self.zones.get(&demand.zone_id) else {
+                continue;
+            };
+            let rate = demand.orders_per_hour * demand.hourly_factors[hour];
+            let arrivals = rng.poisson(rate * self.config.step_secs as f64 / 3600.0);
+            for _ in 0..arrivals {
+                let destinations: Vec<&Zone> = std::iter::once(zone)
+                    .chain(zone.neighbors.iter().filter_map(|id| self.zones.get(id)))
+                    .collect();
+                let destination = destinations[rng.below(destinations.len())];
+                let mut order = Order::new_with(
+                    self.config.order_type.clone(),
+                    random_point(zone, rng),
+                    random_point(destination, rng),
+                    clock,
+                    ids,
+                );
+                if let Some(minutes) = self.config.delivery_sla_minutes {
+                    let deadline = order.created_at + minutes as i64 * 60;
+                    order = order.with_deadline(deadline);
+                }
+                orders.push(ScheduledOrder { at: now, order });
+            }
+        }
+    }
+
+    /// 引擎的规则按引擎时钟判断时效，回放时把订单的时间字段平移到引擎的当前时间。
+    /// 引擎使用仿真时钟时平移量为零
+    fn submit(
+        &self,
+        engine: &DispatchEngine,
+        scheduled: &ScheduledOrder,
+        now: i64,
+        state: &mut RunState,
+    ) {
+        let mut order = scheduled.order.clone();
+        let recorded_at = order.created_at;
+        let shift = engine.clock().now() - recorded_at;
+        order.created_at += shift;
+        order.pickup_ready_at = order.pickup_ready_at.map(|at| at + shift);
+        order.deliver_by = order.deliver_by.map(|at| at + shift);
+
+        let mut track = OrderTrack {
+            created_at: now,
+            deadline: scheduled
+                .order
+                .deliver_by
+                .map(|deadline| now + deadline - recorded_at),
+            ..Default::default()
+        };
+        if engine.dispatch_or_enqueue(&mut order).is_err() && !engine.is_pending(&order.id) {
+            track.unassigned = true;
+        }
+        state.tracks.insert(order.id, track);
+    }
+
+    /// 配送员在 [from, to) 内沿计划路线行驶，途经的停靠点依次取货或送达
+    fn advance_driver(
+        &self,
+        engine: &DispatchEngine,
+        driver_id: Uuid,
+        from: i64,
+        to: i64,
+        state: &mut RunState,
+    ) {
+        let model = engine.travel_model();
+        let mut now = from;
+        let mut handled: Option<Stop> = None;
+        self.clock.set(from);
+        while let Ok(driver) = engine.get_driver_stats(&driver_id) {
+            let Some(stop) = driver.planned_stops.first().cloned() else {
+                return;
+            };
+            // 引擎拒绝了上一个停靠点的状态变更，留到下一步再处理
+            if handled.as_ref() == Some(&stop) {
+                return;
+            }
+            let minutes = model.travel_minutes(
+                driver.current_location,
+                stop.location,
+                &driver.vehicle_type,
+                now,
+            );
+            let arrive_at = now + (minutes * 60.0).ceil() as i64;
+            let target = if arrive_at > to {
+                // 本步内到不了，按时间比例前进
+                let fraction = (to - now) as f64 / (arrive_at - now) as f64;
+                interpolate(driver.current_location, stop.location, fraction)
+            } else {
+                stop.location
+            };
+            *state.distance_km.entry(driver_id).or_default() +=
+                model.distance_km(driver.current_location, target);
+            let _ = engine.update_driver_location(&driver_id, target);
+            if arrive_at > to {
+                return;
+            }
+
+            now = arrive_at;
+            self.clock.set(now);
+            handled = Some(stop.clone());
+            let track = state.tracks.get_mut(&stop.order_id);
+            match stop.kind {
+                StopKind::Pickup => {
+                    let _ = engine.mark_picked_up(&stop.order_id);
+                    if let Some(track) = track {
+                        track.picked_up_at = Some(now);
+                    }
+                }
+                StopKind::Delivery => {
+                    let _ = engine.release_order(&driver_id, &stop.order_id);
+                    if let Some(track) = track {
+                        track.delivered_at = Some(now);
+                    }
+                    *state.delivered.entry(driver_id).or_default() += 1;
+                }
+            }
+        }
+    }
+
+    fn report(
+        &self,
+        engine: &DispatchEngine,
+        scenario: &Scenario,
+        state: &RunState,
+    ) -> SimulationReport {
+        let tracks = &state.tracks;
+        let orders_generated = tracks.len();
+        let orders_unassigned = tracks
+            .iter()
+            .filter(|(order_id, track)| track.unassigned || engine.is_pending(order_id))
+            .count();
+        let etas: Vec<f64> = tracks
+            .values()
+            .filter_map(|track| track.pickup_eta_minutes.map(f64::from))
+            .collect();
+        let pickup_waits: Vec<f64> = tracks
+            .values()
+            .filter_map(|track| {
+                track
+                    .picked_up_at
+                    .map(|at| (at - track.created_at) as f64 / 60.0)
+            })
+            .collect();
+        let deliveries: Vec<f64> = tracks
+            .values()
+            .filter_map(|track| {
+                track
+                    .delivered_at
+                    .map(|at| (at - track.created_at) as f64 / 60.0)
+            })
+            .collect();
+        let lateness: Vec<f64> = tracks
+            .values()
+            .filter_map(|track| match (track.delivered_at, track.deadline) {
+                (Some(at), Some(deadline)) => Some((at - deadline).max(0) as f64 / 60.0),
+                _ => None,
+            })
+            .collect();
+        let late = lateness.iter().filter(|minutes| **minutes > 0.0).count();
+
+        // 场景中的配送员即使一单未送也计入公平指数
+        let counts: Vec<f64> = scenario
+            .drivers
+            .iter()
+            .map(|driver| state.delivered.get(&driver.id).copied().unwrap_or(0) as f64)
+            .collect();
+        let squares: f64 = counts.iter().map(|count| count * count).sum();
+        let total: f64 = counts.iter().sum();
+
+        SimulationReport {
+            strategy: engine.strategy_name(),
+            orders_generated,
+            orders_dispatched: etas.len(),
+            orders_delivered: deliveries.len(),
+            orders_unassigned,
+            unassigned_rate: ratio(orders_unassigned as f64, orders_generated as f64),
+            avg_pickup_eta_minutes: mean(&etas),
+            avg_pickup_wait_minutes: mean(&pickup_waits),
+            avg_delivery_minutes: mean(&deliveries),
+            avg_lateness_minutes: mean(&lateness),
+            late_rate: ratio(late as f64, lateness.len() as f64),
+            total_distance_km: state.distance_km.values().sum(),
+            utilization: ratio(state.busy_secs as f64, state.online_secs as f64),
+            fairness_index: ratio(total * total, counts.len() as f64 * squares),
+        }
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    ratio(values.iter().sum(), values.len() as f64)
+}
+
+fn ratio(numerator: f64, denominator: f64) -> f64 {
+    if denominator > 0.0 {
+        numerator / denominator
+    } else {
+        0.0
+    }
+}
+
+fn interpolate(from: Location, to: Location, fraction: f64) -> Location {
+    Location::new(
+        from.latitude + (to.latitude - from.latitude) * fraction,
+        from.longitude + (to.longitude - from.longitude) * fraction,
+    )
+}
+
+/// 在服务区外接矩形内拒绝采样，多次落空时退回到第一个顶点
+fn random_point(zone: &Zone, rng: &mut SimRng) -> Location {
+    let vertices = zone.polygons.iter().flat_map(|polygon| &polygon.exterior);
+    let (mut min_lat, mut max_lat) = (f64::MAX, f64::MIN);
+    let (mut min_lng, mut max_lng) = (f64::MAX, f64::MIN);
+    for vertex in vertices {
+        min_lat = min_lat.min(vertex.latitude);
+        max_lat = max_lat.max(vertex.latitude);
+        min_lng = min_lng.min(vertex.longitude);
+        max_lng = max_lng.max(vertex.longitude);
+    }
+
+    for _ in 0..100 {
+        let location = Location::new(
+            min_lat + (max_lat - min_lat) * rng.next_f64(),
+            min_lng + (max_lng - min_lng) * rng.next_f64(),
+        );
+        if zone.contains(location) {
+            return location;
+        }
+    }
+    zone.polygons[0].exterior[0]
+}
+
+/// SplitMix64 伪随机数，保证相同种子的仿真可以复现
+struct SimRng {
+    state: u64,
+}
+
+impl SimRng {
+    fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// [0, 1) 均匀分布
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Knuth 算法，每步的期望到达数很小
+    fn poisson(&mut self, lambda: f64) -> usize {
+        if lambda <= 0.0 {
+            return 0;
+        }
+        let limit = (-lambda).exp();
+        let mut product = self.next_f64();
+        let mut count = 0;
+        while product > limit {
+            product *= self.next_f64();
+            count += 1;
+        }
+        count
+    }
+}
@@ -0,0 +1,175 @@
+use super::*;
+use std::collections::HashSet;
+
+/// 每纬度对应的公里数
+const KM_PER_DEGREE: f64 = 111.32;
+
+/// 网格单元坐标（纬度索引, 经度索引）
+type Cell = (i64, i64);
+
+/// 基于等经纬度网格的空间索引，用于快速查找附近的配送员
+#[derive(Debug, Clone)]
+pub struct GridIndex {
+    cell_size_deg: f64,
+    cells: HashMap<Cell, HashSet<Uuid>>,
+    positions: HashMap<Uuid, (Cell, Location)>,
+}
+
+impl GridIndex {
+    /// `cell_size_deg` 为网格边长（度），0.01 度约 1.1 公里
+    pub fn new(cell_size_deg: f64) -> Self {
+        Self {
+            cell_size_deg,
+            cells: HashMap::new(),
+            positions: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    fn cell_of(&self, location: Location) -> Cell {
+        (
+            (location.latitude / self.cell_size_deg).floor() as i64,
+            (location.longitude / self.cell_size_deg).floor() as i64,
+        )
+    }
+
+    /// 插入或更新一个点的位置
+    pub fn insert(&mut self, id: Uuid, location: Location) {
+        self.remove(&id);
+        let cell = self.cell_of(location);
+        self.cells.entry(cell).or_default().insert(id);
+        self.positions.insert(id, (cell, location));
+    }
+
+    pub fn remove(&mut self, id: &Uuid) {
+        if let Some((cell, _)) = self.positions.remove(id)
+            && let Some(ids) = self.cells.get_mut(&cell)
+        {
+            ids.remove(id);
+            if ids.is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+    }
+
+    /// 返回半径范围内的点，按距离升序排列。
+    /// 半径覆盖的网格单元多于已索引的点时，逐个计算距离比扫描网格更快
+    pub fn within_radius(&self, location: Location, radius_km: f64) -> Vec<(Uuid, f64)> {
+        // 纬度和经度分别不会超出 ±90° 和 ±180°，限制跨度避免 cell_of 饱和后的超长循环
+        let lat_span = (radius_km / KM_PER_DEGREE).min(180.0);
+        let lng_span = (radius_km / (KM_PER_DEGREE * cos_lat(location.latitude))).min(360.0);
+        let (min_lat, min_lng) = self.cell_of(Location::new(
+            location.latitude - lat_span,
+            location.longitude - lng_span,
+        ));
+        let (max_lat, max_lng) = self.cell_of(Location::new(
+            location.latitude + lat_span,
+            location.longitude + lng_span,
+        ));
+
+        let cells = (max_lat - min_lat + 1).saturating_mul(max_lng - min_lng + 1);
+        let mut found = if cells > self.len() as i64 {
+            self.all_points(location)
+        } else {
+            let mut found = Vec::new();
+            for lat in min_lat..=max_lat {
+                for lng in min_lng..=max_lng {
+                    self.collect_cell((lat, lng), location, &mut found);
+                }
+            }
+            found
+        };
+        found.retain(|(_, dist)| *dist <= radius_km);
+        sort_by_distance(&mut found);
+        found
+    }
+
+    /// 返回距离最近的 k 个点，按距离升序排列
+    pub fn nearest_k(&self, location: Location, k: usize) -> Vec<(Uuid, f64)> {
+        if k == 0 || self.is_empty() {
+            return Vec::new();
+        }
+
+        // 网格单元的最短边长（公里），用于估计外圈点的距离下界
+        let min_cell_km = self.cell_size_deg * KM_PER_DEGREE * cos_lat(location.latitude);
+        let (center_lat, center_lng) = self.cell_of(location);
+        let mut found = Vec::new();
+        let mut ring = 0i64;
+
+        loop {
+            // 点很稀疏时外圈大多是空单元，范围内的单元多于点数后改为逐个计算
+            let side = 2 * ring + 1;
+            if side.saturating_mul(side) > self.len() as i64 {
+                found = self.all_points(location);
+                break;
+            }
+            for lat in (center_lat - ring)..=(center_lat + ring) {
+                for lng in (center_lng - ring)..=(center_lng + ring) {
+                    let on_ring =
+                        (lat - center_lat).abs() == ring || (lng - center_lng).abs() == ring;
+                    if on_ring {
+                        self.collect_cell((lat, lng), location, &mut found);
+                    }
+                }
+            }
+
+            if found.len() == self.len() {
+                break;
+            }
+            if found.len() >= k {
+                sort_by_distance(&mut found);
+                // 更外圈的点距离至少为 ring 个网格边长
+                if found[k - 1].1 <= ring as f64 * min_cell_km {
+                    break;
+                }
+            }
+            ring += 1;
+        }
+
+        sort_by_distance(&mut found);
+        found.truncate(k);
+        found
+    }
+
+    fn all_points(&self, location: Location) -> Vec<(Uuid, f64)> {
+        self.positions
+            .iter()
+            .map(|(id, (_, position))| (*id, distance_km(location, *position)))
+            .collect()
+    }
+
+    fn collect_cell(&self, cell: Cell, location: Location, found: &mut Vec<(Uuid, f64)>) {
+        if let Some(ids) = self.cells.get(&cell) {
+            for id in ids {
+                let (_, position) = self.positions[id];
+                found.push((*id, distance_km(location, position)));
+            }
+        }
+    }
+}
+
+impl Default for GridIndex {
+    fn default() -> Self {
+        Self::new(0.01)
+    }
+}
+
+fn cos_lat(latitude: f64) -> f64 {
+    // 避免高纬度时经度跨度趋于无穷
+    latitude.to_radians().cos().max(0.01)
+}
+
+fn sort_by_distance(found: &mut [(Uuid, f64)]) {
+    found.sort_by(|a, b| {
+        a.1.partial_cmp(&b.1)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.0.cmp(&b.0))
+    });
+}
@@ -0,0 +1,274 @@
+use super::*;
+use rusqlite::{Connection, params};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// 按顺序执行的数据库迁移，`PRAGMA user_version` 记录已执行的数量
+const MIGRATIONS: &[&str] = &[
+    // v1: 配送员、订单、等待队列与派单记录
+    "CREATE TABLE drivers (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        status TEXT NOT NULL,
+        vehicle_type TEXT NOT NULL,
+        latitude REAL NOT NULL,
+        longitude REAL NOT NULL,
+        capacity INTEGER NOT NULL,
+        current_load INTEGER NOT NULL,
+        rating REAL NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE orders (
+        id TEXT PRIMARY KEY,
+        order_type TEXT NOT NULL,
+        status TEXT NOT NULL,
+        assigned_to TEXT,
+        distance_km REAL NOT NULL,
+        price REAL NOT NULL,
+        created_at INTEGER NOT NULL,
+        deliver_by INTEGER,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX idx_orders_status ON orders(status);
+    CREATE INDEX idx_orders_assigned_to ON orders(assigned_to);
+    CREATE TABLE pending_orders (
+        order_id TEXT PRIMARY KEY,
+        order_type TEXT NOT NULL,
+        enqueued_at INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE dispatch_results (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        order_id TEXT NOT NULL,
+        driver_id TEXT NOT NULL,
+        driver_name TEXT NOT NULL,
+        vehicle_type TEXT NOT NULL,
+        distance_to_pickup_km REAL NOT NULL,
+        estimated_arrival_minutes INTEGER NOT NULL,
+        estimated_delivery_at INTEGER NOT NULL,
+        predicted_lateness_minutes INTEGER,
+        strategy_used TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX idx_dispatch_results_order_id ON dispatch_results(order_id);
+    CREATE INDEX idx_dispatch_results_driver_id ON dispatch_results(driver_id);",
+];
+
+/// 基于嵌入式 SQLite 的状态存储。
+/// 常用字段展开为列便于用 SQL 查询，`data` 列保存完整 JSON 用于精确恢复
+pub struct SqliteStateStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStateStore {
+    /// 打开（必要时创建）数据库文件并执行未完成的迁移
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        Self::from_connection(conn)
+    }
+
+    pub fn open_in_memory() -> Result<Self, StoreError> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(mut conn: Connection) -> Result<Self, StoreError> {
+        migrate(&mut conn)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// 当前数据库结构版本
+    pub fn schema_version(&self) -> Result<usize, StoreError> {
+        Ok(schema_version(&self.conn.lock().unwrap())?)
+    }
+
+    /// 最近的派单记录，按时间倒序
+    pub fn dispatch_history(&self, limit: usize) -> Result<Vec<DispatchResult>, StoreError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT data FROM dispatch_results ORDER BY id DESC LIMIT ?1")?;
+        let rows = stmt.query_map(params![limit as i64], |row| row.get::<_, String>(0))?;
+        rows.map(|data| Ok(serde_json::from_str(&data?)?)).collect()
+    }
+
+    /// 某个订单的派单记录，按时间正序
+    pub fn dispatch_history_for_order(
+        &self,
+        order_id: &Uuid,
+    ) -> Result<Vec<DispatchResult>, StoreError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT data FROM dispatch_results WHERE order_id = ?1 ORDER BY id")?;
+        let rows = stmt.query_map(params![order_id.to_string()], |row| row.get::<_, String>(0))?;
+        rows.map(|data| Ok(serde_json::from_str(&data?)?)).collect()
+    }
+}
+
+fn schema_version(conn: &Connection) -> rusqlite::Result<usize> {
+    conn.pragma_query_value(None, "user_version", |row| row.get(0))
+}
+
+fn migrate(conn: &mut Connection) -> Result<(), StoreError> {
+    let version = schema_version(conn)?;
+    for (i, sql) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(sql)?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+/// 单元枚举序列化后的名称，如 `ElectricBike`
+fn enum_name<T: Serialize>(value: &T) -> Result<String, StoreError> {
+    Ok(match serde_json::to_value(value)? {
+        serde_json::Value::String(name) => name,
+        other => other.to_string(),
+    })
+}
+
+fn upsert_driver(conn: &Connection, driver: &Driver) -> Result<(), StoreError> {
+    conn.execute(
+        "INSERT OR REPLACE INTO drivers
+         (id, name, status, vehicle_type, latitude, longitude,
+          capacity, current_load, rating, data)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            driver.id.to_string(),
+            driver.name,
+            enum_name(&driver.status)?,
+            enum_name(&driver.vehicle_type)?,
+            driver.current_location.latitude,
+            driver.current_location.longitude,
+            driver.capacity as i64,
+            driver.current_load() as i64,
+            driver.rating,
+            serde_json::to_string(driver)?,
+        ],
+    )?;
+    Ok(())
+}
+
+fn upsert_order(conn: &Connection, order: &Order) -> Result<(), StoreError> {
+    conn.execute(
+        "INSERT OR REPLACE INTO orders
+         (id, order_type, status, assigned_to, distance_km, price,
+          created_at, deliver_by, data)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            order.id.to_string(),
+            enum_name(&order.order_type)?,
+            enum_name(&order.status)?,
+            order.assigned_to.map(|id| id.to_string()),
+            order.distance_km,
+            order.price,
+            order.created_at,
+            order.deliver_by,
+            serde_json::to_string(order)?,
+        ],
+    )?;
+    Ok(())
+}
+
+impl StateStore for SqliteStateStore {
+    fn load(&self) -> Result<EngineSnapshot, StoreError> {
+        let conn = self.conn.lock().unwrap();
+        let mut snapshot = EngineSnapshot::default();
+
+        let mut stmt = conn.prepare("SELECT data FROM drivers")?;
+        for data in stmt.query_map([], |row| row.get::<_, String>(0))? {
+            let driver: Driver = serde_json::from_str(&data?)?;
+            snapshot.drivers.insert(driver.id, driver);
+        }
+
+        let mut stmt = conn.prepare("SELECT data FROM orders")?;
+        for data in stmt.query_map([], |row| row.get::<_, String>(0))? {
+            let order: Order = serde_json::from_str(&data?)?;
+            snapshot.orders.insert(order.id, order);
+        }
+
+        let mut stmt = conn.prepare("SELECT data FROM pending_orders")?;
+        for data in stmt.query_map([], |row| row.get::<_, String>(0))? {
+            let entry: PendingEntry = serde_json::from_str(&data?)?;
+            snapshot.pending.insert(entry.order_id, entry);
+        }
+
+        Ok(snapshot)
+    }
+
+    fn append(&self, change: &StateChange) -> Result<(), StoreError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        match change {
+            StateChange::UpsertDriver { driver } => upsert_driver(&tx, driver)?,
+            StateChange::RemoveDriver { driver_id } => {
+                tx.execute(
+                    "DELETE FROM drivers WHERE id = ?1",
+                    params![driver_id.to_string()],
+                )?;
+            }
+            StateChange::MoveDriver {
+                driver_id,
+                location,
+            } => {
+                tx.execute(
+                    "UPDATE drivers SET latitude = ?2, longitude = ?3,
+                     data = json_set(data, '$.current_location.latitude', ?2,
+                                           '$.current_location.longitude', ?3)
+                     WHERE id = ?1",
+                    params![driver_id.to_string(), location.latitude, location.longitude],
+                )?;
+            }
+            StateChange::UpsertOrder { order } => upsert_order(&tx, order)?,
+            StateChange::AssignOrder { driver, order } => {
+                upsert_driver(&tx, driver)?;
+                upsert_order(&tx, order)?;
+            }
+            StateChange::Enqueue { entry } => {
+                tx.execute(
+                    "INSERT OR REPLACE INTO pending_orders (order_id, order_type, enqueued_at, data)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![
+                        entry.order_id.to_string(),
+                        enum_name(&entry.order_type)?,
+                        entry.enqueued_at,
+                        serde_json::to_string(entry)?,
+                    ],
+                )?;
+            }
+            StateChange::Dequeue { order_id } => {
+                tx.execute(
+                    "DELETE FROM pending_orders WHERE order_id = ?1",
+                    params![order_id.to_string()],
+                )?;
+            }
+            StateChange::RecordDispatch { result } => {
+                tx.execute(
+                    "INSERT INTO dispatch_results
+                     (order_id, driver_id, driver_name, vehicle_type, distance_to_pickup_km,
+                      estimated_arrival_minutes, estimated_delivery_at,
+                      predicted_lateness_minutes, strategy_used, timestamp, data)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                    params![
+                        result.order_id.to_string(),
+                        result.driver_id.to_string(),
+                        result.driver_name,
+                        result.vehicle_type,
+                        result.distance_to_pickup_km,
+                        result.estimated_arrival_minutes,
+                        result.estimated_delivery_at,
+                        result.predicted_lateness_minutes,
+                        result.strategy_used,
+                        result.timestamp,
+                        serde_json::to_string(result)?,
+                    ],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,284 @@
+use super::*;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[error("Storage I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Storage serialization error: {0}")]
+    Serde(#[from] serde_json::Error),
+
+    #[error("Corrupted journal at line {line}: {message}")]
+    Corrupted { line: usize, message: String },
+
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+}
+
+/// 一次需要持久化的引擎状态变更。
+/// 变更均为幂等的写入或删除，重复回放不会改变结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op")]
+pub enum StateChange {
+    UpsertDriver {
+        driver: Driver,
+    },
+    RemoveDriver {
+        driver_id: Uuid,
+    },
+    /// 只更新配送员位置
+    MoveDriver {
+        driver_id: Uuid,
+        location: Location,
+    },
+    UpsertOrder {
+        order: Order,
+    },
+    Enqueue {
+        entry: PendingEntry,
+    },
+    Dequeue {
+        order_id: Uuid,
+    },
+    /// 同时修改配送员和订单（派单，以及取货、送达、取消时同步更新配送员），
+    /// 写成一条记录，崩溃后不会只恢复其中一半
+    AssignOrder {
+        driver: Driver,
+        order: Box<Order>,
+    },
+    /// 派单记录，只用于历史查询，不属于引擎状态
+    RecordDispatch {
+        result: DispatchResult,
+    },
+}
+
+/// 引擎在某一时刻的完整状态
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EngineSnapshot {
+    pub drivers: BTreeMap<Uuid, Driver>,
+    pub orders: BTreeMap<Uuid, Order>,
+    pub pending: BTreeMap<Uuid, PendingEntry>,
+}
+
+impl EngineSnapshot {
+    pub fn apply(&mut self, change: &StateChange) {
+        match change {
+            StateChange::UpsertDriver { driver } => {
+                self.drivers.insert(driver.id, driver.clone());
+            }
+            StateChange::RemoveDriver { driver_id } => {
+                self.drivers.remove(driver_id);
+            }
+            StateChange::MoveDriver {
+                driver_id,
+                location,
+            } => {
+                if let Some(driver) = self.drivers.get_mut(driver_id) {
+                    driver.current_location = *location;
+                }
+            }
+            StateChange::UpsertOrder { order } => {
+                self.orders.insert(order.id, order.clone());
+            }
+            StateChange::Enqueue { entry } => {
+                self.pending.insert(entry.order_id, entry.clone());
+            }
+            StateChange::Dequeue { order_id } => {
+                self.pending.remove(order_id);
+            }
+            StateChange::AssignOrder { driver, order } => {
+                self.drivers.insert(driver.id, driver.clone());
+                self.orders.insert(order.id, order.as_ref().clone());
+            }
+            StateChange::RecordDispatch { .. } => {}
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.drivers.is_empty() && self.orders.is_empty() && self.pending.is_empty()
+    }
+}
+
+/// 引擎状态的持久化后端
+pub trait StateStore: Send + Sync {
+    /// 读取已持久化的状态，用于启动时恢复引擎
+    fn load(&self) -> Result<EngineSnapshot, StoreError>;
+
+    /// 记录一次状态变更，返回前应保证变更已落盘
+    fn append(&self, change: &StateChange) -> Result<(), StoreError>;
+
+    /// 将已记录的变更合并为快照，默认不做任何事
+    fn compact(&self) -> Result<(), StoreError> {
+        Ok(())
+    }
+
+    /// 变更已落盘但之后的维护（如自动快照）失败的原因，不影响已记录的变更
+    fn maintenance_error(&self) -> Option<String> {
+        None
+    }
+}
+
+/// 默认每记录多少条变更自动写一次快照
+pub const DEFAULT_SNAPSHOT_INTERVAL: usize = 1000;
+
+const SNAPSHOT_FILE: &str = "snapshot.json";
+const JOURNAL_FILE: &str = "journal.jsonl";
+
+/// 基于文件的状态存储：`journal.jsonl` 逐行追加变更（预写日志），
+/// `snapshot.json` 保存完整状态。变更数达到间隔后自动写快照并清空日志。
+/// 派单记录只保留在日志中，写快照时丢弃，需要历史查询请使用 SQLite 存储
+pub struct FileStateStore {
+    dir: PathBuf,
+    snapshot_interval: usize,
+    inner: Mutex<FileStoreInner>,
+}
+
+struct FileStoreInner {
+    journal: File,
+    /// 快照加日志回放后的当前状态，用于自动写快照
+    state: EngineSnapshot,
+    entries_since_snapshot: usize,
+    /// 最近一次自动快照失败的原因，下次快照成功后清除
+    snapshot_error: Option<String>,
+}
+
+impl FileStateStore {
+    /// 打开（必要时创建）数据目录，并回放已有的快照和日志
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, StoreError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let snapshot_path = dir.join(SNAPSHOT_FILE);
+        let mut state = if snapshot_path.exists() {
+            serde_json::from_reader(BufReader::new(File::open(&snapshot_path)?))?
+        } else {
+            EngineSnapshot::default()
+        };
+
+        let journal_path = dir.join(JOURNAL_FILE);
+        let mut entries_since_snapshot = 0;
+        if journal_path.exists() {
+            let valid_len = replay_journal(&journal_path, &mut state, &mut entries_since_snapshot)?;
+            OpenOptions::new()
+                .write(true)
+                .open(&journal_path)?
+                .set_len(valid_len)?;
+        }
+
+        let journal = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&journal_path)?;
+
+        Ok(Self {
+            dir,
+            snapshot_interval: DEFAULT_SNAPSHOT_INTERVAL,
+            inner: Mutex::new(FileStoreInner {
+                journal,
+                state,
+                entries_since_snapshot,
+                snapshot_error: None,
+            }),
+        })
+    }
+
+    /// 设置自动快照间隔（变更条数），0 表示不自动写快照
+    pub fn with_snapshot_interval(mut self, interval: usize) -> Self {
+        self.snapshot_interval = interval;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn write_snapshot(&self, inner: &mut FileStoreInner) -> Result<(), StoreError> {
+        // 先写临时文件再原子替换，避免崩溃时留下不完整的快照
+        let tmp_path = self.dir.join(format!("{}.tmp", SNAPSHOT_FILE));
+        let mut tmp = File::create(&tmp_path)?;
+        serde_json::to_writer(&mut tmp, &inner.state)?;
+        tmp.sync_all()?;
+        fs::rename(&tmp_path, self.dir.join(SNAPSHOT_FILE))?;
+
+        // 快照已包含日志中的全部变更，截断日志。
+        // 若在截断前崩溃，重放日志也只是重复写入相同状态
+        inner.journal.set_len(0)?;
+        inner.journal.sync_all()?;
+        inner.entries_since_snapshot = 0;
+        Ok(())
+    }
+}
+
+/// 回放日志，返回有效内容的字节长度
+fn replay_journal(
+    path: &Path,
+    state: &mut EngineSnapshot,
+    entries: &mut usize,
+) -> Result<u64, StoreError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut valid_len = 0u64;
+    let mut line = String::new();
+    let mut line_no = 0;
+
+    loop {
+        line.clear();
+        let read = reader.read_line(&mut line)?;
+        if read == 0 {
+            break;
+        }
+        line_no += 1;
+
+        // 没有换行符的只可能是崩溃时写了一半的最后一行
+        if !line.ends_with('\n') {
+            break;
+        }
+        let change: StateChange =
+            serde_json::from_str(line.trim_end()).map_err(|e| StoreError::Corrupted {
+                line: line_no,
+                message: e.to_string(),
+            })?;
+        state.apply(&change);
+        *entries += 1;
+        valid_len += read as u64;
+    }
+    Ok(valid_len)
+}
+
+impl StateStore for FileStateStore {
+    fn load(&self) -> Result<EngineSnapshot, StoreError> {
+        Ok(self.inner.lock().unwrap().state.clone())
+    }
+
+    fn append(&self, change: &StateChange) -> Result<(), StoreError> {
+        let mut inner = self.inner.lock().unwrap();
+        let mut line = serde_json::to_vec(change)?;
+        line.push(b'\n');
+        inner.journal.write_all(&line)?;
+        inner.journal.sync_data()?;
+
+        inner.state.apply(change);
+        inner.entries_since_snapshot += 1;
+
+        // 变更已写入日志，快照失败不影响本次记录，日志保留到下次快照成功
+        if self.snapshot_interval > 0 && inner.entries_since_snapshot >= self.snapshot_interval {
+            inner.snapshot_error = self.write_snapshot(&mut inner).err().map(|e| e.to_string());
+        }
+        Ok(())
+    }
+
+    fn compact(&self) -> Result<(), StoreError> {
+        let mut inner = self.inner.lock().unwrap();
+        self.write_snapshot(&mut inner)?;
+        inner.snapshot_error = None;
+        Ok(())
+    }
+
+    fn maintenance_error(&self) -> Option<String> {
+        self.inner.lock().unwrap().snapshot_error.clone()
+    }
+}
@@ -1,4 +1,5 @@
 use super::*;
+use std::sync::Arc;
 
 pub trait DispatchStrategy: Send + Sync {
     fn select_driver<'a>(
@@ -9,21 +10,77 @@ pub trait DispatchStrategy: Send + Sync {
     ) -> Option<&'a Driver>;
 
     fn name(&self) -> &str;
+
+    /// 给出配送员的分项评分，不基于评分选人的策略返回 None
+    fn score_breakdown(&self, _order: &Order, _driver: &Driver) -> Option<ScoreBreakdown> {
+        None
+    }
+
+    /// 按策略偏好从高到低排列配送员，被规则或策略拒绝的配送员不在结果中。
+    /// 默认反复调用 `select_driver`，内置策略按各自的排序键一次排好
+    fn rank_drivers<'a>(
+        &self,
+        order: &Order,
+        drivers: Vec<&'a Driver>,
+        rules: &[Box<dyn DispatchRule>],
+    ) -> Vec<&'a Driver> {
+        let mut remaining = drivers;
+        let mut ranked = Vec::new();
+        while let Some(driver) = self.select_driver(order, remaining.clone(), rules) {
+            ranked.push(driver);
+            remaining.retain(|d| d.id != driver.id);
+        }
+        ranked
+    }
+
+    /// 引擎设置策略或行程时间模型时传入自身的模型，返回改用该模型的策略副本；
+    /// 不依赖行程时间的策略返回 None。策略以 `Arc` 共享，不能像规则那样原地修改
+    fn bind_travel_model(
+        &self,
+        _model: Arc<dyn TravelTimeModel>,
+    ) -> Option<Arc<dyn DispatchStrategy>> {
+        None
+    }
+
+    /// 引擎设置策略或时钟时传入自身的时钟，返回改用该时钟的策略副本；
+    /// 不按时间打分的策略返回 None
+    fn bind_clock(&self, _clock: Arc<dyn Clock>) -> Option<Arc<dyn DispatchStrategy>> {
+        None
+    }
+}
+
+/// 通过所有规则的配送员
+fn eligible<'a>(
+    order: &Order,
+    drivers: Vec<&'a Driver>,
+    rules: &[Box<dyn DispatchRule>],
+) -> impl Iterator<Item = &'a Driver> {
+    drivers
+        .into_iter()
+        .filter(move |driver| rules.iter().all(|rule| rule.evaluate(order, driver)))
+}
+
+/// 按排序键从小到大排列，键相同时保持原有顺序
+fn rank_by(mut keyed: Vec<(&Driver, f64)>) -> Vec<&Driver> {
+    keyed.sort_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    keyed.into_iter().map(|(driver, _)| driver).collect()
 }
 
 /// 就近派单策略：选择距离订单取货点最近的司机
-#[derive(Debug)]
-pub struct NearestFirstStrategy;
+#[derive(Clone, Default)]
+pub struct NearestFirstStrategy {
+    /// 设置后按道路距离而不是直线距离比较，加入引擎后使用引擎的行程时间模型
+    travel_model: Option<Arc<dyn TravelTimeModel>>,
+}
 
 impl NearestFirstStrategy {
     pub fn new() -> Self {
-        Self
+        Self { travel_model: None }
     }
-}
 
-impl Default for NearestFirstStrategy {
-    fn default() -> Self {
-        Self::new()
+    pub fn with_travel_model(mut self, travel_model: Arc<dyn TravelTimeModel>) -> Self {
+        self.travel_model = Some(travel_model);
+        self
     }
 }
 
@@ -34,21 +91,35 @@ impl DispatchStrategy for NearestFirstStrategy {
         drivers: Vec<&'a Driver>,
         rules: &[Box<dyn DispatchRule>],
     ) -> Option<&'a Driver> {
-        drivers
-            .into_iter()
-            .filter(|driver| rules.iter().all(|rule| rule.evaluate(order, driver)))
-            .min_by(|a, b| {
-                let dist_a = a.distance_to(order.pickup_location);
-                let dist_b = b.distance_to(order.pickup_location);
-                dist_a
-                    .partial_cmp(&dist_b)
-                    .unwrap_or(std::cmp::Ordering::Equal)
-            })
+        self.rank_drivers(order, drivers, rules).into_iter().next()
     }
 
     fn name(&self) -> &str {
         "NearestFirst"
     }
+
+    fn rank_drivers<'a>(
+        &self,
+        order: &Order,
+        drivers: Vec<&'a Driver>,
+        rules: &[Box<dyn DispatchRule>],
+    ) -> Vec<&'a Driver> {
+        rank_by(
+            eligible(order, drivers, rules)
+                .map(|driver| {
+                    let distance = pickup_distance_km(self.travel_model.as_deref(), order, driver);
+                    (driver, distance)
+                })
+                .collect(),
+        )
+    }
+
+    fn bind_travel_model(
+        &self,
+        model: Arc<dyn TravelTimeModel>,
+    ) -> Option<Arc<dyn DispatchStrategy>> {
+        Some(Arc::new(self.clone().with_travel_model(model)))
+    }
 }
 
 /// 负载均衡策略：优先分配给负载率最低的司机
@@ -74,19 +145,25 @@ impl DispatchStrategy for LoadBalancedStrategy {
         drivers: Vec<&'a Driver>,
         rules: &[Box<dyn DispatchRule>],
     ) -> Option<&'a Driver> {
-        drivers
-            .into_iter()
-            .filter(|driver| rules.iter().all(|rule| rule.evaluate(order, driver)))
-            .min_by(|a, b| {
-                a.load_ratio()
-                    .partial_cmp(&b.load_ratio())
-                    .unwrap_or(std::cmp::Ordering::Equal)
-            })
+        self.rank_drivers(order, drivers, rules).into_iter().next()
     }
 
     fn name(&self) -> &str {
         "LoadBalanced"
     }
+
+    fn rank_drivers<'a>(
+        &self,
+        order: &Order,
+        drivers: Vec<&'a Driver>,
+        rules: &[Box<dyn DispatchRule>],
+    ) -> Vec<&'a Driver> {
+        rank_by(
+            eligible(order, drivers, rules)
+                .map(|driver| (driver, driver.load_ratio()))
+                .collect(),
+        )
+    }
 }
 
 /// 高评分优先策略：优先分配给评分高的司机
@@ -112,11 +189,20 @@ impl DispatchStrategy for RatingPriorityStrategy {
         drivers: Vec<&'a Driver>,
         rules: &[Box<dyn DispatchRule>],
     ) -> Option<&'a Driver> {
-        let mut eligible: Vec<_> = drivers
-            .into_iter()
-            .filter(|driver| rules.iter().all(|rule| rule.evaluate(order, driver)))
-            .collect();
+        self.rank_drivers(order, drivers, rules).into_iter().next()
+    }
 
+    fn name(&self) -> &str {
+        "RatingPriority"
+    }
+
+    fn rank_drivers<'a>(
+        &self,
+        order: &Order,
+        drivers: Vec<&'a Driver>,
+        rules: &[Box<dyn DispatchRule>],
+    ) -> Vec<&'a Driver> {
+        let mut eligible: Vec<_> = eligible(order, drivers, rules).collect();
         eligible.sort_by(|a, b| {
             b.rating
                 .partial_cmp(&a.rating)
@@ -127,28 +213,25 @@ impl DispatchStrategy for RatingPriorityStrategy {
                         .unwrap_or(std::cmp::Ordering::Equal)
                 })
         });
-
-        eligible.first().copied()
-    }
-
-    fn name(&self) -> &str {
-        "RatingPriority"
+        eligible
     }
 }
 
 /// 运力匹配策略：根据订单类型选择合适的车辆
-#[derive(Debug)]
-pub struct CapacityMatchStrategy;
+#[derive(Clone, Default)]
+pub struct CapacityMatchStrategy {
+    /// 设置后按道路距离而不是直线距离比较，加入引擎后使用引擎的行程时间模型
+    travel_model: Option<Arc<dyn TravelTimeModel>>,
+}
 
 impl CapacityMatchStrategy {
     pub fn new() -> Self {
-        Self
+        Self { travel_model: None }
     }
-}
 
-impl Default for CapacityMatchStrategy {
-    fn default() -> Self {
-        Self::new()
+    pub fn with_travel_model(mut self, travel_model: Arc<dyn TravelTimeModel>) -> Self {
+        self.travel_model = Some(travel_model);
+        self
     }
 }
 
@@ -159,39 +242,341 @@ impl DispatchStrategy for CapacityMatchStrategy {
         drivers: Vec<&'a Driver>,
         rules: &[Box<dyn DispatchRule>],
     ) -> Option<&'a Driver> {
-        let eligible: Vec<_> = drivers
-            .into_iter()
-            .filter(|driver| rules.iter().all(|rule| rule.evaluate(order, driver)))
+        self.rank_drivers(order, drivers, rules).into_iter().next()
+    }
+
+    fn name(&self) -> &str {
+        "CapacityMatch"
+    }
+
+    fn rank_drivers<'a>(
+        &self,
+        order: &Order,
+        drivers: Vec<&'a Driver>,
+        rules: &[Box<dyn DispatchRule>],
+    ) -> Vec<&'a Driver> {
+        // 如果需要大型车辆，大车排在前面，同组内按距离从近到远
+        let large_first = order.order_type.requires_large_vehicle();
+        let fits = |driver: &Driver| !large_first || driver.vehicle_type.can_carry_large_items();
+        let mut keyed: Vec<_> = eligible(order, drivers, rules)
+            .map(|driver| {
+                let distance = pickup_distance_km(self.travel_model.as_deref(), order, driver);
+                (driver, distance)
+            })
             .collect();
+        keyed.sort_by(|(a, dist_a), (b, dist_b)| {
+            fits(b).cmp(&fits(a)).then_with(|| {
+                dist_a
+                    .partial_cmp(dist_b)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+        });
+        keyed.into_iter().map(|(driver, _)| driver).collect()
+    }
+
+    fn bind_travel_model(
+        &self,
+        model: Arc<dyn TravelTimeModel>,
+    ) -> Option<Arc<dyn DispatchStrategy>> {
+        Some(Arc::new(self.clone().with_travel_model(model)))
+    }
+}
+
+/// 加权评分的各项因子，每项均归一化到 [0, 1]，越大越好
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ScoreBreakdown {
+    pub distance: f64,
+    pub load: f64,
+    pub rating: f64,
+    pub vehicle_fit: f64,
+    pub idle_time: f64,
+    /// 加权后的综合得分
+    pub total: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ScoreWeights {
+    pub distance: f64,
+    pub load: f64,
+    pub rating: f64,
+    pub vehicle_fit: f64,
+    pub idle_time: f64,
+}
+
+impl Default for ScoreWeights {
+    fn default() -> Self {
+        Self {
+            distance: 0.4,
+            load: 0.2,
+            rating: 0.2,
+            vehicle_fit: 0.1,
+            idle_time: 0.1,
+        }
+    }
+}
+
+/// 加权评分策略：综合距离、负载、评分、车型匹配度和空闲时长打分，选择得分最高的司机
+#[derive(Clone)]
+pub struct WeightedScoreStrategy {
+    pub weights: ScoreWeights,
+    /// 距离归一化上限，超过该距离的距离得分为 0
+    pub max_distance_km: f64,
+    /// 空闲时长归一化上限，超过该时长的空闲得分为 1
+    pub max_idle_minutes: f64,
+    /// 设置后距离得分按道路距离计算，加入引擎后使用引擎的行程时间模型
+    travel_model: Option<Arc<dyn TravelTimeModel>>,
+    /// 计算空闲时长使用的时钟
+    clock: Arc<dyn Clock>,
+}
+
+impl WeightedScoreStrategy {
+    pub fn new(weights: ScoreWeights) -> Self {
+        Self {
+            weights,
+            max_distance_km: 10.0,
+            max_idle_minutes: 30.0,
+            travel_model: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn with_travel_model(mut self, travel_model: Arc<dyn TravelTimeModel>) -> Self {
+        self.travel_model = Some(travel_model);
+        self
+    }
 
-        // 如果需要大型车辆，优先选择大车
-        let preferred = if order.order_type.requires_large_vehicle() {
-            eligible
-                .iter()
-                .filter(|d| d.vehicle_type.can_carry_large_items())
-                .copied()
-                .collect::<Vec<_>>()
+    pub fn with_max_distance_km(mut self, max_distance_km: f64) -> Self {
+        self.max_distance_km = max_distance_km;
+        self
+    }
+
+    pub fn with_max_idle_minutes(mut self, max_idle_minutes: f64) -> Self {
+        self.max_idle_minutes = max_idle_minutes;
+        self
+    }
+
+    pub fn score(&self, order: &Order, driver: &Driver) -> ScoreBreakdown {
+        let now = self.clock.now();
+        let distance = 1.0
+            - normalize(
+                pickup_distance_km(self.travel_model.as_deref(), order, driver),
+                self.max_distance_km,
+            );
+        let load = 1.0 - driver.load_ratio().clamp(0.0, 1.0);
+        let rating = (driver.rating / 5.0).clamp(0.0, 1.0);
+        let vehicle_fit = vehicle_fit(&order.order_type, &driver.vehicle_type);
+        let idle_time = normalize(driver.idle_minutes(now), self.max_idle_minutes);
+
+        let w = &self.weights;
+        let weight_sum = w.distance + w.load + w.rating + w.vehicle_fit + w.idle_time;
+        let total = if weight_sum > 0.0 {
+            (w.distance * distance
+                + w.load * load
+                + w.rating * rating
+                + w.vehicle_fit * vehicle_fit
+                + w.idle_time * idle_time)
+                / weight_sum
         } else {
-            eligible.clone()
+            0.0
         };
 
-        let candidates = if preferred.is_empty() {
-            eligible
+        ScoreBreakdown {
+            distance,
+            load,
+            rating,
+            vehicle_fit,
+            idle_time,
+            total,
+        }
+    }
+}
+
+impl Default for WeightedScoreStrategy {
+    fn default() -> Self {
+        Self::new(ScoreWeights::default())
+    }
+}
+
+/// 配送员到取货点的距离，给定行程时间模型时取道路距离
+fn pickup_distance_km(model: Option<&dyn TravelTimeModel>, order: &Order, driver: &Driver) -> f64 {
+    match model {
+        Some(model) => model.distance_km(driver.current_location, order.pickup_location),
+        None => driver.distance_to(order.pickup_location),
+    }
+}
+
+fn normalize(value: f64, max: f64) -> f64 {
+    if max <= 0.0 {
+        0.0
+    } else {
+        (value / max).clamp(0.0, 1.0)
+    }
+}
+
+/// 车型匹配度：大件订单必须大车，其余订单车型越贴合得分越高
+fn vehicle_fit(order_type: &OrderType, vehicle_type: &VehicleType) -> f64 {
+    if order_type.requires_large_vehicle() {
+        return if vehicle_type.can_carry_large_items() {
+            1.0
         } else {
-            preferred
+            0.0
         };
+    }
 
-        // 在符合条件的司机中选择距离最近的
-        candidates.into_iter().min_by(|a, b| {
-            let dist_a = a.distance_to(order.pickup_location);
-            let dist_b = b.distance_to(order.pickup_location);
-            dist_a
-                .partial_cmp(&dist_b)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        })
+    match (order_type, vehicle_type) {
+        (OrderType::RideHailing, VehicleType::Car) => 1.0,
+        (OrderType::RideHailing, VehicleType::Van) => 0.8,
+        (OrderType::RideHailing, _) => 0.3,
+        (_, VehicleType::ElectricBike | VehicleType::Motorcycle) => 1.0,
+        (_, VehicleType::Car) => 0.6,
+        (_, VehicleType::Van) => 0.4,
+        (_, VehicleType::Truck) => 0.2,
+    }
+}
+
+impl DispatchStrategy for WeightedScoreStrategy {
+    fn select_driver<'a>(
+        &self,
+        order: &Order,
+        drivers: Vec<&'a Driver>,
+        rules: &[Box<dyn DispatchRule>],
+    ) -> Option<&'a Driver> {
+        self.rank_drivers(order, drivers, rules).into_iter().next()
     }
 
     fn name(&self) -> &str {
-        "CapacityMatch"
+        "WeightedScore"
+    }
+
+    fn rank_drivers<'a>(
+        &self,
+        order: &Order,
+        drivers: Vec<&'a Driver>,
+        rules: &[Box<dyn DispatchRule>],
+    ) -> Vec<&'a Driver> {
+        rank_by(
+            eligible(order, drivers, rules)
+                .map(|driver| (driver, -self.score(order, driver).total))
+                .collect(),
+        )
+    }
+
+    fn score_breakdown(&self, order: &Order, driver: &Driver) -> Option<ScoreBreakdown> {
+        Some(self.score(order, driver))
+    }
+
+    fn bind_travel_model(
+        &self,
+        model: Arc<dyn TravelTimeModel>,
+    ) -> Option<Arc<dyn DispatchStrategy>> {
+        Some(Arc::new(self.clone().with_travel_model(model)))
+    }
+
+    fn bind_clock(&self, clock: Arc<dyn Clock>) -> Option<Arc<dyn DispatchStrategy>> {
+        Some(Arc::new(self.clone().with_clock(clock)))
+    }
+}
+
+/// 拼单策略：评估把新订单插入配送员现有路线的绕路成本，
+/// 只在绕路时间不超过阈值时派单，并选择绕路最少的配送员
+#[derive(Clone)]
+pub struct PoolingStrategy {
+    /// 允许增加的最大时间（分钟），不含订单自身的取送行程
+    pub max_detour_minutes: f64,
+    /// 估算绕路时间使用的行程时间模型，加入引擎后使用引擎的模型
+    travel_model: Arc<dyn TravelTimeModel>,
+    /// 绕路时间按当前时刻出发估算
+    clock: Arc<dyn Clock>,
+}
+
+impl PoolingStrategy {
+    pub fn new(max_detour_minutes: f64) -> Self {
+        Self {
+            max_detour_minutes,
+            travel_model: Arc::new(ConstantSpeedModel::default()),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn with_travel_model(mut self, travel_model: Arc<dyn TravelTimeModel>) -> Self {
+        self.travel_model = travel_model;
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 把订单插入配送员路线增加的时间（分钟），不含订单自身的取送行程。
+    /// 不可拼单的订单只能派给路线为空的配送员，否则返回 None
+    pub fn detour_minutes(&self, order: &Order, driver: &Driver) -> Option<f64> {
+        if !order.order_type.is_poolable() && !driver.planned_stops.is_empty() {
+            return None;
+        }
+
+        let model = self.travel_model.as_ref();
+        let start = driver.current_location;
+        let vehicle = &driver.vehicle_type;
+        let now = self.clock.now();
+        let insertion = best_insertion(model, start, &driver.planned_stops, order, vehicle, now);
+        let trip =
+            model.travel_minutes(order.pickup_location, order.delivery_location, vehicle, now);
+        Some((insertion.added_minutes - trip).max(0.0))
+    }
+}
+
+impl Default for PoolingStrategy {
+    fn default() -> Self {
+        Self::new(10.0)
+    }
+}
+
+impl DispatchStrategy for PoolingStrategy {
+    fn select_driver<'a>(
+        &self,
+        order: &Order,
+        drivers: Vec<&'a Driver>,
+        rules: &[Box<dyn DispatchRule>],
+    ) -> Option<&'a Driver> {
+        self.rank_drivers(order, drivers, rules).into_iter().next()
+    }
+
+    fn name(&self) -> &str {
+        "Pooling"
+    }
+
+    fn rank_drivers<'a>(
+        &self,
+        order: &Order,
+        drivers: Vec<&'a Driver>,
+        rules: &[Box<dyn DispatchRule>],
+    ) -> Vec<&'a Driver> {
+        rank_by(
+            eligible(order, drivers, rules)
+                .filter_map(|driver| {
+                    self.detour_minutes(order, driver)
+                        .filter(|detour| *detour <= self.max_detour_minutes)
+                        .map(|detour| (driver, detour))
+                })
+                .collect(),
+        )
+    }
+
+    fn bind_travel_model(
+        &self,
+        model: Arc<dyn TravelTimeModel>,
+    ) -> Option<Arc<dyn DispatchStrategy>> {
+        Some(Arc::new(self.clone().with_travel_model(model)))
+    }
+
+    fn bind_clock(&self, clock: Arc<dyn Clock>) -> Option<Arc<dyn DispatchStrategy>> {
+        Some(Arc::new(self.clone().with_clock(clock)))
     }
 }
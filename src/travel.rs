@@ -0,0 +1,243 @@
+use super::*;
+
+/// 行程时间模型：估算两点间的实际行驶距离和耗时
+pub trait TravelTimeModel: Send + Sync {
+    /// 两点间的实际行驶距离（公里）
+    fn distance_km(&self, from: Location, to: Location) -> f64;
+
+    /// 使用 `vehicle` 在 `departure_at` 时刻出发所需的行驶时间（分钟）
+    fn travel_minutes(
+        &self,
+        from: Location,
+        to: Location,
+        vehicle: &VehicleType,
+        departure_at: i64,
+    ) -> f64;
+
+    fn name(&self) -> &str;
+}
+
+/// 匀速直线模型：忽略车型、路网和时段，按固定速度走直线
+#[derive(Debug, Clone)]
+pub struct ConstantSpeedModel {
+    pub speed_kmh: f64,
+}
+
+impl ConstantSpeedModel {
+    pub fn new(speed_kmh: f64) -> Self {
+        Self { speed_kmh }
+    }
+}
+
+impl Default for ConstantSpeedModel {
+    fn default() -> Self {
+        Self::new(30.0)
+    }
+}
+
+impl TravelTimeModel for ConstantSpeedModel {
+    fn distance_km(&self, from: Location, to: Location) -> f64 {
+        distance_km(from, to)
+    }
+
+    fn travel_minutes(
+        &self,
+        from: Location,
+        to: Location,
+        _vehicle: &VehicleType,
+        _departure_at: i64,
+    ) -> f64 {
+        self.distance_km(from, to) / self.speed_kmh * 60.0
+    }
+
+    fn name(&self) -> &str {
+        "ConstantSpeed"
+    }
+}
+
+/// 分时段拥堵曲线：按当地小时给出车速系数，1.0 为畅通
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CongestionCurve {
+    /// 当地时间相对 UTC 的小时偏移
+    pub utc_offset_hours: i32,
+    /// 0-23 点的车速系数
+    pub hourly_speed_factors: [f64; 24],
+}
+
+impl CongestionCurve {
+    /// 全天畅通
+    pub fn free_flow() -> Self {
+        Self {
+            utc_offset_hours: 0,
+            hourly_speed_factors: [1.0; 24],
+        }
+    }
+
+    pub fn speed_factor(&self, timestamp: i64) -> f64 {
+        let local_secs = timestamp + self.utc_offset_hours as i64 * 3600;
+        let hour = local_secs.div_euclid(3600).rem_euclid(24) as usize;
+        self.hourly_speed_factors[hour]
+    }
+
+    /// 车型实际受到的车速系数。两轮车可以穿行拥堵路段，受高峰影响较小
+    pub fn vehicle_speed_factor(&self, vehicle: &VehicleType, timestamp: i64) -> f64 {
+        let slowdown = 1.0 - self.speed_factor(timestamp);
+        1.0 - slowdown * vehicle.congestion_sensitivity()
+    }
+}
+
+impl Default for CongestionCurve {
+    /// 北京时间的典型城市曲线：早晚高峰明显变慢，深夜略快
+    fn default() -> Self {
+        Self {
+            utc_offset_hours: 8,
+            hourly_speed_factors: std::array::from_fn(|hour| match hour {
+                0..=5 => 1.2,
+                6 | 20 | 21 => 1.0,
+                7..=9 => 0.6,
+                17..=19 => 0.55,
+                22 | 23 => 1.1,
+                _ => 0.9,
+            }),
+        }
+    }
+}
+
+/// 路网模型：直线距离乘以绕行系数，按车型速度和分时段拥堵估算耗时
+#[derive(Debug, Clone)]
+pub struct RoadTravelModel {
+    /// 实际道路距离与直线距离之比
+    pub detour_factor: f64,
+    pub congestion: CongestionCurve,
+    vehicle_speeds: HashMap<VehicleType, f64>,
+}
+
+impl RoadTravelModel {
+    pub fn new(detour_factor: f64) -> Self {
+        Self {
+            detour_factor,
+            congestion: CongestionCurve::default(),
+            vehicle_speeds: HashMap::new(),
+        }
+    }
+
+    pub fn with_congestion(mut self, congestion: CongestionCurve) -> Self {
+        self.congestion = congestion;
+        self
+    }
+
+    /// 覆盖某种车型的畅通时速度
+    pub fn with_vehicle_speed(mut self, vehicle_type: VehicleType, speed_kmh: f64) -> Self {
+        self.vehicle_speeds.insert(vehicle_type, speed_kmh);
+        self
+    }
+
+    pub fn vehicle_speed_kmh(&self, vehicle_type: &VehicleType) -> f64 {
+        self.vehicle_speeds
+            .get(vehicle_type)
+            .copied()
+            .unwrap_or_else(|| vehicle_type.average_speed_kmh())
+    }
+}
+
+impl Default for RoadTravelModel {
+    fn default() -> Self {
+        Self::new(1.3)
+    }
+}
+
+impl TravelTimeModel for RoadTravelModel {
+    fn distance_km(&self, from: Location, to: Location) -> f64 {
+        distance_km(from, to) * self.detour_factor
+    }
+
+    fn travel_minutes(
+        &self,
+        from: Location,
+        to: Location,
+        vehicle: &VehicleType,
+        departure_at: i64,
+    ) -> f64 {
+        let speed_kmh = self.vehicle_speed_kmh(vehicle)
+            * self.congestion.vehicle_speed_factor(vehicle, departure_at);
+        self.distance_km(from, to) / speed_kmh * 60.0
+    }
+
+    fn name(&self) -> &str {
+        "Road"
+    }
+}
+
+/// 配送员承接订单后的时间预估
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeliveryEstimate {
+    /// 到达取货点的分钟数
+    pub pickup_eta_minutes: u32,
+    /// 从取货点到送达点的分钟数
+    pub trip_minutes: u32,
+    /// 预计送达时间戳
+    pub delivered_at: i64,
+}
+
+/// 按行程时间模型估算 `driver` 从 `now` 出发完成 `order` 的时间。
+/// 配送员已有路线时沿路线依次经过各停靠点：订单已在路线上则按现有路线估算，
+/// 否则按最佳插入位置并入路线
+pub fn estimate_delivery(
+    model: &dyn TravelTimeModel,
+    order: &Order,
+    driver: &Driver,
+    now: i64,
+) -> DeliveryEstimate {
+    let route = if driver
+        .planned_stops
+        .iter()
+        .any(|stop| stop.order_id == order.id)
+    {
+        driver.planned_stops.clone()
+    } else {
+        let insertion = best_insertion(
+            model,
+            driver.current_location,
+            &driver.planned_stops,
+            order,
+            &driver.vehicle_type,
+            now,
+        );
+        apply_insertion(&driver.planned_stops, order, &insertion)
+    };
+
+    let vehicle = &driver.vehicle_type;
+    let mut current = driver.current_location;
+    // 从 `departed_at` 出发后累计的行驶分钟数
+    let mut departed_at = now;
+    let mut elapsed = 0.0;
+    let mut pickup = None;
+    for stop in &route {
+        let at = departed_at + (elapsed * 60.0) as i64;
+        elapsed += model.travel_minutes(current, stop.location, vehicle, at);
+        current = stop.location;
+        if stop.order_id != order.id {
+            continue;
+        }
+        match stop.kind {
+            StopKind::Pickup => {
+                let pickup_eta_minutes = elapsed.ceil() as u32;
+                let pickup_at = order.pickup_at(now, pickup_eta_minutes);
+                pickup = Some((pickup_eta_minutes, pickup_at));
+                // 等待出餐后从取货点重新计时
+                departed_at = pickup_at;
+                elapsed = 0.0;
+            }
+            StopKind::Delivery => break,
+        }
+    }
+
+    // 路线上没有取货点（已取货）时从当前位置直接出发
+    let (pickup_eta_minutes, pickup_at) = pickup.unwrap_or((0, now));
+    let trip_minutes = elapsed.ceil() as u32;
+    DeliveryEstimate {
+        pickup_eta_minutes,
+        trip_minutes,
+        delivered_at: pickup_at + trip_minutes as i64 * 60,
+    }
+}
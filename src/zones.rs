@@ -0,0 +1,289 @@
+use super::*;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+/// 判断两个顶点重合的经纬度误差
+const VERTEX_EPSILON_DEG: f64 = 1e-7;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ZoneError {
+    #[error("Failed to read zones: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid GeoJSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Invalid zone definition: {0}")]
+    Invalid(String),
+}
+
+/// 多边形，外环加可选的内环（洞）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Polygon {
+    pub exterior: Vec<Location>,
+    #[serde(default)]
+    pub holes: Vec<Vec<Location>>,
+}
+
+impl Polygon {
+    pub fn new(exterior: Vec<Location>) -> Self {
+        Self {
+            exterior,
+            holes: Vec::new(),
+        }
+    }
+
+    pub fn contains(&self, location: Location) -> bool {
+        ring_contains(&self.exterior, location)
+            && !self.holes.iter().any(|hole| ring_contains(hole, location))
+    }
+}
+
+/// 射线法判断点是否在环内，经度为 x、纬度为 y
+fn ring_contains(ring: &[Location], location: Location) -> bool {
+    let (x, y) = (location.longitude, location.latitude);
+    let mut inside = false;
+    let mut j = ring.len().wrapping_sub(1);
+    for i in 0..ring.len() {
+        let (xi, yi) = (ring[i].longitude, ring[i].latitude);
+        let (xj, yj) = (ring[j].longitude, ring[j].latitude);
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// 服务区
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Zone {
+    pub id: String,
+    pub name: String,
+    pub polygons: Vec<Polygon>,
+    /// 相邻服务区，配送员可以跨到相邻服务区接单
+    pub neighbors: BTreeSet<String>,
+}
+
+impl Zone {
+    pub fn new(id: impl Into<String>, name: impl Into<String>, polygon: Polygon) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            polygons: vec![polygon],
+            neighbors: BTreeSet::new(),
+        }
+    }
+
+    pub fn with_neighbor(mut self, zone_id: impl Into<String>) -> Self {
+        self.neighbors.insert(zone_id.into());
+        self
+    }
+
+    pub fn contains(&self, location: Location) -> bool {
+        self.polygons
+            .iter()
+            .any(|polygon| polygon.contains(location))
+    }
+
+    /// 本服务区或相邻服务区
+    pub fn covers_zone(&self, zone_id: &str) -> bool {
+        self.id == zone_id || self.neighbors.contains(zone_id)
+    }
+
+    fn vertices(&self) -> impl Iterator<Item = Location> + '_ {
+        self.polygons
+            .iter()
+            .flat_map(|polygon| polygon.exterior.iter().copied())
+    }
+
+    /// 两个服务区有重合的顶点，或一方的顶点落在另一方内部
+    fn touches(&self, other: &Zone) -> bool {
+        self.vertices().any(|a| {
+            other.contains(a)
+                || other.vertices().any(|b| {
+                    (a.latitude - b.latitude).abs() < VERTEX_EPSILON_DEG
+                        && (a.longitude - b.longitude).abs() < VERTEX_EPSILON_DEG
+                })
+        })
+    }
+}
+
+/// 服务区集合。按位置查找服务区时返回第一个包含该位置的服务区
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ZoneMap {
+    zones: Vec<Zone>,
+}
+
+impl ZoneMap {
+    /// 除了显式声明的相邻关系，边界相接的服务区也互为相邻，相邻关系总是双向的
+    pub fn new(mut zones: Vec<Zone>) -> Self {
+        let mut pairs = Vec::new();
+        for (i, a) in zones.iter().enumerate() {
+            for b in &zones[i + 1..] {
+                if a.neighbors.contains(&b.id)
+                    || b.neighbors.contains(&a.id)
+                    || a.touches(b)
+                    || b.touches(a)
+                {
+                    pairs.push((a.id.clone(), b.id.clone()));
+                }
+            }
+        }
+        for (a, b) in pairs {
+            for zone in zones.iter_mut() {
+                if zone.id == a {
+                    zone.neighbors.insert(b.clone());
+                } else if zone.id == b {
+                    zone.neighbors.insert(a.clone());
+                }
+            }
+        }
+        Self { zones }
+    }
+
+    /// 读取 GeoJSON FeatureCollection，每个 Polygon / MultiPolygon 要素为一个服务区。
+    /// `properties` 中的 `id`、`name` 和 `neighbors` 均可省略
+    pub fn from_geojson_str(content: &str) -> Result<Self, ZoneError> {
+        let value: Value = serde_json::from_str(content)?;
+        let features = match value["type"].as_str() {
+            Some("FeatureCollection") => value["features"]
+                .as_array()
+                .cloned()
+                .ok_or_else(|| invalid("FeatureCollection without features"))?,
+            Some("Feature") => vec![value],
+            other => return Err(invalid(&format!("unsupported GeoJSON type {:?}", other))),
+        };
+
+        let mut zones = Vec::with_capacity(features.len());
+        for (i, feature) in features.iter().enumerate() {
+            let properties = &feature["properties"];
+            let id = match (&properties["id"], &feature["id"]) {
+                (Value::String(id), _) | (_, Value::String(id)) => id.clone(),
+                (Value::Number(id), _) | (_, Value::Number(id)) => id.to_string(),
+                _ => format!("zone-{}", i + 1),
+            };
+            let name = properties["name"].as_str().unwrap_or(&id).to_string();
+            let polygons = parse_geometry(&feature["geometry"])
+                .map_err(|message| invalid(&format!("zone {}: {}", id, message)))?;
+            let neighbors = properties["neighbors"]
+                .as_array()
+                .map(|ids| {
+                    ids.iter()
+                        .filter_map(|id| id.as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            zones.push(Zone {
+                id,
+                name,
+                polygons,
+                neighbors,
+            });
+        }
+
+        let mut ids = BTreeSet::new();
+        for zone in &zones {
+            if !ids.insert(zone.id.as_str()) {
+                return Err(invalid(&format!("duplicate zone id {}", zone.id)));
+            }
+        }
+        Ok(Self::new(zones))
+    }
+
+    pub fn from_geojson_file(path: impl AsRef<Path>) -> Result<Self, ZoneError> {
+        Self::from_geojson_str(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn zones(&self) -> &[Zone] {
+        &self.zones
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.zones.is_empty()
+    }
+
+    pub fn get(&self, zone_id: &str) -> Option<&Zone> {
+        self.zones.iter().find(|zone| zone.id == zone_id)
+    }
+
+    pub fn zone_at(&self, location: Location) -> Option<&Zone> {
+        self.zones.iter().find(|zone| zone.contains(location))
+    }
+
+    /// 配送员所属服务区：优先取当前位置所在的服务区，
+    /// 位于所有服务区之外时才使用指定的 `zone_id`
+    pub fn driver_zone<'a>(&'a self, driver: &'a Driver) -> Option<&'a str> {
+        self.zone_at(driver.current_location)
+            .map(|zone| zone.id.as_str())
+            .or(driver.zone_id.as_deref())
+    }
+}
+
+fn invalid(message: &str) -> ZoneError {
+    ZoneError::Invalid(message.to_string())
+}
+
+fn parse_geometry(geometry: &Value) -> Result<Vec<Polygon>, String> {
+    let coordinates = &geometry["coordinates"];
+    let polygons = match geometry["type"].as_str() {
+        Some("Polygon") => vec![parse_polygon(coordinates)?],
+        Some("MultiPolygon") => coordinates
+            .as_array()
+            .ok_or("MultiPolygon without coordinates")?
+            .iter()
+            .map(parse_polygon)
+            .collect::<Result<_, _>>()?,
+        other => return Err(format!("unsupported geometry {:?}", other)),
+    };
+    // 没有多边形的服务区无法判断归属，也无法在其中取点
+    if polygons.is_empty() {
+        return Err("MultiPolygon without polygons".to_string());
+    }
+    Ok(polygons)
+}
+
+fn parse_polygon(rings: &Value) -> Result<Polygon, String> {
+    let mut rings = rings
+        .as_array()
+        .ok_or("polygon without rings")?
+        .iter()
+        .map(parse_ring);
+    let exterior = rings.next().ok_or("polygon without exterior ring")??;
+    let holes = rings.collect::<Result<_, _>>()?;
+    Ok(Polygon { exterior, holes })
+}
+
+/// GeoJSON 坐标顺序为 [经度, 纬度]
+fn parse_ring(ring: &Value) -> Result<Vec<Location>, String> {
+    let points = ring
+        .as_array()
+        .ok_or("ring is not an array")?
+        .iter()
+        .map(|point| match (point[0].as_f64(), point[1].as_f64()) {
+            (Some(longitude), Some(latitude)) => Ok(Location::new(latitude, longitude)),
+            _ => Err(format!("invalid position {}", point)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if points.len() < 3 {
+        return Err("ring needs at least 3 positions".to_string());
+    }
+    Ok(points)
+}
+
+/// 单个服务区的运营统计，订单按取货点归属服务区
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ZoneStats {
+    pub zone_id: String,
+    pub name: String,
+    pub drivers: usize,
+    pub available_drivers: usize,
+    /// 等待队列中的订单
+    pub pending_orders: usize,
+    /// 已派单、已取货或配送中的订单
+    pub active_orders: usize,
+    pub delivered_orders: usize,
+    pub cancelled_orders: usize,
+}
@@ -38,19 +38,19 @@ fn test_load_balanced_strategy() {
     let strategy = Arc::new(LoadBalancedStrategy::new());
     let engine = DispatchEngine::new(strategy);
 
-    let mut driver1 = Driver::new(
+    let driver1 = Driver::new(
         "空闲司机".to_string(),
         VehicleType::ElectricBike,
         Location::new(39.9042, 116.4074),
     );
-    driver1.current_load = 0;
 
     let mut driver2 = Driver::new(
         "忙碌司机".to_string(),
         VehicleType::ElectricBike,
         Location::new(39.9042, 116.4074),
     );
-    driver2.current_load = 2;
+    driver2.assign_order(uuid::Uuid::new_v4());
+    driver2.assign_order(uuid::Uuid::new_v4());
 
     engine.add_driver(driver1);
     engine.add_driver(driver2);
@@ -161,8 +161,8 @@ fn test_capacity_exceeded() {
         Location::new(39.9042, 116.4074),
     );
     driver.capacity = 1;
-    driver.current_load = 1; // 已满载
-    driver.status = DriverStatus::Busy;
+    driver.assign_order(uuid::Uuid::new_v4()); // 已满载
+    assert_eq!(driver.status, DriverStatus::Busy);
 
     engine.add_driver(driver);
 
@@ -220,12 +220,28 @@ fn test_driver_release() {
 
     // 检查负载
     let driver_stats = engine.get_driver_stats(&driver_id).unwrap();
-    assert_eq!(driver_stats.current_load, 1);
+    assert_eq!(driver_stats.current_load(), 1);
+    assert!(driver_stats.has_order(&order.id));
+
+    // 未取货的订单不能释放
+    assert!(matches!(
+        engine.release_order(&driver_id, &order.id),
+        Err(DispatchError::InvalidOperation(_))
+    ));
+    assert_eq!(
+        engine.get_order(&order.id).unwrap().status,
+        OrderStatus::Dispatched
+    );
 
-    // 释放订单
-    engine.release_order(&driver_id).unwrap();
+    // 取货后释放订单
+    engine.mark_picked_up(&order.id).unwrap();
+    engine.release_order(&driver_id, &order.id).unwrap();
     let driver_stats = engine.get_driver_stats(&driver_id).unwrap();
-    assert_eq!(driver_stats.current_load, 0);
+    assert_eq!(driver_stats.current_load(), 0);
+    assert_eq!(
+        engine.get_order(&order.id).unwrap().status,
+        OrderStatus::Delivered
+    );
 }
 
 #[test]
@@ -251,3 +267,2936 @@ fn test_update_driver_location() {
     assert_eq!(driver_stats.current_location.latitude, 39.9100);
     assert_eq!(driver_stats.current_location.longitude, 116.4100);
 }
+
+#[test]
+fn test_order_lifecycle() {
+    let strategy = Arc::new(NearestFirstStrategy::new());
+    let engine = DispatchEngine::new(strategy);
+
+    let driver = Driver::new(
+        "司机".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9042, 116.4074),
+    );
+    let driver_id = driver.id;
+    engine.add_driver(driver);
+
+    let order = Order::new(
+        OrderType::Food,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9150, 116.4180),
+    );
+    let order_id = engine.submit_order(order).unwrap();
+    assert_eq!(
+        engine.get_order(&order_id).unwrap().status,
+        OrderStatus::Pending
+    );
+
+    let result = engine.dispatch_order(&order_id).unwrap();
+    assert_eq!(result.driver_id, driver_id);
+    let order = engine.get_order(&order_id).unwrap();
+    assert_eq!(order.status, OrderStatus::Dispatched);
+    assert_eq!(order.assigned_to, Some(driver_id));
+
+    engine.mark_picked_up(&order_id).unwrap();
+    engine.mark_in_transit(&order_id).unwrap();
+    engine.mark_delivered(&order_id).unwrap();
+    assert_eq!(
+        engine.get_order(&order_id).unwrap().status,
+        OrderStatus::Delivered
+    );
+    assert_eq!(
+        engine.get_driver_stats(&driver_id).unwrap().current_load(),
+        0
+    );
+}
+
+#[test]
+fn test_invalid_order_transitions() {
+    let strategy = Arc::new(NearestFirstStrategy::new());
+    let engine = DispatchEngine::new(strategy);
+
+    engine.add_driver(Driver::new(
+        "司机".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9042, 116.4074),
+    ));
+
+    let mut order = Order::new(
+        OrderType::Food,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9150, 116.4180),
+    );
+    let order_id = order.id;
+
+    // 未派单的订单不能直接送达
+    engine.submit_order(order.clone()).unwrap();
+    assert!(matches!(
+        engine.mark_delivered(&order_id),
+        Err(DispatchError::InvalidOperation(_))
+    ));
+
+    // 同一订单不能重复派单
+    engine.dispatch(&mut order).unwrap();
+    assert!(matches!(
+        engine.dispatch(&mut order.clone()),
+        Err(DispatchError::InvalidOperation(_))
+    ));
+
+    // 已取货的订单不能取消
+    engine.mark_picked_up(&order_id).unwrap();
+    assert!(matches!(
+        engine.cancel_order(&order_id),
+        Err(DispatchError::InvalidOperation(_))
+    ));
+
+    assert!(matches!(
+        engine.mark_picked_up(&uuid::Uuid::new_v4()),
+        Err(DispatchError::OrderNotFound(_))
+    ));
+}
+
+#[test]
+fn test_cancel_dispatched_order_releases_driver() {
+    let strategy = Arc::new(NearestFirstStrategy::new());
+    let engine = DispatchEngine::new(strategy);
+
+    let driver = Driver::new(
+        "司机".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9042, 116.4074),
+    );
+    let driver_id = driver.id;
+    engine.add_driver(driver);
+
+    let mut order = Order::new(
+        OrderType::Food,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9150, 116.4180),
+    );
+    engine.dispatch(&mut order).unwrap();
+    assert_eq!(
+        engine.get_driver_stats(&driver_id).unwrap().current_load(),
+        1
+    );
+
+    engine.cancel_order(&order.id).unwrap();
+    assert_eq!(
+        engine.get_order(&order.id).unwrap().status,
+        OrderStatus::Cancelled
+    );
+    assert_eq!(
+        engine.get_driver_stats(&driver_id).unwrap().current_load(),
+        0
+    );
+}
+
+#[test]
+fn test_release_requires_assigned_order() {
+    let strategy = Arc::new(NearestFirstStrategy::new());
+    let engine = DispatchEngine::new(strategy);
+
+    let driver = Driver::new(
+        "司机".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9042, 116.4074),
+    );
+    let driver_id = driver.id;
+    engine.add_driver(driver);
+
+    // 没有派单时释放应报错
+    assert!(matches!(
+        engine.release_order(&driver_id, &uuid::Uuid::new_v4()),
+        Err(DispatchError::InvalidOperation(_))
+    ));
+
+    let mut order = Order::new(
+        OrderType::Food,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9150, 116.4180),
+    );
+    engine.dispatch(&mut order).unwrap();
+
+    // 释放其他订单不影响当前负载
+    assert!(
+        engine
+            .release_order(&driver_id, &uuid::Uuid::new_v4())
+            .is_err()
+    );
+    assert_eq!(
+        engine.get_driver_stats(&driver_id).unwrap().current_load(),
+        1
+    );
+
+    // 重复释放报错
+    engine.mark_picked_up(&order.id).unwrap();
+    engine.release_order(&driver_id, &order.id).unwrap();
+    assert!(matches!(
+        engine.release_order(&driver_id, &order.id),
+        Err(DispatchError::InvalidOperation(_))
+    ));
+}
+
+#[test]
+fn test_batch_dispatch_minimizes_total_distance() {
+    let strategy = Arc::new(NearestFirstStrategy::new());
+    let engine = DispatchEngine::new(strategy);
+
+    // 两个司机各只能接一单
+    let mut driver_a = Driver::new(
+        "A".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9000, 116.4000),
+    );
+    driver_a.capacity = 1;
+    let mut driver_b = Driver::new(
+        "B".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9000, 116.4300),
+    );
+    driver_b.capacity = 1;
+    engine.add_driver(driver_a);
+    engine.add_driver(driver_b);
+
+    // 逐单贪心会把订单1派给A，导致订单2只能由较远的B接单
+    let mut orders = vec![
+        Order::new(
+            OrderType::Food,
+            Location::new(39.9000, 116.4100),
+            Location::new(39.9100, 116.4100),
+        ),
+        Order::new(
+            OrderType::Food,
+            Location::new(39.9000, 116.3900),
+            Location::new(39.9100, 116.3900),
+        ),
+    ];
+
+    let results = engine.dispatch_batch(&mut orders);
+    let names: Vec<_> = results
+        .iter()
+        .map(|r| r.as_ref().unwrap().driver_name.clone())
+        .collect();
+    assert_eq!(names, vec!["B", "A"]);
+    assert!(orders.iter().all(|o| o.status == OrderStatus::Dispatched));
+}
+
+#[test]
+fn test_batch_dispatch_respects_capacity() {
+    let strategy = Arc::new(NearestFirstStrategy::new());
+    let engine = DispatchEngine::new(strategy);
+
+    let mut driver = Driver::new(
+        "司机".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9042, 116.4074),
+    );
+    driver.capacity = 1;
+    engine.add_driver(driver);
+
+    let mut orders: Vec<Order> = (0..2)
+        .map(|_| {
+            Order::new(
+                OrderType::Food,
+                Location::new(39.9050, 116.4080),
+                Location::new(39.9150, 116.4180),
+            )
+        })
+        .collect();
+
+    let results = engine.dispatch_batch(&mut orders);
+    assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 1);
+    assert!(
+        results
+            .iter()
+            .any(|r| matches!(r, Err(DispatchError::NoDriverAvailable)))
+    );
+    // 未要求入队时没派出的订单不进入等待队列
+    assert!(orders.iter().all(|o| !engine.is_pending(&o.id)));
+
+    // 同一订单在批次中出现两次，不会派给两个配送员
+    engine.add_driver(Driver::new(
+        "司机2".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9042, 116.4074),
+    ));
+    let order = Order::new(
+        OrderType::Food,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9150, 116.4180),
+    );
+    let mut duplicated = vec![order.clone(), order];
+    let results = engine.dispatch_batch(&mut duplicated);
+    assert!(results[0].is_ok());
+    assert!(matches!(
+        results[1],
+        Err(DispatchError::InvalidOperation(_))
+    ));
+
+    // 配送员都被规则拒绝时与逐单派单返回相同的错误，要求入队时才入队
+    let mut cargo = vec![Order::new(
+        OrderType::Cargo,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9150, 116.4180),
+    )];
+    let results = engine.dispatch_batch(&mut cargo.clone());
+    assert!(matches!(
+        &results[0],
+        Err(DispatchError::NoEligibleDriver(summary)) if summary.rejected_by.contains_key("VehicleTypeRule")
+    ));
+    assert!(!engine.is_pending(&cargo[0].id));
+    let results = engine.dispatch_batch_or_enqueue(&mut cargo);
+    assert!(matches!(
+        results[0],
+        Err(DispatchError::NoEligibleDriver(_))
+    ));
+    assert!(engine.is_pending(&cargo[0].id));
+}
+
+#[test]
+fn test_solve_assignment() {
+    let cost = vec![
+        vec![4.0, 1.0, 3.0],
+        vec![2.0, 0.0, 5.0],
+        vec![3.0, 2.0, 2.0],
+    ];
+    assert_eq!(solve_assignment(&cost), vec![Some(1), Some(0), Some(2)]);
+
+    // 行数多于列数
+    let cost = vec![vec![1.0], vec![0.5]];
+    assert_eq!(solve_assignment(&cost), vec![None, Some(0)]);
+}
+
+#[test]
+fn test_weighted_score_strategy() {
+    let near = Driver::new(
+        "近的司机".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9042, 116.4074),
+    );
+    let mut far = Driver::new(
+        "远的高分司机".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9300, 116.4300),
+    );
+    far.rating = 5.0;
+    let mut near_low = near.clone();
+    near_low.rating = 3.0;
+
+    let order = Order::new(
+        OrderType::Food,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9150, 116.4180),
+    );
+
+    // 只看距离时选近的
+    let by_distance = WeightedScoreStrategy::new(ScoreWeights {
+        distance: 1.0,
+        load: 0.0,
+        rating: 0.0,
+        vehicle_fit: 0.0,
+        idle_time: 0.0,
+    });
+    let selected = by_distance
+        .select_driver(&order, vec![&near_low, &far], &[])
+        .unwrap();
+    assert_eq!(selected.name, "近的司机");
+
+    // 只看评分时选高分的
+    let by_rating = WeightedScoreStrategy::new(ScoreWeights {
+        distance: 0.0,
+        load: 0.0,
+        rating: 1.0,
+        vehicle_fit: 0.0,
+        idle_time: 0.0,
+    });
+    let selected = by_rating
+        .select_driver(&order, vec![&near_low, &far], &[])
+        .unwrap();
+    assert_eq!(selected.name, "远的高分司机");
+
+    let breakdown = by_rating.score(&order, &far);
+    assert_eq!(breakdown.rating, 1.0);
+    assert_eq!(breakdown.total, 1.0);
+    assert!(breakdown.distance < 1.0);
+}
+
+#[test]
+fn test_weighted_score_breakdown_in_result() {
+    let engine = DispatchEngine::new(Arc::new(WeightedScoreStrategy::default()));
+    engine.add_driver(Driver::new(
+        "司机".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9042, 116.4074),
+    ));
+
+    let mut order = Order::new(
+        OrderType::Food,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9150, 116.4180),
+    );
+    let result = engine.dispatch(&mut order).unwrap();
+    let breakdown = result.score_breakdown.unwrap();
+    assert_eq!(breakdown.vehicle_fit, 1.0);
+    assert!(breakdown.total > 0.0 && breakdown.total <= 1.0);
+}
+
+#[test]
+fn test_dispatch_explain() {
+    let strategy = Arc::new(NearestFirstStrategy::new());
+    let engine = DispatchEngine::new(strategy).with_rules(vec![
+        Box::new(CapacityRule),
+        Box::new(DistanceRule::new(3.0)),
+        Box::new(VehicleTypeRule),
+    ]);
+
+    let far = Driver::new(
+        "远的司机".to_string(),
+        VehicleType::Car,
+        Location::new(40.0000, 116.5000),
+    );
+    let bike = Driver::new(
+        "骑手".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9042, 116.4074),
+    );
+    let near = Driver::new(
+        "近的司机".to_string(),
+        VehicleType::Van,
+        Location::new(39.9045, 116.4075),
+    );
+    let second = Driver::new(
+        "次近的司机".to_string(),
+        VehicleType::Car,
+        Location::new(39.9100, 116.4100),
+    );
+    let far_id = far.id;
+    let bike_id = bike.id;
+    engine.add_driver(far);
+    engine.add_driver(bike);
+    engine.add_driver(near);
+    engine.add_driver(second);
+
+    let order = Order::new(
+        OrderType::Express,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9150, 116.4180),
+    );
+    let explanation = engine.dispatch_explain(&order);
+
+    let rejected_by = |id| {
+        explanation
+            .rejections
+            .iter()
+            .find(|r| r.driver_id == id)
+            .unwrap()
+            .rejected_by
+            .clone()
+    };
+    assert_eq!(rejected_by(far_id), vec!["DistanceRule"]);
+    assert_eq!(rejected_by(bike_id), vec!["VehicleTypeRule"]);
+
+    let ranked: Vec<_> = explanation
+        .ranked
+        .iter()
+        .map(|r| r.driver_name.as_str())
+        .collect();
+    assert_eq!(ranked, vec!["近的司机", "次近的司机"]);
+    assert_eq!(explanation.summary.total_drivers, 4);
+    assert_eq!(explanation.summary.rejected_by["DistanceRule"], 1);
+
+    // 解释不会修改订单或配送员状态
+    assert!(engine.get_order(&order.id).is_err());
+    assert!(
+        engine
+            .get_all_drivers()
+            .iter()
+            .all(|d| d.current_load() == 0)
+    );
+}
+
+#[test]
+fn test_no_eligible_driver_error_carries_summary() {
+    let strategy = Arc::new(NearestFirstStrategy::new());
+    let engine = DispatchEngine::new(strategy).with_rules(vec![
+        Box::new(CapacityRule),
+        Box::new(DistanceRule::new(1.0)),
+    ]);
+    engine.add_driver(Driver::new(
+        "司机".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9042, 116.4074),
+    ));
+
+    let mut order = Order::new(
+        OrderType::Food,
+        Location::new(39.9200, 116.4300),
+        Location::new(39.9250, 116.4350),
+    );
+
+    match engine.dispatch(&mut order) {
+        Err(DispatchError::NoEligibleDriver(summary)) => {
+            assert_eq!(summary.total_drivers, 1);
+            assert_eq!(summary.rejected_by.get("DistanceRule"), Some(&1));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn test_no_driver_available_and_strategy_rejection() {
+    let engine = DispatchEngine::new(Arc::new(PoolingStrategy::new(1.0)));
+    let mut order = Order::new(
+        OrderType::Food,
+        Location::new(39.9000, 116.4000),
+        Location::new(39.9000, 116.4100),
+    );
+
+    // 没有任何配送员时仍返回 NoDriverAvailable
+    assert!(matches!(
+        engine.dispatch(&mut order),
+        Err(DispatchError::NoDriverAvailable)
+    ));
+
+    // 配送员距取货点约 8.5 公里，绕路超过阈值，被拼单策略拒绝
+    engine.add_driver(Driver::new(
+        "远的骑手".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9000, 116.5000),
+    ));
+    match engine.dispatch(&mut order) {
+        Err(DispatchError::NoEligibleDriver(summary)) => {
+            assert_eq!(summary.total_drivers, 1);
+            assert_eq!(summary.rejected_by.get("Pooling"), Some(&1));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn test_driver_pool_spatial_queries() {
+    let mut pool = DriverPool::new();
+    let center = Location::new(39.9042, 116.4074);
+
+    // 在中心点以东每隔约 0.85 公里放置一个司机
+    let mut ids = Vec::new();
+    for i in 0..10 {
+        let driver = Driver::new(
+            format!("司机{}", i),
+            VehicleType::ElectricBike,
+            Location::new(39.9042, 116.4074 + (i + 1) as f64 * 0.01),
+        );
+        ids.push(driver.id);
+        pool.add_driver(driver);
+    }
+
+    let nearest: Vec<_> = pool.nearest_k(center, 3).iter().map(|d| d.id).collect();
+    assert_eq!(nearest, ids[..3].to_vec());
+
+    let within: Vec<_> = pool
+        .within_radius(center, 2.0)
+        .iter()
+        .map(|d| d.id)
+        .collect();
+    assert_eq!(within, ids[..2].to_vec());
+
+    // 更新位置后索引同步
+    assert!(pool.update_location(&ids[9], center));
+    assert_eq!(pool.nearest_k(center, 1)[0].id, ids[9]);
+
+    pool.remove_driver(&ids[9]);
+    assert_eq!(pool.nearest_k(center, 1)[0].id, ids[0]);
+    assert_eq!(pool.nearest_k(center, 100).len(), 9);
+
+    // 超大半径或远离所有配送员的查询不会逐个扫描空网格
+    assert_eq!(pool.within_radius(center, f64::INFINITY).len(), 9);
+    assert_eq!(pool.within_radius(center, 1e7).len(), 9);
+    let far = Location::new(-33.86, 151.21);
+    assert_eq!(pool.nearest_k(far, 1)[0].id, ids[8]);
+}
+
+#[test]
+fn test_dispatch_uses_location_updates() {
+    let strategy = Arc::new(NearestFirstStrategy::new());
+    let engine = DispatchEngine::new(strategy).with_rules(vec![
+        Box::new(CapacityRule),
+        Box::new(DistanceRule::new(1.0)),
+    ]);
+
+    let driver = Driver::new(
+        "司机".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9500, 116.4500),
+    );
+    let driver_id = driver.id;
+    engine.add_driver(driver);
+
+    let mut order = Order::new(
+        OrderType::Food,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9150, 116.4180),
+    );
+    assert!(engine.dispatch_or_enqueue(&mut order).is_err());
+
+    // 位置上报本身不重试，配送员靠近后由下一次 tick 派出等待中的订单
+    engine
+        .update_driver_location(&driver_id, Location::new(39.9045, 116.4075))
+        .unwrap();
+    assert!(engine.is_pending(&order.id));
+    assert_eq!(engine.tick().dispatched.len(), 1);
+    assert_eq!(
+        engine.get_order(&order.id).unwrap().assigned_to,
+        Some(driver_id)
+    );
+}
+
+#[test]
+fn test_engine_tracks_planned_stops() {
+    let strategy = Arc::new(NearestFirstStrategy::new());
+    let engine = DispatchEngine::new(strategy);
+
+    let driver = Driver::new(
+        "骑手".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9000, 116.4000),
+    );
+    let driver_id = driver.id;
+    engine.add_driver(driver);
+
+    let mut first = Order::new(
+        OrderType::Food,
+        Location::new(39.9000, 116.4100),
+        Location::new(39.9000, 116.4500),
+    );
+    let mut second = Order::new(
+        OrderType::Food,
+        Location::new(39.9000, 116.4200),
+        Location::new(39.9000, 116.4300),
+    );
+    engine.dispatch(&mut first).unwrap();
+    engine.dispatch(&mut second).unwrap();
+
+    // 第二单顺路，应插入在第一单的取货与送达之间
+    let stops = engine.get_driver_stats(&driver_id).unwrap().planned_stops;
+    let plan: Vec<_> = stops.iter().map(|s| (s.order_id, s.kind)).collect();
+    assert_eq!(
+        plan,
+        vec![
+            (first.id, StopKind::Pickup),
+            (second.id, StopKind::Pickup),
+            (second.id, StopKind::Delivery),
+            (first.id, StopKind::Delivery),
+        ]
+    );
+
+    engine.mark_picked_up(&first.id).unwrap();
+    engine.mark_delivered(&first.id).unwrap();
+    let stops = engine.get_driver_stats(&driver_id).unwrap().planned_stops;
+    assert_eq!(stops.len(), 2);
+    assert!(stops.iter().all(|s| s.order_id == second.id));
+}
+
+#[test]
+fn test_pooling_strategy_prefers_on_route_driver() {
+    let existing = Order::new(
+        OrderType::Food,
+        Location::new(39.9000, 116.4000),
+        Location::new(39.9000, 116.4600),
+    );
+    let mut on_route = Driver::new(
+        "顺路骑手".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9000, 116.4000),
+    );
+    on_route.assign_order(existing.id);
+    on_route.plan_order(&existing);
+
+    let idle = Driver::new(
+        "空闲骑手".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9000, 116.4150),
+    );
+
+    let order = Order::new(
+        OrderType::Food,
+        Location::new(39.9000, 116.4100),
+        Location::new(39.9000, 116.4500),
+    );
+
+    // 就近派单会选空闲骑手，拼单策略选择几乎不绕路的顺路骑手
+    let nearest = NearestFirstStrategy::new()
+        .select_driver(&order, vec![&on_route, &idle], &[])
+        .unwrap();
+    assert_eq!(nearest.name, "空闲骑手");
+
+    let pooling = PoolingStrategy::new(10.0);
+    let selected = pooling
+        .select_driver(&order, vec![&on_route, &idle], &[])
+        .unwrap();
+    assert_eq!(selected.name, "顺路骑手");
+    assert!(pooling.detour_minutes(&order, &on_route).unwrap() < 0.1);
+
+    // 反方向的订单绕路过多，不派给顺路骑手
+    let opposite = Order::new(
+        OrderType::Food,
+        Location::new(39.9000, 116.3900),
+        Location::new(39.9000, 116.3500),
+    );
+    assert!(
+        PoolingStrategy::new(1.0)
+            .select_driver(&opposite, vec![&on_route], &[])
+            .is_none()
+    );
+
+    // 打车订单不能拼单
+    let ride = Order::new(
+        OrderType::RideHailing,
+        Location::new(39.9000, 116.4100),
+        Location::new(39.9000, 116.4500),
+    );
+    assert!(pooling.detour_minutes(&ride, &on_route).is_none());
+}
+
+#[test]
+fn test_order_duration_and_pooling_detour_follow_engine_travel_model() {
+    let order = Order::new(
+        OrderType::Food,
+        Location::new(39.9000, 116.4100),
+        Location::new(39.9000, 116.4500),
+    );
+    let trip_km = order.distance_km;
+
+    // 引擎默认使用与时钟无关的匀速模型
+    let engine = DispatchEngine::new(Arc::new(PoolingStrategy::new(1.0)));
+    let id = engine.submit_order(order.clone()).unwrap();
+    let expected = (trip_km / 30.0 * 60.0).ceil().max(5.0) as u32;
+    assert_eq!(
+        engine.get_order(&id).unwrap().estimated_duration_minutes,
+        expected
+    );
+
+    // 换成更慢的模型后，订单时长和拼单绕路都按该模型估算
+    let slow: Arc<dyn TravelTimeModel> = Arc::new(ConstantSpeedModel::new(6.0));
+    let engine = DispatchEngine::new(Arc::new(PoolingStrategy::new(1.0))).with_travel_model(slow);
+    let id = engine.submit_order(order.clone()).unwrap();
+    let expected = (trip_km / 6.0 * 60.0).ceil().max(5.0) as u32;
+    assert_eq!(
+        engine.get_order(&id).unwrap().estimated_duration_minutes,
+        expected
+    );
+
+    // 空闲骑手需先空驶约 0.5 公里到取货点：30 km/h 约 1 分钟，6 km/h 约 5 分钟
+    let idle = Driver::new(
+        "空闲骑手".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9000, 116.4041),
+    );
+    let pooling = PoolingStrategy::new(2.0);
+    assert!(pooling.detour_minutes(&order, &idle).unwrap() < 2.0);
+    let slow_pooling =
+        PoolingStrategy::new(2.0).with_travel_model(Arc::new(ConstantSpeedModel::new(6.0)));
+    assert!(slow_pooling.detour_minutes(&order, &idle).unwrap() > 2.0);
+    assert!(
+        slow_pooling
+            .select_driver(&order, vec![&idle], &[])
+            .is_none()
+    );
+}
+
+#[test]
+fn test_concurrent_dispatch_of_same_order_assigns_once() {
+    for _ in 0..100 {
+        let engine = Arc::new(DispatchEngine::new(Arc::new(LoadBalancedStrategy::new())));
+        for name in ["张师傅", "李师傅"] {
+            engine.add_driver(Driver::new(
+                name.to_string(),
+                VehicleType::ElectricBike,
+                Location::new(39.9042, 116.4074),
+            ));
+        }
+        let order = Order::new(
+            OrderType::Food,
+            Location::new(39.9050, 116.4080),
+            Location::new(39.9150, 116.4180),
+        );
+        let start = std::sync::Barrier::new(4);
+        let results: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| {
+                    let (engine, mut order, start) = (engine.clone(), order.clone(), &start);
+                    scope.spawn(move || {
+                        start.wait();
+                        engine.dispatch(&mut order).is_ok()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert_eq!(results.iter().filter(|ok| **ok).count(), 1);
+        let assigned: usize = engine
+            .get_all_drivers()
+            .iter()
+            .map(|driver| driver.assigned_orders.len())
+            .sum();
+        assert_eq!(assigned, 1);
+    }
+}
+
+#[test]
+fn test_delivery_estimate_follows_pooled_route() {
+    let model = ConstantSpeedModel::new(30.0);
+    let start = Location::new(39.90, 116.40);
+    let mut driver = Driver::new("骑手".to_string(), VehicleType::ElectricBike, start);
+    // 北边约 5 公里的订单，再拼一单东边 1 公里内的短途订单
+    let north = Order::new(OrderType::Food, start, Location::new(39.945, 116.40));
+    let east = Order::new(
+        OrderType::Food,
+        Location::new(39.90, 116.41),
+        Location::new(39.90, 116.42),
+    );
+    driver.plan_order(&north);
+    let direct = estimate_delivery(&model, &north, &driver, 0);
+    driver.plan_order(&east);
+    let kinds: Vec<_> = driver
+        .planned_stops
+        .iter()
+        .map(|stop| (stop.order_id == north.id, stop.kind))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            (true, StopKind::Pickup),
+            (false, StopKind::Pickup),
+            (false, StopKind::Delivery),
+            (true, StopKind::Delivery),
+        ]
+    );
+
+    // 北边订单要先绕去东边送完拼单才能送达
+    let pooled = estimate_delivery(&model, &north, &driver, 0);
+    assert_eq!(pooled.pickup_eta_minutes, 0);
+    assert!(pooled.trip_minutes > direct.trip_minutes + 2);
+    assert_eq!(pooled.delivered_at, pooled.trip_minutes as i64 * 60);
+
+    // 尚未加入路线的订单按最佳插入位置估算，排在已有停靠点之后
+    driver.complete_stop(&north.id, StopKind::Pickup);
+    let late = Order::new(
+        OrderType::Food,
+        Location::new(39.945, 116.40),
+        Location::new(39.95, 116.40),
+    );
+    let estimate = estimate_delivery(&model, &late, &driver, 0);
+    let straight = model.travel_minutes(start, late.pickup_location, &driver.vehicle_type, 0);
+    assert!(estimate.pickup_eta_minutes as f64 > straight + 3.0);
+}
+
+#[test]
+fn test_deadline_rule() {
+    let strategy = Arc::new(NearestFirstStrategy::new());
+    let engine = DispatchEngine::new(strategy)
+        .with_rules(vec![Box::new(CapacityRule), Box::new(DeadlineRule::new())]);
+
+    // 距取货点约 10 公里，接驾需要约 20 分钟
+    engine.add_driver(Driver::new(
+        "远的司机".to_string(),
+        VehicleType::Car,
+        Location::new(39.9950, 116.4080),
+    ));
+
+    let order = Order::new(
+        OrderType::Food,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9150, 116.4180),
+    );
+    let deadline = order.created_at + (order.estimated_duration_minutes as i64 + 10) * 60;
+    let mut tight = order.clone().with_deadline(deadline);
+    match engine.dispatch(&mut tight) {
+        Err(DispatchError::NoEligibleDriver(summary)) => {
+            assert_eq!(summary.rejected_by.get("DeadlineRule"), Some(&1));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let mut relaxed = Order::new(
+        OrderType::Food,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9150, 116.4180),
+    );
+    relaxed.deliver_by = Some(relaxed.created_at + 120 * 60);
+    let result = engine.dispatch(&mut relaxed).unwrap();
+    assert_eq!(result.predicted_lateness_minutes, Some(0));
+}
+
+#[test]
+fn test_predicted_lateness() {
+    let strategy = Arc::new(NearestFirstStrategy::new());
+    let engine = DispatchEngine::new(strategy);
+    engine.add_driver(Driver::new(
+        "司机".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9050, 116.4080),
+    ));
+
+    // 出餐要 30 分钟，但 20 分钟内就要送达
+    let order = Order::new(
+        OrderType::Food,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9150, 116.4180),
+    );
+    let created_at = order.created_at;
+    let mut order = order
+        .with_pickup_ready_at(created_at + 30 * 60)
+        .with_deadline(created_at + 20 * 60);
+
+    let result = engine.dispatch(&mut order).unwrap();
+    let lateness = result.predicted_lateness_minutes.unwrap();
+    assert!(lateness >= 10 + order.estimated_duration_minutes - 1);
+    assert!(result.estimated_delivery_at >= created_at + 30 * 60);
+}
+
+#[test]
+fn test_pending_queue_retries_when_capacity_frees_up() {
+    let strategy = Arc::new(NearestFirstStrategy::new());
+    let engine = DispatchEngine::new(strategy);
+
+    let mut driver = Driver::new(
+        "司机".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9042, 116.4074),
+    );
+    driver.capacity = 1;
+    let driver_id = driver.id;
+    engine.add_driver(driver);
+
+    let new_order = || {
+        Order::new(
+            OrderType::Food,
+            Location::new(39.9050, 116.4080),
+            Location::new(39.9150, 116.4180),
+        )
+    };
+    let mut first = new_order();
+    let mut express = Order::new(
+        OrderType::Express,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9150, 116.4180),
+    );
+    let mut food = new_order();
+
+    engine.dispatch(&mut first).unwrap();
+    // 普通派单失败不会入队
+    assert!(engine.dispatch(&mut express).is_err());
+    assert!(!engine.is_pending(&express.id));
+
+    assert!(engine.dispatch_or_enqueue(&mut express).is_err());
+    assert!(engine.dispatch_or_enqueue(&mut food).is_err());
+    assert!(engine.is_pending(&express.id) && engine.is_pending(&food.id));
+
+    // 外卖订单时效性更强，排在快递前面
+    let queued: Vec<_> = engine.pending_orders().iter().map(|e| e.order_id).collect();
+    assert_eq!(queued, vec![food.id, express.id]);
+
+    // 没有空闲运力时 tick 不会派单
+    let report = engine.tick();
+    assert!(report.dispatched.is_empty());
+    assert_eq!(report.still_pending, 2);
+
+    // 完成第一单后，等待中的外卖订单自动派出
+    engine.mark_picked_up(&first.id).unwrap();
+    engine.release_order(&driver_id, &first.id).unwrap();
+    let food = engine.get_order(&food.id).unwrap();
+    assert_eq!(food.status, OrderStatus::Dispatched);
+    assert_eq!(food.assigned_to, Some(driver_id));
+    assert!(engine.is_pending(&express.id));
+}
+
+#[test]
+fn test_pending_order_times_out() {
+    let strategy = Arc::new(NearestFirstStrategy::new());
+    let engine = DispatchEngine::new(strategy).with_pending_timeout(0);
+
+    let mut order = Order::new(
+        OrderType::Food,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9150, 116.4180),
+    );
+    assert!(engine.dispatch_or_enqueue(&mut order).is_err());
+    assert!(engine.is_pending(&order.id));
+
+    let report = engine.tick();
+    assert_eq!(report.cancelled, vec![order.id]);
+    assert_eq!(report.still_pending, 0);
+    assert_eq!(
+        engine.get_order(&order.id).unwrap().status,
+        OrderStatus::Cancelled
+    );
+}
+
+#[test]
+fn test_hot_swap_strategy_and_rules() {
+    let engine = DispatchEngine::new(Arc::new(NearestFirstStrategy::new()));
+
+    let mut near = Driver::new(
+        "近的低分司机".to_string(),
+        VehicleType::Car,
+        Location::new(39.9042, 116.4074),
+    );
+    let mut far = Driver::new(
+        "远的高分司机".to_string(),
+        VehicleType::Car,
+        Location::new(39.9200, 116.4300),
+    );
+    far.rating = 5.0;
+    near.rating = 4.0;
+    engine.add_driver(near);
+    engine.add_driver(far);
+
+    let new_order = || {
+        Order::new(
+            OrderType::RideHailing,
+            Location::new(39.9050, 116.4080),
+            Location::new(39.9500, 116.4500),
+        )
+    };
+
+    let mut first = new_order();
+    engine.dispatch(&mut first).unwrap();
+
+    engine.set_strategy(Arc::new(RatingPriorityStrategy::new()));
+    assert_eq!(engine.strategy_name(), "RatingPriority");
+    let mut second = new_order();
+    let result = engine.dispatch(&mut second).unwrap();
+    assert_eq!(result.driver_name, "远的高分司机");
+    assert_eq!(result.strategy_used, "RatingPriority");
+
+    // 切换后已派订单仍然保留
+    assert_eq!(
+        engine.get_order(&first.id).unwrap().status,
+        OrderStatus::Dispatched
+    );
+    assert_eq!(engine.get_all_drivers().len(), 2);
+
+    engine.set_rules(vec![
+        Box::new(CapacityRule),
+        Box::new(DistanceRule::new(0.5)),
+    ]);
+    assert_eq!(engine.rule_names(), vec!["CapacityRule", "DistanceRule"]);
+    let mut third = new_order();
+    assert_eq!(
+        engine.dispatch(&mut third).unwrap().driver_name,
+        "近的低分司机"
+    );
+}
+
+#[test]
+fn test_engine_from_toml_config() {
+    let config = DispatchConfig::from_toml_str(
+        r#"
+        pending_timeout_secs = 120
+
+        [strategy]
+        name = "weighted_score"
+        max_distance_km = 8.0
+
+        [strategy.weights]
+        distance = 1.0
+
+        [[rules]]
+        name = "capacity"
+
+        [[rules]]
+        name = "distance"
+        max_distance_km = 5.0
+
+        [vehicles.ElectricBike]
+        capacity = 1
+
+        [order_types.Food]
+        base_price = 10.0
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(config.strategy.name(), "weighted_score");
+    assert_eq!(config.rules.len(), 2);
+
+    let engine = DispatchEngine::from_config(&config).unwrap();
+    assert_eq!(engine.strategy_name(), "WeightedScore");
+    assert_eq!(engine.rule_names(), vec!["CapacityRule", "DistanceRule"]);
+
+    let driver = Driver::new(
+        "张师傅".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9042, 116.4074),
+    );
+    let driver_id = driver.id;
+    engine.add_driver(driver);
+    assert_eq!(engine.get_driver_stats(&driver_id).unwrap().capacity, 1);
+
+    // 单独设置过运力的配送员不受车型配置影响
+    let mut custom = Driver::new(
+        "李师傅".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9042, 116.4074),
+    );
+    custom.capacity = 3;
+    let custom_id = custom.id;
+    engine.add_driver(custom);
+    assert_eq!(engine.get_driver_stats(&custom_id).unwrap().capacity, 3);
+    engine.remove_driver(&custom_id).unwrap();
+
+    let mut order = Order::new(
+        OrderType::Food,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9050, 116.4080),
+    );
+    let result = engine.dispatch(&mut order).unwrap();
+    // 起终点相同，价格即为配置的起步价
+    assert_eq!(engine.get_order(&result.order_id).unwrap().price, 10.0);
+
+    // 运力已被配置为 1，第二单无法派出
+    let mut second = Order::new(
+        OrderType::Food,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9100, 116.4100),
+    );
+    assert!(engine.dispatch(&mut second).is_err());
+
+    // 仓库自带的示例配置可以正常加载
+    let example = DispatchConfig::from_file("config/dispatcher.toml").unwrap();
+    assert!(DispatchEngine::from_config(&example).is_ok());
+}
+
+#[test]
+fn test_invalid_config_rejected() {
+    let err = DispatchConfig::from_toml_str(
+        r#"
+        [[rules]]
+        name = "rating"
+        min_rating = 7.0
+        "#,
+    )
+    .unwrap_err();
+    assert!(matches!(err, ConfigError::Invalid(_)));
+
+    let err = DispatchConfig::from_json_str(r#"{"strategy": {"name": "unknown"}}"#).unwrap_err();
+    assert!(matches!(err, ConfigError::Json(_)));
+
+    // 拼错的字段不会被静默忽略
+    for content in [
+        r#"{"pending_timeout": 60}"#,
+        r#"{"rules": [{"name": "distance", "max_distance": 5.0}]}"#,
+        r#"{"rules": [{"name": "shift", "value": 240}]}"#,
+        r#"{"vehicles": {"Car": {"capacty": 2}}}"#,
+        r#"{"pricing": {"name": "surge", "sensitivty": 0.5}}"#,
+        r#"{"pricing": {"name": "surge", "area_radious_km": 2.0}}"#,
+    ] {
+        let err = DispatchConfig::from_json_str(content).unwrap_err();
+        assert!(matches!(err, ConfigError::Json(_)), "{}", content);
+    }
+    let config =
+        DispatchConfig::from_json_str(r#"{"pricing": {"name": "surge", "sensitivity": 0.5}}"#)
+            .unwrap();
+    assert_eq!(config.pricing.build().name(), "Surge");
+
+    // 无穷大和明显过大的距离、半径、速度都会被拒绝
+    for content in [
+        "[[rules]]\nname = \"distance\"\nmax_distance_km = inf",
+        "[[rules]]\nname = \"distance\"\nmax_distance_km = 1e7",
+        "[strategy]\nname = \"weighted_score\"\nmax_distance_km = inf",
+        "[travel_model]\nname = \"constant_speed\"\nspeed_kmh = inf",
+        "[pricing]\nname = \"surge\"\narea_radius_km = nan",
+    ] {
+        let err = DispatchConfig::from_toml_str(content).unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid(_)), "{}", content);
+    }
+
+    let config = DispatchConfig::from_json_str(r#"{"strategy": {"name": "pooling"}}"#).unwrap();
+    assert_eq!(config.strategy.name(), "pooling");
+    assert_eq!(
+        config.rules,
+        vec![RuleConfig::Capacity, RuleConfig::VehicleType]
+    );
+}
+
+#[test]
+fn test_event_subscriber_receives_lifecycle_events() {
+    let engine = DispatchEngine::new(Arc::new(NearestFirstStrategy::new()));
+    let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let sink = events.clone();
+    engine.subscribe(Arc::new(move |event: &DispatchEvent| {
+        sink.lock().unwrap().push(event.clone());
+    }));
+
+    // 没有配送员时派单失败并进入等待队列
+    let mut order = Order::new(
+        OrderType::Food,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9100, 116.4100),
+    );
+    let order_id = engine.submit_order(order.clone()).unwrap();
+    assert!(engine.dispatch_or_enqueue(&mut order).is_err());
+
+    // 加入配送员后等待中的订单被自动派出
+    let driver = Driver::new(
+        "张师傅".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9042, 116.4074),
+    );
+    let driver_id = driver.id;
+    engine.add_driver(driver);
+    engine
+        .update_driver_location(&driver_id, Location::new(39.9045, 116.4075))
+        .unwrap();
+    engine.mark_picked_up(&order_id).unwrap();
+    engine.release_order(&driver_id, &order_id).unwrap();
+    engine.set_strategy(Arc::new(LoadBalancedStrategy::new()));
+    engine.remove_driver(&driver_id).unwrap();
+
+    let events = events.lock().unwrap();
+    let kinds: Vec<&str> = events.iter().map(DispatchEvent::kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            "OrderSubmitted",
+            "DispatchFailed",
+            "DriverAdded",
+            "OrderDispatched",
+            "DriverMoved",
+            "OrderStatusChanged",
+            "OrderStatusChanged",
+            "OrderReleased",
+            "StrategyChanged",
+            "DriverRemoved",
+        ]
+    );
+
+    match &events[1] {
+        DispatchEvent::DispatchFailed { queued, .. } => assert!(*queued),
+        other => panic!("unexpected event: {:?}", other),
+    }
+    match &events[3] {
+        DispatchEvent::OrderDispatched { result } => {
+            assert_eq!(result.order_id, order_id);
+            assert_eq!(result.driver_id, driver_id);
+        }
+        other => panic!("unexpected event: {:?}", other),
+    }
+}
+
+#[test]
+fn test_event_broadcast_receiver() {
+    let engine = DispatchEngine::new(Arc::new(NearestFirstStrategy::new()));
+    let mut receiver = engine.event_receiver();
+
+    let driver = Driver::new(
+        "李师傅".to_string(),
+        VehicleType::Car,
+        Location::new(39.9042, 116.4074),
+    );
+    let driver_id = driver.id;
+    engine.add_driver(driver);
+
+    let mut order = Order::new(
+        OrderType::RideHailing,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9500, 116.4500),
+    );
+    engine.dispatch(&mut order).unwrap();
+
+    match receiver.try_recv().unwrap() {
+        DispatchEvent::DriverAdded {
+            driver_id: id,
+            vehicle_type,
+            ..
+        } => {
+            assert_eq!(id, driver_id);
+            assert_eq!(vehicle_type, VehicleType::Car);
+        }
+        other => panic!("unexpected event: {:?}", other),
+    }
+    match receiver.try_recv().unwrap() {
+        DispatchEvent::OrderDispatched { result } => assert_eq!(result.order_id, order.id),
+        other => panic!("unexpected event: {:?}", other),
+    }
+    assert!(receiver.try_recv().is_err());
+
+    // 事件可序列化为带类型标签的 JSON
+    let json = serde_json::to_value(DispatchEvent::StrategyChanged {
+        strategy: "NearestFirst".to_string(),
+        timestamp: 0,
+    })
+    .unwrap();
+    assert_eq!(json["type"], "StrategyChanged");
+}
+
+fn temp_data_dir() -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("dispatcher-test-{}", uuid::Uuid::new_v4()))
+}
+
+#[test]
+fn test_file_store_restores_engine_after_crash() {
+    let dir = temp_data_dir();
+    let store = Arc::new(FileStateStore::open(&dir).unwrap());
+    let engine = DispatchEngine::new(Arc::new(NearestFirstStrategy::new()))
+        .with_store(store)
+        .unwrap();
+
+    let driver = Driver::new(
+        "张师傅".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9042, 116.4074),
+    );
+    let driver_id = driver.id;
+    engine.add_driver(driver);
+    let removed = Driver::new(
+        "李师傅".to_string(),
+        VehicleType::Motorcycle,
+        Location::new(39.9100, 116.4100),
+    );
+    let removed_id = removed.id;
+    engine.add_driver(removed);
+    engine.remove_driver(&removed_id).unwrap();
+    engine
+        .update_driver_location(&driver_id, Location::new(39.9045, 116.4076))
+        .unwrap();
+
+    let mut delivered = Order::new(
+        OrderType::Food,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9100, 116.4100),
+    );
+    engine.dispatch(&mut delivered).unwrap();
+    engine.mark_picked_up(&delivered.id).unwrap();
+    engine.release_order(&driver_id, &delivered.id).unwrap();
+
+    let mut active = Order::new(
+        OrderType::Food,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9150, 116.4180),
+    );
+    engine.dispatch(&mut active).unwrap();
+    engine.mark_picked_up(&active.id).unwrap();
+
+    // 货车订单没有合适的配送员，进入等待队列
+    let mut queued = Order::new(
+        OrderType::Cargo,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9500, 116.4500),
+    );
+    assert!(engine.dispatch_or_enqueue(&mut queued).is_err());
+
+    let before = serde_json::to_value(engine.snapshot()).unwrap();
+    // 模拟崩溃：不写快照直接丢弃引擎
+    drop(engine);
+
+    let store = Arc::new(FileStateStore::open(&dir).unwrap());
+    let restored = DispatchEngine::new(Arc::new(NearestFirstStrategy::new()))
+        .with_store(store)
+        .unwrap();
+    assert_eq!(serde_json::to_value(restored.snapshot()).unwrap(), before);
+
+    let driver = restored.get_driver_stats(&driver_id).unwrap();
+    assert_eq!(driver.current_load(), 1);
+    assert_eq!(driver.current_location.latitude, 39.9045);
+    assert!(restored.get_driver_stats(&removed_id).is_err());
+    assert_eq!(
+        restored.get_order(&delivered.id).unwrap().status,
+        OrderStatus::Delivered
+    );
+    assert_eq!(
+        restored.get_order(&active.id).unwrap().status,
+        OrderStatus::PickedUp
+    );
+    assert!(restored.is_pending(&queued.id));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_file_store_snapshot_and_truncated_journal() {
+    let dir = temp_data_dir();
+    let store = Arc::new(
+        FileStateStore::open(&dir)
+            .unwrap()
+            .with_snapshot_interval(2),
+    );
+    let engine = DispatchEngine::new(Arc::new(NearestFirstStrategy::new()))
+        .with_store(store)
+        .unwrap();
+
+    for i in 0..3 {
+        engine.add_driver(Driver::new(
+            format!("师傅{}", i),
+            VehicleType::Car,
+            Location::new(39.90 + i as f64 * 0.01, 116.40),
+        ));
+    }
+    drop(engine);
+
+    // 每 2 条变更写一次快照，日志中只剩最后 1 条
+    assert!(dir.join("snapshot.json").exists());
+    let journal = std::fs::read_to_string(dir.join("journal.jsonl")).unwrap();
+    assert_eq!(journal.lines().count(), 1);
+
+    // 崩溃时写了一半的最后一行会被丢弃
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(dir.join("journal.jsonl"))
+        .unwrap();
+    std::io::Write::write_all(&mut file, br#"{"op":"RemoveDriver","dri"#).unwrap();
+    drop(file);
+
+    let store = FileStateStore::open(&dir).unwrap();
+    assert_eq!(store.load().unwrap().drivers.len(), 3);
+
+    // 中间行损坏则报错
+    std::fs::write(dir.join("journal.jsonl"), "not json\n").unwrap();
+    assert!(matches!(
+        FileStateStore::open(&dir),
+        Err(StoreError::Corrupted { line: 1, .. })
+    ));
+
+    // 日志写入成功后自动快照失败：本次记录仍然成功，失败原因通过健康状态报告
+    std::fs::remove_dir_all(&dir).unwrap();
+    let store = Arc::new(
+        FileStateStore::open(&dir)
+            .unwrap()
+            .with_snapshot_interval(1),
+    );
+    std::fs::create_dir_all(dir.join("snapshot.json.tmp")).unwrap();
+    let engine = DispatchEngine::new(Arc::new(NearestFirstStrategy::new()))
+        .with_store(store.clone())
+        .unwrap();
+    let driver = Driver::new(
+        "师傅".to_string(),
+        VehicleType::Car,
+        Location::new(39.90, 116.40),
+    );
+    let driver_id = driver.id;
+    engine.add_driver(driver);
+    engine
+        .update_driver_location(&driver_id, Location::new(39.91, 116.40))
+        .unwrap();
+    assert!(engine.persistence_error().is_some());
+    assert_eq!(
+        FileStateStore::open(&dir).unwrap().load().unwrap().drivers[&driver_id]
+            .current_location
+            .latitude,
+        39.91
+    );
+    std::fs::remove_dir_all(dir.join("snapshot.json.tmp")).unwrap();
+    store.compact().unwrap();
+    assert!(engine.persistence_error().is_none());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// 记录写入的变更，`failing` 打开后所有写入都失败
+#[derive(Default)]
+struct FlakyStore {
+    ops: std::sync::Mutex<Vec<String>>,
+    failing: std::sync::atomic::AtomicBool,
+}
+
+impl StateStore for FlakyStore {
+    fn load(&self) -> Result<EngineSnapshot, StoreError> {
+        Ok(EngineSnapshot::default())
+    }
+
+    fn append(&self, change: &StateChange) -> Result<(), StoreError> {
+        if self.failing.load(std::sync::atomic::Ordering::SeqCst) {
+            return Err(StoreError::Io(std::io::Error::other("disk full")));
+        }
+        let op = serde_json::to_value(change).unwrap()["op"].to_string();
+        self.ops
+            .lock()
+            .unwrap()
+            .push(op.trim_matches('"').to_string());
+        Ok(())
+    }
+}
+
+#[test]
+fn test_persistence_failures_are_reported() {
+    let store = Arc::new(FlakyStore::default());
+    let engine = DispatchEngine::new(Arc::new(NearestFirstStrategy::new()))
+        .with_store(store.clone())
+        .unwrap();
+    let driver = Driver::new(
+        "张师傅".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9042, 116.4074),
+    );
+    let driver_id = driver.id;
+    engine.add_driver(driver);
+
+    // 派单只写一条记录，同时包含配送员和订单
+    let new_order = || {
+        Order::new(
+            OrderType::Food,
+            Location::new(39.9050, 116.4080),
+            Location::new(39.9150, 116.4180),
+        )
+    };
+    let mut first = new_order();
+    engine.dispatch(&mut first).unwrap();
+    engine
+        .update_driver_location(&driver_id, Location::new(39.9045, 116.4075))
+        .unwrap();
+    assert_eq!(
+        *store.ops.lock().unwrap(),
+        vec![
+            "UpsertDriver",
+            "AssignOrder",
+            "RecordDispatch",
+            "MoveDriver"
+        ]
+    );
+    assert!(engine.persistence_error().is_none());
+
+    // 写入失败时返回错误，内存状态保持不变
+    store
+        .failing
+        .store(true, std::sync::atomic::Ordering::SeqCst);
+    let mut order = new_order();
+    assert!(matches!(
+        engine.dispatch(&mut order),
+        Err(DispatchError::Persistence(_))
+    ));
+    assert!(engine.get_order(&order.id).is_err());
+    assert!(matches!(
+        engine.update_driver_location(&driver_id, Location::new(39.95, 116.45)),
+        Err(DispatchError::Persistence(_))
+    ));
+    // 取货、送达同时修改订单和配送员，写入失败时两者都不变
+    assert!(matches!(
+        engine.mark_picked_up(&first.id),
+        Err(DispatchError::Persistence(_))
+    ));
+    assert_eq!(
+        engine.get_order(&first.id).unwrap().status,
+        OrderStatus::Dispatched
+    );
+    let driver = engine.get_driver_stats(&driver_id).unwrap();
+    assert_eq!(driver.current_load(), 1);
+    assert_eq!(driver.planned_stops.len(), 2);
+    assert_eq!(driver.current_location.latitude, 39.9045);
+    assert!(engine.persistence_error().unwrap().contains("disk full"));
+
+    store
+        .failing
+        .store(false, std::sync::atomic::Ordering::SeqCst);
+    store.ops.lock().unwrap().clear();
+    engine.mark_picked_up(&first.id).unwrap();
+    engine.mark_delivered(&first.id).unwrap();
+    assert_eq!(
+        *store.ops.lock().unwrap(),
+        vec!["AssignOrder", "AssignOrder"]
+    );
+    assert_eq!(
+        engine.get_driver_stats(&driver_id).unwrap().current_load(),
+        0
+    );
+}
+
+#[test]
+fn test_sqlite_store_restores_engine_and_keeps_history() {
+    let dir = temp_data_dir();
+    std::fs::create_dir_all(&dir).unwrap();
+    let db_path = dir.join("dispatcher.db");
+
+    let store = Arc::new(SqliteStateStore::open(&db_path).unwrap());
+    assert_eq!(store.schema_version().unwrap(), 1);
+    let engine = DispatchEngine::new(Arc::new(NearestFirstStrategy::new()))
+        .with_store(store)
+        .unwrap();
+
+    let driver = Driver::new(
+        "张师傅".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9042, 116.4074),
+    );
+    let driver_id = driver.id;
+    engine.add_driver(driver);
+
+    let mut order = Order::new(
+        OrderType::Food,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9100, 116.4100),
+    );
+    let result = engine.dispatch(&mut order).unwrap();
+    engine.mark_picked_up(&order.id).unwrap();
+    engine
+        .update_driver_location(&driver_id, Location::new(39.9045, 116.4075))
+        .unwrap();
+
+    let mut queued = Order::new(
+        OrderType::Cargo,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9500, 116.4500),
+    );
+    assert!(engine.dispatch_or_enqueue(&mut queued).is_err());
+
+    let before = serde_json::to_value(engine.snapshot()).unwrap();
+    drop(engine);
+
+    // 重新打开数据库，迁移不会重复执行
+    let store = Arc::new(SqliteStateStore::open(&db_path).unwrap());
+    assert_eq!(store.schema_version().unwrap(), 1);
+    let restored = DispatchEngine::new(Arc::new(NearestFirstStrategy::new()))
+        .with_store(store.clone())
+        .unwrap();
+    assert_eq!(serde_json::to_value(restored.snapshot()).unwrap(), before);
+    assert_eq!(
+        restored
+            .get_driver_stats(&driver_id)
+            .unwrap()
+            .current_load(),
+        1
+    );
+    assert!(restored.is_pending(&queued.id));
+
+    let history = store.dispatch_history_for_order(&order.id).unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].driver_id, result.driver_id);
+    assert_eq!(history[0].timestamp, result.timestamp);
+    assert_eq!(store.dispatch_history(10).unwrap().len(), 1);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_road_travel_model_vehicle_and_congestion() {
+    let model = RoadTravelModel::default();
+    let from = Location::new(39.9042, 116.4074);
+    let to = Location::new(39.9500, 116.4500);
+    let straight = distance_km(from, to);
+    assert!((model.distance_km(from, to) - straight * 1.3).abs() < 1e-9);
+
+    // 时间戳 0 为北京时间早 8 点高峰，18 点 UTC 为凌晨 2 点
+    let rush_hour = 0;
+    let night = 18 * 3600;
+    let car_rush = model.travel_minutes(from, to, &VehicleType::Car, rush_hour);
+    let car_night = model.travel_minutes(from, to, &VehicleType::Car, night);
+    assert!(car_rush > car_night * 1.9);
+
+    // 电动车平时比汽车慢，但受高峰影响小得多
+    let bike_rush = model.travel_minutes(from, to, &VehicleType::ElectricBike, rush_hour);
+    let bike_night = model.travel_minutes(from, to, &VehicleType::ElectricBike, night);
+    assert!(bike_night > car_night);
+    assert!(bike_rush / bike_night < car_rush / car_night);
+
+    // 全天畅通且覆盖车速时退化为匀速模型
+    let flat = RoadTravelModel::new(1.0)
+        .with_congestion(CongestionCurve::free_flow())
+        .with_vehicle_speed(VehicleType::Car, 30.0);
+    let constant = ConstantSpeedModel::default();
+    assert!(
+        (flat.travel_minutes(from, to, &VehicleType::Car, rush_hour)
+            - constant.travel_minutes(from, to, &VehicleType::Van, night))
+        .abs()
+            < 1e-9
+    );
+}
+
+#[test]
+fn test_engine_travel_model_drives_eta_and_deadline() {
+    let driver_location = Location::new(39.9950, 116.4080);
+    let pickup = Location::new(39.9050, 116.4080);
+    let delivery = Location::new(39.9150, 116.4180);
+
+    let fast = DispatchEngine::new(Arc::new(NearestFirstStrategy::new()))
+        .with_travel_model(Arc::new(ConstantSpeedModel::new(60.0)));
+    fast.add_driver(Driver::new(
+        "司机".to_string(),
+        VehicleType::Car,
+        driver_location,
+    ));
+    let mut order = Order::new(OrderType::Express, pickup, delivery);
+    let result = fast.dispatch(&mut order).unwrap();
+    // 约 10 公里，60 km/h 需要 10 分钟左右
+    assert!((10..=11).contains(&result.estimated_arrival_minutes));
+    assert_eq!(fast.travel_model().name(), "ConstantSpeed");
+
+    // 先添加规则再替换模型，规则同样使用新模型
+    let slow = DispatchEngine::new(Arc::new(NearestFirstStrategy::new()))
+        .with_rules(vec![Box::new(DeadlineRule::new())])
+        .with_travel_model(Arc::new(ConstantSpeedModel::new(5.0)));
+    slow.add_driver(Driver::new(
+        "司机".to_string(),
+        VehicleType::Car,
+        driver_location,
+    ));
+    let order = Order::new(OrderType::Express, pickup, delivery);
+    let deadline = order.created_at + 60 * 60;
+    let mut order = order.with_deadline(deadline);
+    match slow.dispatch(&mut order) {
+        Err(DispatchError::NoEligibleDriver(summary)) => {
+            assert_eq!(summary.rejected_by.get("DeadlineRule"), Some(&1));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    // 配置文件可以选择行程时间模型
+    let config = DispatchConfig::from_toml_str(
+        r#"
+        [travel_model]
+        name = "constant_speed"
+        speed_kmh = 60.0
+        "#,
+    )
+    .unwrap();
+    let engine = DispatchEngine::from_config(&config).unwrap();
+    assert_eq!(engine.travel_model().name(), "ConstantSpeed");
+
+    let invalid = DispatchConfig::from_toml_str(
+        r#"
+        [travel_model]
+        name = "road"
+        detour_factor = 0.5
+        "#,
+    );
+    assert!(invalid.is_err());
+}
+
+/// 东西向河流两岸各一条路，只有东边 116.44 处有一座桥
+fn river_graph() -> RoadGraph {
+    let mut graph = RoadGraph::new();
+    let south_west = graph.add_node(Location::new(39.905, 116.40));
+    let south_east = graph.add_node(Location::new(39.905, 116.44));
+    let north_west = graph.add_node(Location::new(39.915, 116.40));
+    let north_east = graph.add_node(Location::new(39.915, 116.44));
+    graph.add_road(south_west, south_east, 30.0);
+    graph.add_road(north_west, north_east, 30.0);
+    graph.add_road(south_east, north_east, 30.0);
+    graph
+}
+
+#[test]
+fn test_road_graph_astar_routes() {
+    let graph = river_graph();
+    assert_eq!(graph.node_count(), 4);
+    assert_eq!(graph.edge_count(), 6);
+
+    let south = Location::new(39.905, 116.40);
+    let north = Location::new(39.915, 116.40);
+    let route = graph.route(south, north, RouteMetric::Distance).unwrap();
+    // 直线约 1.1 公里，过河要绕到桥上
+    let bridge_km = distance_km(south, Location::new(39.905, 116.44))
+        + distance_km(Location::new(39.905, 116.44), Location::new(39.915, 116.44))
+        + distance_km(Location::new(39.915, 116.44), north);
+    assert!((route.distance_km - bridge_km).abs() < 1e-6);
+    assert_eq!(route.path.len(), 4);
+
+    // 远离路网的位置无法规划路径
+    assert!(
+        graph
+            .route(south, Location::new(40.2, 116.40), RouteMetric::Distance)
+            .is_none()
+    );
+
+    // 绕远的快速路比直穿的慢速路更省时
+    let mut graph = RoadGraph::new();
+    let a = graph.add_node(Location::new(39.90, 116.40));
+    let b = graph.add_node(Location::new(39.90, 116.43));
+    let ring = graph.add_node(Location::new(39.92, 116.415));
+    graph.add_road(a, b, 10.0);
+    graph.add_road(a, ring, 80.0);
+    graph.add_edge(ring, b, 80.0);
+    let from = Location::new(39.90, 116.40);
+    let to = Location::new(39.90, 116.43);
+    let shortest = graph.route(from, to, RouteMetric::Distance).unwrap();
+    assert_eq!(shortest.path.len(), 2);
+    let fastest = graph
+        .route(
+            from,
+            to,
+            RouteMetric::Time {
+                max_speed_kmh: 80.0,
+            },
+        )
+        .unwrap();
+    assert_eq!(fastest.path.len(), 3);
+    assert!(fastest.minutes < shortest.minutes);
+    // 电动车跑不快，快速路失去优势
+    let slow = graph
+        .route(
+            from,
+            to,
+            RouteMetric::Time {
+                max_speed_kmh: 10.0,
+            },
+        )
+        .unwrap();
+    assert_eq!(slow.path.len(), 2);
+    // 快速路单向，返程只能走慢速路
+    let back = graph
+        .route(
+            to,
+            from,
+            RouteMetric::Time {
+                max_speed_kmh: 80.0,
+            },
+        )
+        .unwrap();
+    assert_eq!(back.path.len(), 2);
+}
+
+#[test]
+fn test_graph_travel_model_in_strategy_and_distance_rule() {
+    let model: Arc<dyn TravelTimeModel> = Arc::new(
+        GraphTravelModel::new(Arc::new(river_graph()))
+            .with_congestion(CongestionCurve::free_flow()),
+    );
+    let pickup = Location::new(39.905, 116.40);
+    let across_river = Location::new(39.915, 116.40);
+    let same_bank = Location::new(39.905, 116.43);
+    assert!(model.distance_km(across_river, pickup) > model.distance_km(same_bank, pickup));
+
+    // 直线距离上对岸更近，按道路距离则同岸更近。策略使用引擎绑定的行程时间模型
+    let straight = DispatchEngine::new(Arc::new(NearestFirstStrategy::new()));
+    let road =
+        DispatchEngine::new(Arc::new(NearestFirstStrategy::new())).with_travel_model(model.clone());
+    for engine in [&straight, &road] {
+        engine.add_driver(Driver::new(
+            "对岸".to_string(),
+            VehicleType::Car,
+            across_river,
+        ));
+        engine.add_driver(Driver::new("同岸".to_string(), VehicleType::Car, same_bank));
+    }
+    let order = Order::new(OrderType::Express, pickup, Location::new(39.905, 116.41));
+    assert_eq!(
+        straight.dispatch(&mut order.clone()).unwrap().driver_name,
+        "对岸"
+    );
+    let result = road.dispatch(&mut order.clone()).unwrap();
+    assert_eq!(result.driver_name, "同岸");
+    assert_eq!(
+        result.distance_to_pickup_km,
+        model.distance_km(same_bank, pickup)
+    );
+    // 运行时切换的策略同样绑定引擎的模型
+    road.set_strategy(StrategyConfig::from_name("weighted_score").unwrap().build());
+    let explanation = road.dispatch_explain(&order);
+    assert_eq!(explanation.ranked[0].driver_name, "同岸");
+    assert_eq!(
+        explanation.ranked[0].distance_to_pickup_km,
+        model.distance_km(same_bank, pickup)
+    );
+    road.set_strategy(StrategyConfig::from_name("capacity_match").unwrap().build());
+    let mut another = Order::new(OrderType::Express, pickup, Location::new(39.905, 116.41));
+    assert_eq!(road.dispatch(&mut another).unwrap().driver_name, "同岸");
+
+    // 批量派单同样以道路距离为成本
+    let batch =
+        DispatchEngine::new(Arc::new(NearestFirstStrategy::new())).with_travel_model(model.clone());
+    batch.add_driver(Driver::new(
+        "对岸".to_string(),
+        VehicleType::Car,
+        across_river,
+    ));
+    batch.add_driver(Driver::new("同岸".to_string(), VehicleType::Car, same_bank));
+    let results = batch.dispatch_batch(&mut [order.clone()]);
+    assert_eq!(results[0].as_ref().unwrap().driver_name, "同岸");
+
+    // 道路距离超过上限的配送员被距离规则拒绝
+    let engine = DispatchEngine::new(Arc::new(NearestFirstStrategy::new()))
+        .with_rules(vec![Box::new(DistanceRule::new(5.0).with_road_distance())])
+        .with_travel_model(model);
+    engine.add_driver(Driver::new(
+        "对岸".to_string(),
+        VehicleType::Car,
+        across_river,
+    ));
+    match engine.dispatch(&mut order.clone()) {
+        Err(DispatchError::NoEligibleDriver(summary)) => {
+            assert_eq!(summary.rejected_by.get("DistanceRule"), Some(&1));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn test_road_graph_file_and_config() {
+    let dir = temp_data_dir();
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("graph.json");
+    std::fs::write(
+        &path,
+        r#"{
+            "nodes": [
+                {"id": 10, "latitude": 39.905, "longitude": 116.40},
+                {"id": 11, "latitude": 39.905, "longitude": 116.44}
+            ],
+            "edges": [{"from": 10, "to": 11, "speed_kmh": 40.0, "oneway": true}]
+        }"#,
+    )
+    .unwrap();
+    let graph = RoadGraph::from_file(&path).unwrap();
+    assert_eq!(graph.edge_count(), 1);
+
+    let config = DispatchConfig::from_toml_str(&format!(
+        "[travel_model]\nname = \"graph\"\npath = {:?}\n",
+        path
+    ))
+    .unwrap();
+    let engine = DispatchEngine::from_config(&config).unwrap();
+    assert_eq!(engine.travel_model().name(), "Graph");
+
+    std::fs::write(&path, r#"{"nodes": [], "edges": [{"from": 1, "to": 2}]}"#).unwrap();
+    assert!(matches!(
+        RoadGraph::from_file(&path),
+        Err(RoutingError::UnknownNode(1))
+    ));
+    assert!(DispatchEngine::from_config(&config).is_err());
+
+    std::fs::write(
+        &path,
+        r#"{
+            "nodes": [
+                {"id": 1, "latitude": 39.905, "longitude": 116.40},
+                {"id": 2, "latitude": 39.905, "longitude": 116.44}
+            ],
+            "edges": [{"from": 1, "to": 2, "speed_kmh": 0.0}]
+        }"#,
+    )
+    .unwrap();
+    assert!(matches!(
+        RoadGraph::from_file(&path),
+        Err(RoutingError::InvalidSpeed { from: 1, to: 2, .. })
+    ));
+    assert!(matches!(
+        RoadGraph::from_file(dir.join("graph.txt")),
+        Err(RoutingError::UnsupportedFormat(_))
+    ));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "osm")]
+#[test]
+fn test_road_graph_from_osm_xml() {
+    let dir = temp_data_dir();
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("extract.osm");
+    std::fs::write(
+        &path,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+        <osm version="0.6">
+          <node id="1" lat="39.905" lon="116.40"/>
+          <node id="2" lat="39.905" lon="116.44"/>
+          <node id="3" lat="39.915" lon="116.44"/>
+          <way id="100">
+            <nd ref="1"/><nd ref="2"/>
+            <tag k="highway" v="primary"/>
+          </way>
+          <way id="101">
+            <nd ref="2"/><nd ref="3"/>
+            <tag k="highway" v="residential"/>
+            <tag k="oneway" v="yes"/>
+          </way>
+          <way id="102">
+            <nd ref="1"/><nd ref="3"/>
+            <tag k="highway" v="footway"/>
+          </way>
+        </osm>"#,
+    )
+    .unwrap();
+
+    let graph = RoadGraph::from_file(&path).unwrap();
+    assert_eq!(graph.node_count(), 3);
+    // 主路双向、居民区道路单向、人行道不计入
+    assert_eq!(graph.edge_count(), 3);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_surge_pricing_multipliers_and_surcharges() {
+    let surge = SurgePricing::new();
+    let context = |pending_orders, available_drivers| PricingContext {
+        now: 0,
+        pending_orders,
+        available_drivers,
+    };
+    // 当前订单计入需求：1 单 1 人不加价，3 单 1 人按 0.5 的幅度加价
+    assert_eq!(surge.surge_multiplier(&context(0, 1)), 1.0);
+    assert_eq!(surge.surge_multiplier(&context(2, 2)), 1.3);
+    assert_eq!(surge.surge_multiplier(&context(2, 1)), 2.0);
+    assert_eq!(surge.surge_multiplier(&context(10, 0)), 2.0);
+
+    let order = Order::new(
+        OrderType::Food,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9150, 116.4180),
+    );
+    let profile = order.order_type.default_profile();
+    let fixed = FixedPricing.quote(&order, &profile, &context(5, 0));
+    assert_eq!(fixed.total, order.price);
+    assert_eq!(fixed.surge_multiplier, 1.0);
+
+    // 时间戳 0 为北京时间 8 点，4 点 UTC 为午高峰，16 点 UTC 为午夜
+    let morning = surge.quote(&order, &profile, &context(0, 1));
+    assert_eq!(morning.time_of_day_multiplier, 1.0);
+    assert_eq!(morning.night_surcharge, 0.0);
+    assert_eq!(morning.total, order.price);
+
+    let lunch = PricingContext {
+        now: 4 * 3600,
+        ..context(2, 1)
+    };
+    let lunch = surge.quote(&order, &profile, &lunch);
+    assert_eq!(lunch.time_of_day_multiplier, 1.2);
+    assert_eq!(
+        lunch.total,
+        ((lunch.base_price + lunch.distance_price) * 2.0 * 1.2).round()
+    );
+
+    let midnight = PricingContext {
+        now: 16 * 3600,
+        ..context(0, 1)
+    };
+    let midnight = surge.quote(&order, &profile, &midnight);
+    assert_eq!(midnight.night_surcharge, 5.0);
+    assert_eq!(midnight.total, order.price + 5.0);
+}
+
+#[test]
+fn test_engine_prices_orders_from_nearby_supply_and_demand() {
+    // 关闭时段倍率和夜间费，只看供需倍率
+    let surge = SurgePricing::new()
+        .with_hourly_multipliers(8, [1.0; 24])
+        .with_night_surcharge(0.0, 0, 0);
+    let engine = DispatchEngine::new(Arc::new(NearestFirstStrategy::new()))
+        .with_pricing_model(Arc::new(surge));
+    assert_eq!(engine.pricing_model_name(), "Surge");
+
+    let pickup = Location::new(39.9050, 116.4080);
+    let delivery = Location::new(39.9150, 116.4180);
+
+    // 附近没有配送员，订单进入等待队列并按最高倍率计价
+    let mut first = Order::new(OrderType::Food, pickup, delivery);
+    let base_price = first.price;
+    assert!(engine.dispatch_or_enqueue(&mut first).is_err());
+    let stored = engine.get_order(&first.id).unwrap();
+    let breakdown = stored.price_breakdown.unwrap();
+    assert_eq!(breakdown.surge_multiplier, 2.0);
+    assert_eq!(stored.price, (base_price * 2.0).round());
+
+    // 远处的配送员不计入附近运力
+    engine.add_driver(Driver::new(
+        "远处".to_string(),
+        VehicleType::Car,
+        Location::new(40.2, 116.4),
+    ));
+    let mut second = Order::new(OrderType::Food, pickup, delivery);
+    engine.dispatch(&mut second).unwrap();
+    assert_eq!(
+        second.price_breakdown.as_ref().unwrap().surge_multiplier,
+        2.0
+    );
+
+    // 附近运力充足时不加价，计价结果保存在订单上
+    for name in ["甲", "乙"] {
+        engine.add_driver(Driver::new(name.to_string(), VehicleType::Car, pickup));
+    }
+    let mut third = Order::new(OrderType::Food, pickup, delivery);
+    let result = engine.dispatch(&mut third).unwrap();
+    let stored = engine.get_order(&result.order_id).unwrap();
+    assert_eq!(stored.price_breakdown.unwrap().surge_multiplier, 1.0);
+    assert_eq!(stored.price, base_price);
+
+    // 默认使用固定计价
+    let fixed = DispatchEngine::new(Arc::new(NearestFirstStrategy::new()));
+    let order_id = fixed
+        .submit_order(Order::new(OrderType::Food, pickup, delivery))
+        .unwrap();
+    let stored = fixed.get_order(&order_id).unwrap();
+    assert_eq!(stored.price, base_price);
+    assert_eq!(stored.price_breakdown.unwrap().pricing_model, "Fixed");
+
+    let config = DispatchConfig::from_toml_str(
+        r#"
+        [pricing]
+        name = "surge"
+        max_multiplier = 3.0
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        config.pricing,
+        PricingConfig::Surge(Box::new(SurgePricing::new().with_max_multiplier(3.0)))
+    );
+    assert_eq!(
+        DispatchEngine::from_config(&config)
+            .unwrap()
+            .pricing_model_name(),
+        "Surge"
+    );
+    assert!(
+        DispatchConfig::from_toml_str("[pricing]\nname = \"surge\"\nmax_multiplier = 0.5\n")
+            .is_err()
+    );
+}
+
+#[test]
+fn test_zone_map_from_geojson() {
+    let zones = ZoneMap::from_geojson_file("config/zones.geojson").unwrap();
+    assert_eq!(zones.zones().len(), 3);
+    assert_eq!(
+        zones.zone_at(Location::new(39.9042, 116.4074)).unwrap().id,
+        "central"
+    );
+    assert_eq!(
+        zones.zone_at(Location::new(39.90, 116.39)).unwrap().name,
+        "西城片区"
+    );
+    assert!(zones.zone_at(Location::new(39.95, 116.40)).is_none());
+
+    // 边界相接的服务区自动相邻，相隔一个服务区的不相邻
+    let west = zones.get("west").unwrap();
+    assert!(west.covers_zone("central"));
+    assert!(!west.covers_zone("east"));
+    assert!(zones.get("east").unwrap().covers_zone("central"));
+
+    // 显式声明的相邻关系是双向的；MultiPolygon 和内环（洞）
+    let zones = ZoneMap::from_geojson_str(
+        r#"{
+            "type": "FeatureCollection",
+            "features": [
+                {
+                    "type": "Feature",
+                    "id": 7,
+                    "properties": {"neighbors": ["island"]},
+                    "geometry": {"type": "Polygon", "coordinates": [
+                        [[116.0, 39.0], [117.0, 39.0], [117.0, 40.0], [116.0, 40.0], [116.0, 39.0]],
+                        [[116.4, 39.4], [116.6, 39.4], [116.6, 39.6], [116.4, 39.6], [116.4, 39.4]]
+                    ]}
+                },
+                {
+                    "type": "Feature",
+                    "properties": {"id": "island", "name": "岛"},
+                    "geometry": {"type": "MultiPolygon", "coordinates": [
+                        [[[118.0, 39.0], [118.1, 39.0], [118.1, 39.1], [118.0, 39.0]]],
+                        [[[119.0, 39.0], [119.1, 39.0], [119.1, 39.1], [119.0, 39.0]]]
+                    ]}
+                }
+            ]
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(zones.zone_at(Location::new(39.2, 116.2)).unwrap().id, "7");
+    assert!(zones.zone_at(Location::new(39.5, 116.5)).is_none());
+    assert_eq!(
+        zones.zone_at(Location::new(39.02, 119.09)).unwrap().id,
+        "island"
+    );
+    assert!(zones.get("island").unwrap().covers_zone("7"));
+
+    let duplicate = r#"{"type": "FeatureCollection", "features": [
+        {"type": "Feature", "properties": {"id": "a"}, "geometry": {"type": "Polygon",
+         "coordinates": [[[0, 0], [1, 0], [1, 1], [0, 0]]]}},
+        {"type": "Feature", "properties": {"id": "a"}, "geometry": {"type": "Polygon",
+         "coordinates": [[[2, 0], [3, 0], [3, 1], [2, 0]]]}}
+    ]}"#;
+    assert!(matches!(
+        ZoneMap::from_geojson_str(duplicate),
+        Err(ZoneError::Invalid(_))
+    ));
+    let point = r#"{"type": "Feature", "geometry": {"type": "Point", "coordinates": [0, 0]}}"#;
+    assert!(ZoneMap::from_geojson_str(point).is_err());
+    // 没有多边形的服务区会让模拟无法在其中取点
+    let empty = r#"{"type": "Feature", "properties": {"id": "a"},
+        "geometry": {"type": "MultiPolygon", "coordinates": []}}"#;
+    assert!(matches!(
+        ZoneMap::from_geojson_str(empty),
+        Err(ZoneError::Invalid(_))
+    ));
+}
+
+#[test]
+fn test_zone_rule_and_service_area() {
+    let zones = Arc::new(ZoneMap::from_geojson_file("config/zones.geojson").unwrap());
+    let engine = DispatchEngine::new(Arc::new(NearestFirstStrategy::new()))
+        .with_rules(vec![Box::new(CapacityRule), Box::new(ZoneRule::new())])
+        .with_zones(zones);
+
+    // 东边的配送员离西城的取货点最近，但不在相邻服务区
+    let east = Driver::new(
+        "朝阳".to_string(),
+        VehicleType::Car,
+        Location::new(39.90, 116.436),
+    );
+    let east_id = east.id;
+    engine.add_driver(east);
+    // 未指定服务区时不写入配送员，按当前位置解析
+    let stored = engine.get_driver_stats(&east_id).unwrap();
+    assert_eq!(stored.zone_id, None);
+    assert_eq!(engine.driver_zone(&stored).as_deref(), Some("east"));
+
+    let pickup = Location::new(39.90, 116.404);
+    let mut order = Order::new(OrderType::Food, pickup, Location::new(39.91, 116.39));
+    match engine.dispatch_or_enqueue(&mut order) {
+        Err(DispatchError::NoEligibleDriver(summary)) => {
+            assert_eq!(summary.rejected_by.get("ZoneRule"), Some(&1));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    // 位于服务区内时以当前位置为准，指定的服务区不生效
+    let inside = Driver::new(
+        "驻点".to_string(),
+        VehicleType::Car,
+        Location::new(39.90, 116.46),
+    )
+    .with_zone("central");
+    assert_eq!(engine.driver_zone(&inside).as_deref(), Some("east"));
+
+    // 位于服务区外、指定归属中心片区的配送员可以到相邻的西城接单，等待中的订单随即派出
+    engine.add_driver(
+        Driver::new(
+            "中心".to_string(),
+            VehicleType::Car,
+            Location::new(39.90, 116.48),
+        )
+        .with_zone("central"),
+    );
+    assert_eq!(
+        engine.get_order(&order.id).unwrap().status,
+        OrderStatus::Dispatched
+    );
+
+    // 服务区外的订单直接拒绝，不进入等待队列
+    let mut outside = Order::new(OrderType::Food, pickup, Location::new(40.5, 116.40));
+    assert!(matches!(
+        engine.dispatch(&mut outside),
+        Err(DispatchError::OutsideServiceArea(id)) if id == outside.id
+    ));
+    assert!(!engine.is_pending(&outside.id));
+    assert!(engine.submit_order(outside).is_err());
+
+    let mut queued = Order::new(OrderType::Food, pickup, Location::new(39.91, 116.39));
+    engine.mark_picked_up(&order.id).unwrap();
+    let _ = engine.dispatch(&mut queued);
+
+    let stats = engine.zone_stats();
+    assert_eq!(stats.len(), 3);
+    let by_id = |id: &str| stats.iter().find(|s| s.zone_id == id).unwrap().clone();
+    assert_eq!(by_id("east").drivers, 1);
+    assert_eq!(by_id("central").drivers, 1);
+    assert_eq!(by_id("west").drivers, 0);
+    // 中心片区的配送员还有运力，第二单也派给了他；订单按取货点统计在西城
+    assert_eq!(by_id("west").active_orders, 2);
+    assert_eq!(by_id("west").pending_orders, 0);
+    assert_eq!(by_id("central").available_drivers, 1);
+
+    // 未指定服务区的配送员移动后按新位置统计
+    engine
+        .update_driver_location(&east_id, Location::new(39.90, 116.40))
+        .unwrap();
+    let stats = engine.zone_stats();
+    let by_id = |id: &str| stats.iter().find(|s| s.zone_id == id).unwrap().clone();
+    assert_eq!(by_id("east").drivers, 0);
+    assert_eq!(by_id("west").drivers, 1);
+}
+
+#[test]
+fn test_shift_rule_checks_shift_end_breaks_and_continuous_work() {
+    let now = chrono::Utc::now().timestamp();
+    let shift = Shift::new(now - 3600, now + 4 * 3600).with_break(now + 1800, now + 2700);
+    assert_eq!(shift.validate(), Ok(()));
+    assert!(matches!(
+        Shift::new(now, now).validate(),
+        Err(ShiftError::EmptyShift { .. })
+    ));
+    assert!(matches!(
+        Shift::new(now, now + 3600)
+            .with_break(now + 3000, now + 4000)
+            .validate(),
+        Err(ShiftError::InvalidBreak { .. })
+    ));
+    assert_eq!(
+        Shift::new(now, now + 3600)
+            .with_break(now + 600, now + 1200)
+            .with_break(now + 900, now + 1500)
+            .validate(),
+        Err(ShiftError::OverlappingBreaks(now + 900))
+    );
+    assert!(shift.is_working(now));
+    assert!(shift.on_break(now + 2000));
+    assert_eq!(shift.work_started_at(now), now - 3600);
+    assert_eq!(shift.work_ends_at(now), now + 1800);
+    assert_eq!(shift.work_started_at(now + 3000), now + 2700);
+    assert_eq!(shift.work_ends_at(now + 3000), now + 4 * 3600);
+
+    let rule = ShiftRule::new();
+    let order = Order::new(
+        OrderType::Food,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9200, 116.4200),
+    );
+    let driver = |shift: Option<Shift>| {
+        let mut driver = Driver::new(
+            "张师傅".to_string(),
+            VehicleType::ElectricBike,
+            Location::new(39.9042, 116.4074),
+        );
+        driver.shift = shift;
+        driver
+    };
+
+    // 没有班次不受限制，班次内离休息还有半小时可以接单
+    assert!(rule.evaluate(&order, &driver(None)));
+    assert!(rule.evaluate(&order, &driver(Some(shift))));
+
+    // 预计完成前就要下班或开始休息
+    let ending = Shift::new(now - 3600, now + 120);
+    assert!(!rule.evaluate(&order, &driver(Some(ending))));
+    let before_break = Shift::new(now - 3600, now + 3600).with_break(now + 120, now + 1800);
+    assert!(!rule.evaluate(&order, &driver(Some(before_break))));
+
+    // 休息中或尚未上班
+    let on_break = Shift::new(now - 3600, now + 3600).with_break(now - 60, now + 600);
+    assert!(!rule.evaluate(&order, &driver(Some(on_break))));
+    assert!(!rule.evaluate(&order, &driver(Some(Shift::new(now + 600, now + 3600)))));
+
+    // 已连续工作接近 4 小时；中途休息过则从休息结束重新计算
+    let long = Shift::new(now - 4 * 3600 + 300, now + 3600);
+    assert!(!rule.evaluate(&order, &driver(Some(long.clone()))));
+    let rested = long.with_break(now - 3600, now - 1800);
+    assert!(rule.evaluate(&order, &driver(Some(rested))));
+    let relaxed = ShiftRule::new().with_max_continuous_work_minutes(300);
+    assert!(relaxed.evaluate(
+        &order,
+        &driver(Some(Shift::new(now - 4 * 3600 + 300, now + 3600)))
+    ));
+}
+
+#[test]
+fn test_tick_takes_drivers_offline_when_shift_ends() {
+    let config = DispatchConfig::from_toml_str(
+        r#"
+        [[rules]]
+        name = "capacity"
+
+        [[rules]]
+        name = "shift"
+        max_continuous_work_minutes = 180
+        "#,
+    )
+    .unwrap();
+    let engine = DispatchEngine::from_config(&config).unwrap();
+    assert_eq!(engine.rule_names(), vec!["CapacityRule", "ShiftRule"]);
+    assert!(
+        DispatchConfig::from_toml_str(
+            "[[rules]]\nname = \"shift\"\nmax_continuous_work_minutes = 0"
+        )
+        .is_err()
+    );
+
+    let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let sink = events.clone();
+    engine.subscribe(Arc::new(move |event: &DispatchEvent| {
+        sink.lock().unwrap().push(event.clone());
+    }));
+
+    let now = chrono::Utc::now().timestamp();
+    let location = Location::new(39.9042, 116.4074);
+    let finished = Driver::new("下班".to_string(), VehicleType::ElectricBike, location)
+        .with_shift(Shift::new(now - 8 * 3600, now - 1));
+    let finished_id = finished.id;
+    let working = Driver::new("在班".to_string(), VehicleType::ElectricBike, location)
+        .with_shift(Shift::new(now - 3600, now + 3600));
+    let working_id = working.id;
+    engine.add_driver(finished);
+    engine.add_driver(working);
+
+    let report = engine.tick();
+    assert_eq!(report.off_shift, vec![finished_id]);
+    assert_eq!(
+        engine.get_driver_stats(&finished_id).unwrap().status,
+        DriverStatus::Offline
+    );
+    assert_eq!(
+        engine.get_driver_stats(&working_id).unwrap().status,
+        DriverStatus::Idle
+    );
+    assert!(events.lock().unwrap().iter().any(|event| matches!(
+        event,
+        DispatchEvent::DriverShiftEnded { driver_id, .. } if *driver_id == finished_id
+    )));
+
+    // 已离线的配送员不会重复处理，订单只派给在班的配送员
+    assert!(engine.tick().off_shift.is_empty());
+    let mut order = Order::new(OrderType::Food, location, Location::new(39.9100, 116.4100));
+    let result = engine.dispatch(&mut order).unwrap();
+    assert_eq!(result.driver_id, working_id);
+}
+
+fn simulation_engine() -> DispatchEngine {
+    DispatchEngine::new(Arc::new(NearestFirstStrategy::new())).with_rules(vec![
+        Box::new(CapacityRule),
+        Box::new(DistanceRule::new(5.0)),
+    ])
+}
+
+#[test]
+fn test_simulation_reports_kpis_reproducibly() {
+    let zones = Arc::new(ZoneMap::from_geojson_file("config/zones.geojson").unwrap());
+    let config = SimulationConfig::new()
+        .with_duration_minutes(60)
+        .with_demand(ZoneDemand::new("west", 20.0))
+        .with_demand(ZoneDemand::new("central", 30.0))
+        .with_fleet(FleetSpec::new("west", VehicleType::ElectricBike, 3))
+        .with_fleet(FleetSpec::new("central", VehicleType::Motorcycle, 4));
+    let simulation = Simulation::new(config, zones.clone());
+
+    let engine = simulation_engine().with_clock(simulation.clock());
+    let report = simulation.run(&engine).unwrap();
+    assert_eq!(engine.get_all_drivers().len(), 7);
+    assert_eq!(report.strategy, "NearestFirst");
+    assert!(report.orders_generated > 20, "{:?}", report);
+    assert!(report.orders_dispatched > 0 && report.orders_delivered > 0);
+    assert!(report.orders_delivered <= report.orders_dispatched);
+    assert!(report.utilization > 0.0 && report.utilization <= 1.0);
+    assert!((0.0..=1.0).contains(&report.unassigned_rate));
+    assert!(report.avg_pickup_wait_minutes > 0.0);
+    assert!(report.avg_delivery_minutes > report.avg_pickup_wait_minutes);
+
+    // 相同种子生成相同的订单序列，引擎使用仿真时钟时报告完全一致
+    let again = simulation
+        .run(&simulation_engine().with_clock(simulation.clock()))
+        .unwrap();
+    assert_eq!(again, report);
+
+    // 需求只在晚高峰出现，11 点开始的仿真没有订单
+    let mut evening = [0.0; 24];
+    evening[18] = 1.0;
+    let quiet = Simulation::new(
+        SimulationConfig::new()
+            .with_duration_minutes(30)
+            .with_demand(ZoneDemand::new("west", 100.0).with_hourly_factors(evening)),
+        zones,
+    )
+    .run(&simulation_engine())
+    .unwrap();
+    assert_eq!(quiet.orders_generated, 0);
+}
+
+#[test]
+fn test_simulation_unassigned_rate_and_unknown_zone() {
+    let zones = Arc::new(ZoneMap::from_geojson_file("config/zones.geojson").unwrap());
+    let demand = SimulationConfig::new()
+        .with_duration_minutes(60)
+        .with_demand(ZoneDemand::new("east", 60.0));
+
+    // 一个配送员应付每小时 60 单，大量订单等待超时
+    let scarce = Simulation::new(
+        demand
+            .clone()
+            .with_fleet(FleetSpec::new("east", VehicleType::ElectricBike, 1)),
+        zones.clone(),
+    )
+    .run(&simulation_engine())
+    .unwrap();
+    let ample = Simulation::new(
+        demand.with_fleet(FleetSpec::new("east", VehicleType::Motorcycle, 12)),
+        zones.clone(),
+    )
+    .run(&simulation_engine())
+    .unwrap();
+    assert!(scarce.unassigned_rate > ample.unassigned_rate);
+    assert!(scarce.utilization > ample.utilization);
+
+    let unknown = SimulationConfig::new().with_fleet(FleetSpec::new("north", VehicleType::Car, 1));
+    assert!(matches!(
+        Simulation::new(unknown, zones).run(&simulation_engine()),
+        Err(SimulationError::UnknownZone(zone)) if zone == "north"
+    ));
+}
+
+const BENCHMARK_TOML: &str = r#"
+zones = "config/zones.geojson"
+
+[simulation]
+duration_minutes = 45
+seed = 3
+
+[[simulation.demand]]
+zone_id = "central"
+orders_per_hour = 40.0
+
+[[simulation.fleet]]
+zone_id = "central"
+vehicle_type = "ElectricBike"
+count = 4
+
+[[variants]]
+name = "nearest"
+strategy = { name = "nearest_first" }
+rules = [{ name = "capacity" }]
+
+[[variants]]
+name = "balanced"
+strategy = { name = "load_balanced" }
+rules = [{ name = "capacity" }, { name = "distance", max_distance_km = 5.0 }]
+"#;
+
+#[test]
+fn test_benchmark_replays_same_demand_for_each_variant() {
+    let config = BenchmarkConfig::from_toml_str(BENCHMARK_TOML).unwrap();
+    let benchmark = config.build().unwrap();
+    let report = benchmark.run().unwrap();
+
+    assert_eq!(report.drivers, 4);
+    assert!(report.orders > 10);
+    assert_eq!(report.rows.len(), 2);
+    for row in &report.rows {
+        assert_eq!(row.report.orders_generated, report.orders);
+        assert!(row.report.total_distance_km > 0.0);
+        assert!(row.report.fairness_index > 0.0 && row.report.fairness_index <= 1.0);
+        assert!((0.0..=1.0).contains(&row.report.late_rate));
+    }
+    assert_eq!(report.row("nearest").unwrap().strategy, "NearestFirst");
+    assert_eq!(report.row("balanced").unwrap().strategy, "LoadBalanced");
+
+    // 相同种子生成相同的订单流，重复运行得到相同的报告
+    let (first, second) = (benchmark.scenario().unwrap(), benchmark.scenario().unwrap());
+    assert_eq!(
+        serde_json::to_string(&first).unwrap(),
+        serde_json::to_string(&second).unwrap()
+    );
+    assert_eq!(benchmark.run().unwrap(), report);
+
+    let csv = report.to_csv();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("variant,strategy,dispatched"));
+    assert!(lines[1].starts_with("nearest,NearestFirst,"));
+    let table = report.to_table();
+    assert!(table.lines().nth(2).unwrap().starts_with("balanced "));
+
+    let json = serde_json::to_string(&report).unwrap();
+    let parsed: BenchmarkReport = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.rows.len(), 2);
+}
+
+#[test]
+fn test_benchmark_recorded_scenario_and_validation() {
+    let config = BenchmarkConfig::from_toml_str(BENCHMARK_TOML).unwrap();
+    let benchmark = config.build().unwrap();
+
+    // 保存的场景可以原样回放
+    let dir = temp_data_dir();
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("scenario.json");
+    let scenario = benchmark.scenario().unwrap();
+    scenario.save_json(&path).unwrap();
+    let recorded = Scenario::from_json_file(&path).unwrap();
+    assert_eq!(recorded.orders.len(), scenario.orders.len());
+    let report = benchmark.run_scenario(&recorded).unwrap();
+    assert_eq!(report.orders, scenario.orders.len());
+    assert_eq!(report, benchmark.run_scenario(&recorded).unwrap());
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let duplicate = BENCHMARK_TOML.replace("name = \"balanced\"", "name = \"nearest\"");
+    assert!(matches!(
+        BenchmarkConfig::from_toml_str(&duplicate),
+        Err(BenchmarkError::Invalid(_))
+    ));
+    let bad_rule = BENCHMARK_TOML.replace("max_distance_km = 5.0", "max_distance_km = -1.0");
+    assert!(matches!(
+        BenchmarkConfig::from_toml_str(&bad_rule),
+        Err(BenchmarkError::Config(_))
+    ));
+    assert!(BenchmarkConfig::from_file("config/benchmark.toml").is_ok());
+
+    // 服务区规则使用对比配置的服务区：东城的配送员不能接西城的订单
+    let cross_zone = BENCHMARK_TOML
+        .replacen("zone_id = \"central\"", "zone_id = \"west\"", 1)
+        .replacen("zone_id = \"central\"", "zone_id = \"east\"", 1)
+        .replace(
+            "rules = [{ name = \"capacity\" }]",
+            "rules = [{ name = \"capacity\" }, { name = \"zone\" }]",
+        );
+    let report = BenchmarkConfig::from_toml_str(&cross_zone)
+        .unwrap()
+        .build()
+        .unwrap()
+        .run()
+        .unwrap();
+    assert!(report.orders > 0);
+    assert_eq!(report.row("nearest").unwrap().orders_dispatched, 0);
+    assert!(report.row("balanced").unwrap().orders_dispatched > 0);
+}
+
+const REPLAY_JSONL: &str = r#"
+# 录制的线上请求，故意打乱时间顺序
+{"timestamp": 1700000000, "type": "add_driver", "driver_id": "11111111-1111-4111-8111-111111111111", "name": "张师傅", "vehicle_type": "电动车", "latitude": 39.9042, "longitude": 116.4074}
+{"timestamp": 1700000120, "type": "dispatch", "order_id": "33333333-3333-4333-8333-333333333333", "order_type": "货运订单", "pickup_lat": 39.9050, "pickup_lng": 116.4080, "delivery_lat": 39.9150, "delivery_lng": 116.4180}
+{"timestamp": 1700000060, "type": "dispatch", "order_id": "22222222-2222-4222-8222-222222222222", "order_type": "外卖订单", "pickup_lat": 39.9050, "pickup_lng": 116.4080, "delivery_lat": 39.9150, "delivery_lng": 116.4180, "deliver_within_minutes": 60}
+{"timestamp": 1700000030, "type": "update_location", "driver_id": "11111111-1111-4111-8111-111111111111", "latitude": 39.9045, "longitude": 116.4077}
+{"timestamp": 1700000200, "type": "add_driver", "driver_id": "44444444-4444-4444-8444-444444444444", "name": "李师傅", "vehicle_type": "Van", "latitude": 39.9100, "longitude": 116.4100}
+{"timestamp": 1700000300, "type": "release", "driver_id": "11111111-1111-4111-8111-111111111111", "order_id": "22222222-2222-4222-8222-222222222222"}
+{"timestamp": 1700000400, "type": "release", "driver_id": "not-a-uuid", "order_id": "22222222-2222-4222-8222-222222222222"}
+"#;
+
+#[test]
+fn test_replay_feeds_recorded_requests_in_timestamp_order() {
+    let replay = Replay::from_jsonl_str(REPLAY_JSONL).unwrap();
+    assert_eq!(replay.len(), 7);
+    let timestamps: Vec<i64> = replay.records().map(|record| record.timestamp).collect();
+    assert!(timestamps.windows(2).all(|pair| pair[0] <= pair[1]));
+
+    let engine = DispatchEngine::new(Arc::new(NearestFirstStrategy::new()))
+        .with_rules(vec![Box::new(CapacityRule), Box::new(VehicleTypeRule)]);
+    let mut output = Vec::new();
+    let outcomes = replay.run_to_writer(&engine, &mut output).unwrap();
+    let kinds: Vec<&str> = outcomes.iter().map(|o| o.kind.as_str()).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            "add_driver",
+            "update_location",
+            "dispatch",
+            "dispatch",
+            "add_driver",
+            "release",
+            "release"
+        ]
+    );
+
+    // 录制的 ID 在回放中保持不变，外卖订单派给张师傅
+    let zhang = uuid::Uuid::parse_str("11111111-1111-4111-8111-111111111111").unwrap();
+    let food = uuid::Uuid::parse_str("22222222-2222-4222-8222-222222222222").unwrap();
+    let cargo = uuid::Uuid::parse_str("33333333-3333-4333-8333-333333333333").unwrap();
+    assert_eq!(outcomes[2].line, 5);
+    assert_eq!(outcomes[2].order_id, Some(food));
+    assert_eq!(outcomes[2].dispatched[0].driver_id, zhang);
+    assert!(engine.get_order(&food).unwrap().deliver_by.is_some());
+
+    // 货运订单先进入等待队列，面包车上线后由这条记录派出
+    assert!(outcomes[3].error.is_some());
+    assert!(outcomes[3].dispatched.is_empty());
+    assert_eq!(outcomes[4].dispatched.len(), 1);
+    assert_eq!(outcomes[4].dispatched[0].order_id, cargo);
+
+    // 录制中没有取货记录，未取货的订单不能直接释放为已送达
+    assert!(
+        outcomes[5]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("not been picked up")
+    );
+    assert_eq!(
+        engine.get_order(&food).unwrap().status,
+        OrderStatus::Dispatched
+    );
+    assert!(outcomes[6].error.as_deref().unwrap().contains("not-a-uuid"));
+
+    let lines: Vec<serde_json::Value> = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 7);
+    assert_eq!(lines[2]["type"], "dispatch");
+    assert_eq!(lines[2]["dispatched"][0]["driver_name"], "张师傅");
+}
+
+#[test]
+fn test_replay_reports_invalid_record_line() {
+    let content = "{\"timestamp\": 1, \"type\": \"add_driver\", \"name\": \"a\", \"vehicle_type\": \"Car\", \"latitude\": 39.9, \"longitude\": 116.4}\n\n{\"timestamp\": 2, \"type\": \"teleport\"}\n";
+    match Replay::from_jsonl_str(content) {
+        Err(ReplayError::Parse { line, .. }) => assert_eq!(line, 3),
+        other => panic!("unexpected result: {:?}", other.map(|r| r.len())),
+    }
+
+    // 没有录制 ID 的配送员使用新生成的 ID
+    let replay = Replay::from_jsonl_str(content.lines().next().unwrap()).unwrap();
+    let engine = DispatchEngine::new(Arc::new(NearestFirstStrategy::new()));
+    let outcomes = replay.run(&engine);
+    assert!(outcomes[0].error.is_none());
+    assert_eq!(engine.get_all_drivers()[0].vehicle_type, VehicleType::Car);
+}
+
+#[test]
+fn test_fixed_clock_and_seeded_ids_make_dispatch_reproducible() {
+    let run = || {
+        let clock = Arc::new(FixedClock::new(1_700_000_000));
+        // 策略使用引擎绑定的时钟
+        let engine = DispatchEngine::new(Arc::new(WeightedScoreStrategy::default()))
+            .with_clock(clock.clone())
+            .with_id_generator(Arc::new(SeededIdGenerator::new(7)))
+            .with_rules(vec![Box::new(CapacityRule), Box::new(DeadlineRule::new())]);
+        for name in ["张师傅", "李师傅"] {
+            engine.add_driver(engine.new_driver(
+                name.to_string(),
+                VehicleType::ElectricBike,
+                Location::new(39.9042, 116.4074),
+            ));
+        }
+        clock.advance(300);
+        let order = engine.new_order(
+            OrderType::Food,
+            Location::new(39.9050, 116.4080),
+            Location::new(39.9150, 116.4180),
+        );
+        assert_eq!(order.created_at, 1_700_000_300);
+        // 截止时间按引擎时钟判断，与真实时间无关
+        let mut order = order.with_deadline(1_700_003_900);
+        let result = engine.dispatch(&mut order).unwrap();
+        serde_json::to_string(&(result, engine.snapshot())).unwrap()
+    };
+    assert_eq!(run(), run());
+
+    // 运行时切换的策略同样按引擎时钟计算空闲时长
+    let clock = Arc::new(FixedClock::new(1_700_000_000));
+    let engine =
+        DispatchEngine::new(Arc::new(NearestFirstStrategy::new())).with_clock(clock.clone());
+    engine.add_driver(engine.new_driver(
+        "张师傅".to_string(),
+        VehicleType::ElectricBike,
+        Location::new(39.9042, 116.4074),
+    ));
+    clock.advance(300);
+    engine.set_strategy(StrategyConfig::from_name("weighted_score").unwrap().build());
+    let order = engine.new_order(
+        OrderType::Food,
+        Location::new(39.9050, 116.4080),
+        Location::new(39.9150, 116.4180),
+    );
+    let score = engine.dispatch_explain(&order).ranked[0]
+        .score
+        .clone()
+        .unwrap();
+    assert!(score.idle_time > 0.0 && score.idle_time < 1.0);
+
+    let ids = SeededIdGenerator::new(7);
+    let (a, b) = (ids.next_id(), ids.next_id());
+    assert_ne!(a, b);
+    assert_eq!(a.get_version_num(), 4);
+    assert_eq!(SeededIdGenerator::new(7).next_id(), a);
+    assert_ne!(SeededIdGenerator::new(8).next_id(), a);
+
+    let clock = FixedClock::new(100);
+    clock.advance(20);
+    assert_eq!(clock.now(), 120);
+    clock.set(5);
+    assert_eq!(clock.now(), 5);
+    assert!(SystemClock.now() > 1_700_000_000);
+}
+
+#[test]
+fn test_replay_with_fixed_clock_is_byte_identical() {
+    let run = || {
+        let clock = Arc::new(FixedClock::default());
+        let engine = DispatchEngine::new(Arc::new(NearestFirstStrategy::new()))
+            .with_clock(clock.clone())
+            .with_id_generator(Arc::new(SeededIdGenerator::new(1)))
+            .with_rules(vec![
+                Box::new(CapacityRule),
+                Box::new(VehicleTypeRule),
+                Box::new(DeadlineRule::new()),
+            ]);
+        // 去掉录制的订单 ID，由引擎按种子生成
+        let content = REPLAY_JSONL.replace(
+            r#""order_id": "33333333-3333-4333-8333-333333333333", "#,
+            "",
+        );
+        let replay = Replay::from_jsonl_str(&content)
+            .unwrap()
+            .with_clock(clock.clone());
+        let mut output = Vec::new();
+        let outcomes = replay.run_to_writer(&engine, &mut output).unwrap();
+        assert_eq!(clock.now(), 1_700_000_400);
+        (outcomes, String::from_utf8(output).unwrap())
+    };
+    let (outcomes, first) = run();
+    let (_, second) = run();
+    assert_eq!(first, second);
+
+    // 截止时间从录制时间算起，事件时间戳取自记录
+    let food = uuid::Uuid::parse_str("22222222-2222-4222-8222-222222222222").unwrap();
+    assert_eq!(outcomes[2].order_id, Some(food));
+    assert_eq!(outcomes[2].dispatched[0].driver_name, "张师傅");
+    assert_eq!(
+        outcomes[4].dispatched[0].order_id,
+        outcomes[3].order_id.unwrap()
+    );
+    assert_eq!(outcomes[4].dispatched[0].timestamp, 1_700_000_200);
+}
+
+fn run_cli(args: &[&str], stdin: &str) -> std::process::Output {
+    use std::io::Write;
+    let mut child = std::process::Command::new(env!("CARGO_BIN_EXE_dispatcher"))
+        .args(args)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_cli_dispatch_prints_json_and_exits_non_zero_on_failure() {
+    let dir = temp_data_dir();
+    std::fs::create_dir_all(&dir).unwrap();
+    let drivers = dir.join("drivers.json");
+    std::fs::write(
+        &drivers,
+        r#"[{"name": "张师傅", "vehicle_type": "电动车", "latitude": 39.9042, "longitude": 116.4074}]"#,
+    )
+    .unwrap();
+    let drivers = drivers.to_str().unwrap();
+
+    let output = run_cli(
+        &[
+            "dispatch",
+            "--drivers",
+            drivers,
+            "--pickup",
+            "39.9050,116.4080",
+            "--delivery",
+            "39.9150,116.4180",
+        ],
+        "",
+    );
+    assert!(output.status.success());
+    let result: DispatchResult = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(result.driver_name, "张师傅");
+
+    // 电动车不能接货运订单，派单失败时错误以 JSON 输出且退出码为 2
+    let request = r#"{"order_type": "货运订单", "pickup_lat": 39.905, "pickup_lng": 116.408, "delivery_lat": 39.915, "delivery_lng": 116.418}"#;
+    let output = run_cli(
+        &["dispatch", "--request", "-", "--drivers", drivers],
+        request,
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert!(output.stdout.is_empty());
+    let error: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(error["kind"], "NoEligibleDriver");
+
+    let output = run_cli(&["dispatch", "--pickup", "north"], "");
+    assert_eq!(output.status.code(), Some(1));
+
+    // 无法识别的订单类型、车型和不合法的班次都是请求错误，退出码为 1
+    let output = run_cli(
+        &[
+            "dispatch",
+            "--drivers",
+            drivers,
+            "--order-type",
+            "外卖单",
+            "--pickup",
+            "39.9050,116.4080",
+            "--delivery",
+            "39.9150,116.4180",
+        ],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    let error: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert!(error["error"].as_str().unwrap().contains("外卖单"));
+    for driver in [
+        r#"[{"name": "李师傅", "vehicle_type": "滑板车", "latitude": 39.9042, "longitude": 116.4074}]"#,
+        r#"[{"name": "李师傅", "vehicle_type": "汽车", "latitude": 39.9042, "longitude": 116.4074, "shift": {"start": 100, "end": 100}}]"#,
+    ] {
+        let path = dir.join("invalid_drivers.json");
+        std::fs::write(&path, driver).unwrap();
+        let output = run_cli(
+            &[
+                "dispatch",
+                "--drivers",
+                path.to_str().unwrap(),
+                "--pickup",
+                "39.9050,116.4080",
+                "--delivery",
+                "39.9150,116.4180",
+            ],
+            "",
+        );
+        assert_eq!(output.status.code(), Some(1), "{}", driver);
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_cli_validate_config_and_bench() {
+    let output = run_cli(&["validate-config", "config/dispatcher.toml"], "");
+    assert!(output.status.success());
+    let checked: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(checked["valid"], true);
+
+    let dir = temp_data_dir();
+    std::fs::create_dir_all(&dir).unwrap();
+    let invalid = dir.join("invalid.toml");
+    std::fs::write(&invalid, "[strategy]\nname = \"teleport\"\n").unwrap();
+    let output = run_cli(&["validate-config", invalid.to_str().unwrap()], "");
+    assert_eq!(output.status.code(), Some(1));
+    let checked: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(checked["valid"], false);
+
+    let benchmark = dir.join("benchmark.toml");
+    std::fs::write(&benchmark, BENCHMARK_TOML).unwrap();
+    let output = run_cli(
+        &["bench", benchmark.to_str().unwrap(), "--format", "csv"],
+        "",
+    );
+    assert!(output.status.success());
+    let csv = String::from_utf8(output.stdout).unwrap();
+    let expected = BenchmarkConfig::from_toml_str(BENCHMARK_TOML)
+        .unwrap()
+        .build()
+        .unwrap()
+        .run()
+        .unwrap()
+        .to_csv();
+    assert_eq!(csv, expected);
+
+    let output = run_cli(&["frobnicate"], "");
+    assert_eq!(output.status.code(), Some(1));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_cli_usage_demo_and_replay_exit_codes() {
+    // 没有子命令时打印用法并以 1 退出，交互菜单只在 demo 下启动，输入结束即退出
+    let output = run_cli(&[], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("usage: dispatcher"));
+    let output = run_cli(&["demo"], "");
+    assert!(output.status.success());
+
+    // 未知选项不会被当作输入文件
+    for args in [
+        ["bench", "--formt"],
+        ["simulate", "--varaint"],
+        ["replay", "--out"],
+    ] {
+        let output = run_cli(&args, "");
+        assert_eq!(output.status.code(), Some(1));
+        let error: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+        assert!(error["error"].as_str().unwrap().contains("unknown option"));
+    }
+
+    // 有记录回放失败时退出码为 2
+    let dir = temp_data_dir();
+    std::fs::create_dir_all(&dir).unwrap();
+    let records = dir.join("records.jsonl");
+    std::fs::write(&records, REPLAY_JSONL).unwrap();
+    let output = run_cli(&["replay", records.to_str().unwrap()], "");
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(String::from_utf8(output.stdout).unwrap().lines().count(), 7);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
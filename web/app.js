@@ -2,52 +2,7 @@ const { useState, useEffect } = React;
 
 const DispatcherDemo = () => {
   const [orders, setOrders] = useState([]);
-  const [drivers, setDrivers] = useState([
-    { 
-      id: 1, 
-      name: '张师傅', 
-      status: 'idle', 
-      capacity: 3, 
-      currentLoad: 0, 
-      vehicleType: '电动车',
-      position: { lat: 39.9042, lng: 116.4074 },
-      rating: 4.8,
-      completedOrders: 156
-    },
-    { 
-      id: 2, 
-      name: '李师傅', 
-      status: 'idle', 
-      capacity: 2, 
-      currentLoad: 0, 
-      vehicleType: '摩托车',
-      position: { lat: 39.9100, lng: 116.4100 },
-      rating: 4.9,
-      completedOrders: 203
-    },
-    { 
-      id: 3, 
-      name: '王师傅', 
-      status: 'idle', 
-      capacity: 4, 
-      currentLoad: 0, 
-      vehicleType: '汽车',
-      position: { lat: 39.9000, lng: 116.4000 },
-      rating: 4.7,
-      completedOrders: 189
-    },
-    { 
-      id: 4, 
-      name: '赵师傅', 
-      status: 'idle', 
-      capacity: 3, 
-      currentLoad: 0, 
-      vehicleType: '电动车',
-      position: { lat: 39.9080, lng: 116.4120 },
-      rating: 4.6,
-      completedOrders: 145
-    }
-  ]);
+  const [drivers, setDrivers] = useState([]);
   const [isRunning, setIsRunning] = useState(false);
   const [strategy, setStrategy] = useState('nearest_first');
   const [stats, setStats] = useState({
@@ -65,183 +20,152 @@ const DispatcherDemo = () => {
     capacity_match: '容量匹配'
   };
 
-  const orderTypes = [
-    { type: '外卖订单', icon: '🍔', distance: () => Math.random() * 5 + 0.5 },
-    { type: '快递订单', icon: '📦', distance: () => Math.random() * 10 + 1 },
-    { type: '生鲜订单', icon: '🥬', distance: () => Math.random() * 3 + 0.5 },
-    { type: '打车订单', icon: '🚗', distance: () => Math.random() * 15 + 2 }
-  ];
-
-  const generateOrder = () => {
-    const orderType = orderTypes[Math.floor(Math.random() * orderTypes.length)];
-    const distance = parseFloat(orderType.distance().toFixed(2));
-    const price = (distance * 5 + Math.random() * 20).toFixed(2);
-    
-    return {
-      id: Date.now() + Math.random(),
-      type: orderType.type,
-      icon: orderType.icon,
-      status: 'pending',
-      distance: distance,
-      price: parseFloat(price),
-      pickupLocation: { 
-        lat: 39.9042 + (Math.random() - 0.5) * 0.02, 
-        lng: 116.4074 + (Math.random() - 0.5) * 0.02 
-      },
-      deliveryLocation: { 
-        lat: 39.9042 + (Math.random() - 0.5) * 0.02, 
-        lng: 116.4074 + (Math.random() - 0.5) * 0.02 
-      },
-      createTime: new Date().toLocaleTimeString(),
-      estimatedTime: Math.ceil(distance * 3 + Math.random() * 10),
-      assignedTo: null
-    };
+  // 引擎事件中的策略名与 /api/strategy 使用的配置名对应关系
+  const strategyKeys = {
+    NearestFirst: 'nearest_first',
+    LoadBalanced: 'load_balanced',
+    RatingPriority: 'rating_priority',
+    CapacityMatch: 'capacity_match'
   };
 
-  const calculateDistance = (pos1, pos2) => {
-    const R = 6371;
-    const dLat = (pos2.lat - pos1.lat) * Math.PI / 180;
-    const dLng = (pos2.lng - pos1.lng) * Math.PI / 180;
-    const a = Math.sin(dLat/2) * Math.sin(dLat/2) +
-              Math.cos(pos1.lat * Math.PI / 180) * Math.cos(pos2.lat * Math.PI / 180) *
-              Math.sin(dLng/2) * Math.sin(dLng/2);
-    const c = 2 * Math.atan2(Math.sqrt(a), Math.sqrt(1-a));
-    return R * c;
+  const orderTypes = {
+    Food: { type: '外卖订单', icon: '🍔' },
+    Express: { type: '快递订单', icon: '📦' },
+    FreshFood: { type: '生鲜订单', icon: '🥬' },
+    RideHailing: { type: '打车订单', icon: '🚗' },
+    Cargo: { type: '货运订单', icon: '🚚' }
   };
 
-  const dispatchOrder = (order) => {
-    let selectedDriver = null;
+  const toDriverState = (info) => ({
+    id: info.id,
+    name: info.name,
+    status: info.current_load > 0 ? 'busy' : 'idle',
+    capacity: info.capacity,
+    currentLoad: info.current_load,
+    vehicleType: info.vehicle_type,
+    position: { lat: info.current_location.latitude, lng: info.current_location.longitude },
+    rating: info.rating,
+    completedOrders: info.total_orders
+  });
+
+  const loadDrivers = () =>
+    api.getDrivers().then(list => setDrivers(list.map(toDriverState)));
 
-    const availableDrivers = drivers.filter(d => d.currentLoad < d.capacity);
-    
-    if (availableDrivers.length === 0) return order;
+  const updateDriver = (driverId, update) =>
+    setDrivers(prev => prev.map(d => (d.id === driverId ? { ...d, ...update(d) } : d)));
 
-    switch(strategy) {
-      case 'nearest_first':
-        selectedDriver = availableDrivers.reduce((nearest, driver) => {
-          const driverDist = calculateDistance(driver.position, order.pickupLocation);
-          const nearestDist = calculateDistance(nearest.position, order.pickupLocation);
-          return driverDist < nearestDist ? driver : nearest;
+  const upsertOrder = (orderId, update) =>
+    setOrders(prev => {
+      const existing = prev.find(o => o.id === orderId);
+      if (existing) {
+        return prev.map(o => (o.id === orderId ? { ...o, ...update(o) } : o));
+      }
+      const created = {
+        id: orderId,
+        type: '订单',
+        icon: '📦',
+        status: 'pending',
+        distance: null,
+        price: null,
+        createTime: new Date().toLocaleTimeString(),
+        estimatedTime: null,
+        assignedTo: null
+      };
+      return [...prev, { ...created, ...update(created) }].slice(-12);
+    });
+
+  // 根据引擎推送的事件刷新配送员和订单，不再轮询
+  const applyEvent = (event) => {
+    switch (event.type) {
+      case 'OrderSubmitted':
+        upsertOrder(event.order_id, () => orderTypes[event.order_type] || {});
+        setStats(s => ({ ...s, totalOrders: s.totalOrders + 1, pending: s.pending + 1 }));
+        break;
+      case 'OrderDispatched': {
+        const result = event.result;
+        upsertOrder(result.order_id, () => ({
+          status: 'dispatched',
+          assignedTo: result.driver_name,
+          distance: result.distance_to_pickup_km.toFixed(2),
+          estimatedTime: result.estimated_arrival_minutes
+        }));
+        updateDriver(result.driver_id, d => ({
+          currentLoad: d.currentLoad + 1,
+          status: 'busy',
+          completedOrders: d.completedOrders + 1
+        }));
+        setStats(s => ({
+          ...s,
+          dispatched: s.dispatched + 1,
+          pending: Math.max(0, s.pending - 1),
+          avgDistance: ((s.avgDistance * s.dispatched + result.distance_to_pickup_km) / (s.dispatched + 1)).toFixed(2)
+        }));
+        break;
+      }
+      case 'DispatchFailed':
+        upsertOrder(event.order_id, () => ({ status: event.queued ? 'pending' : 'failed' }));
+        break;
+      case 'OrderStatusChanged':
+        upsertOrder(event.order_id, () => ({ status: event.to.toLowerCase() }));
+        break;
+      case 'OrderReleased':
+        updateDriver(event.driver_id, d => {
+          const currentLoad = Math.max(0, d.currentLoad - 1);
+          return { currentLoad, status: currentLoad === 0 ? 'idle' : 'busy' };
         });
         break;
-
-      case 'load_balanced':
-        selectedDriver = availableDrivers.sort((a, b) => 
-          (a.currentLoad / a.capacity) - (b.currentLoad / b.capacity)
-        )[0];
+      case 'DriverMoved':
+        updateDriver(event.driver_id, () => ({
+          position: { lat: event.location.latitude, lng: event.location.longitude }
+        }));
         break;
-
-      case 'rating_priority':
-        selectedDriver = availableDrivers.sort((a, b) => {
-          const ratingDiff = b.rating - a.rating;
-          if (Math.abs(ratingDiff) > 0.1) return ratingDiff;
-          return (a.currentLoad / a.capacity) - (b.currentLoad / b.capacity);
-        })[0];
+      case 'DriverAdded':
+        loadDrivers();
         break;
-
-      case 'capacity_match':
-        const needsLargeCapacity = order.type === '快递订单' || order.type === '生鲜订单';
-        const filtered = needsLargeCapacity 
-          ? availableDrivers.filter(d => d.vehicleType === '汽车' || d.capacity >= 3)
-          : availableDrivers;
-        
-        selectedDriver = (filtered.length > 0 ? filtered : availableDrivers).sort((a, b) => {
-          const distA = calculateDistance(a.position, order.pickupLocation);
-          const distB = calculateDistance(b.position, order.pickupLocation);
-          return distA - distB;
-        })[0];
+      case 'DriverRemoved':
+        setDrivers(prev => prev.filter(d => d.id !== event.driver_id));
+        break;
+      case 'DriverShiftEnded':
+        updateDriver(event.driver_id, () => ({ status: 'offline' }));
+        break;
+      case 'StrategyChanged':
+        if (strategyKeys[event.strategy]) setStrategy(strategyKeys[event.strategy]);
         break;
     }
-
-    if (selectedDriver) {
-      setDrivers(prev => prev.map(d => 
-        d.id === selectedDriver.id 
-          ? { 
-              ...d, 
-              currentLoad: d.currentLoad + 1, 
-              status: 'busy',
-              completedOrders: d.completedOrders + 1
-            }
-          : d
-      ));
-      return { 
-        ...order, 
-        status: 'dispatched', 
-        assignedTo: selectedDriver.name,
-        driverId: selectedDriver.id 
-      };
-    }
-    return order;
   };
 
   useEffect(() => {
     if (!isRunning) return;
 
-    const interval = setInterval(() => {
-      const newOrder = generateOrder();
-      setOrders(prev => {
-        const updated = [...prev, newOrder];
-        return updated.slice(-12);
-      });
-
-      setTimeout(() => {
-        setOrders(prev => {
-          const pendingOrders = prev.filter(o => o.status === 'pending');
-          if (pendingOrders.length > 0) {
-            const orderToDispatch = pendingOrders[0];
-            const dispatched = dispatchOrder(orderToDispatch);
-            
-            setStats(s => ({
-              ...s,
-              totalOrders: s.totalOrders + 1,
-              dispatched: dispatched.status === 'dispatched' ? s.dispatched + 1 : s.dispatched,
-              pending: prev.filter(o => o.status === 'pending').length,
-              avgDistance: ((s.avgDistance * s.totalOrders + orderToDispatch.distance) / (s.totalOrders + 1)).toFixed(2)
-            }));
-
-            return prev.map(o => o.id === orderToDispatch.id ? dispatched : o);
-          }
-          return prev;
-        });
-      }, 500);
-
-      if (Math.random() > 0.6) {
-        setDrivers(prev => prev.map(d => {
-          if (d.currentLoad > 0 && Math.random() > 0.4) {
-            return {
-              ...d,
-              currentLoad: Math.max(0, d.currentLoad - 1),
-              status: d.currentLoad - 1 === 0 ? 'idle' : 'busy',
-              position: {
-                lat: d.position.lat + (Math.random() - 0.5) * 0.005,
-                lng: d.position.lng + (Math.random() - 0.5) * 0.005
-              }
-            };
-          }
-          return d;
-        }));
-      }
-    }, 2500);
+    loadDrivers();
+    return api.subscribeEvents(applyEvent);
+  }, [isRunning]);
 
-    return () => clearInterval(interval);
-  }, [isRunning, strategy, drivers]);
+  const changeStrategy = (key) => {
+    api.changeStrategy(key).then(result => {
+      if (result.success) setStrategy(key);
+    });
+  };
 
   const reset = () => {
     setOrders([]);
-    setDrivers(prev => prev.map(d => ({ 
-      ...d, 
-      currentLoad: 0, 
-      status: 'idle',
-      position: { 
-        lat: 39.9042 + (Math.random() - 0.5) * 0.02, 
-        lng: 116.4074 + (Math.random() - 0.5) * 0.02 
-      }
-    })));
     setStats({ totalOrders: 0, dispatched: 0, pending: 0, avgDistance: 0, avgDispatchTime: 0 });
+    loadDrivers();
+  };
+
+  const orderStatusLabels = {
+    pending: '待派单',
+    dispatched: '已派单',
+    pickedup: '已取货',
+    intransit: '配送中',
+    delivered: '已送达',
+    cancelled: '已取消',
+    failed: '派单失败'
   };
 
   const getStatusColor = (status) => {
-    return status === 'dispatched' ? 'bg-green-100 text-green-800' : 'bg-blue-100 text-blue-800';
+    if (status === 'failed' || status === 'cancelled') return 'bg-red-100 text-red-800';
+    return status === 'pending' ? 'bg-blue-100 text-blue-800' : 'bg-green-100 text-green-800';
   };
 
   const getVehicleEmoji = (type) => {
@@ -319,7 +243,7 @@ const DispatcherDemo = () => {
               {Object.entries(strategies).map(([key, label]) => (
                 <button
                   key={key}
-                  onClick={() => setStrategy(key)}
+                  onClick={() => changeStrategy(key)}
                   className={`px-4 py-3 rounded-lg font-medium transition-all ${
                     strategy === key
                       ? 'bg-orange-600 text-white shadow-lg'
@@ -343,7 +267,8 @@ const DispatcherDemo = () => {
                     <div className="flex items-center justify-between mb-3">
                       <div className="flex items-center gap-3">
                         <div className={`w-3 h-3 rounded-full ${
-                          driver.status === 'busy' ? 'bg-red-500 pulse' : 'bg-green-500'
+                          driver.status === 'busy' ? 'bg-red-500 pulse'
+                            : driver.status === 'offline' ? 'bg-gray-400' : 'bg-green-500'
                         }`} />
                         <span className="font-semibold text-gray-800">{driver.name}</span>
                         <span className="text-2xl">{getVehicleEmoji(driver.vehicleType)}</span>
@@ -380,7 +305,7 @@ const DispatcherDemo = () => {
               </h2>
               <div className="space-y-2 max-h-96 overflow-y-auto">
                 {orders.length === 0 ? (
-                  <p className="text-gray-500 text-center py-8">暂无订单，点击"开始"按钮生成订单</p>
+                  <p className="text-gray-500 text-center py-8">暂无订单，点击"开始"按钮接收实时事件</p>
                 ) : (
                   orders.slice().reverse().map(order => (
                     <div key={order.id} className="bg-white rounded-lg p-4 shadow-sm card-hover fade-in">
@@ -390,15 +315,15 @@ const DispatcherDemo = () => {
                           <span className="font-semibold text-gray-800">{order.type}</span>
                         </div>
                         <span className={`text-xs px-2 py-1 rounded-full ${getStatusColor(order.status)}`}>
-                          {order.status === 'dispatched' ? '已派单' : '待派单'}
+                          {orderStatusLabels[order.status] || order.status}
                         </span>
                       </div>
                       <div className="grid grid-cols-3 gap-2 text-xs text-gray-600 mb-2">
                         <div className="flex items-center gap-1">
-                          📍 <span>{order.distance} km</span>
+                          📍 <span>{order.distance ?? '-'} km</span>
                         </div>
-                        <div>💰 ¥{order.price}</div>
-                        <div>⏱️ {order.estimatedTime} 分钟</div>
+                        <div>💰 ¥{order.price ?? '-'}</div>
+                        <div>⏱️ {order.estimatedTime ?? '-'} 分钟</div>
                       </div>
                       <div className="flex items-center justify-between text-sm">
                         <span className="text-gray-500">{order.createTime}</span>
@@ -477,8 +402,8 @@ const api = {
     },
 
     // 释放订单
-    releaseOrder: async (driverId) => {
-        const response = await fetch(`${API_BASE_URL}/api/drivers/${driverId}/release`, {
+    releaseOrder: async (driverId, orderId) => {
+        const response = await fetch(`${API_BASE_URL}/api/drivers/${driverId}/release/${orderId}`, {
             method: 'POST'
         });
         return response.json();
@@ -498,6 +423,30 @@ const api = {
     healthCheck: async () => {
         const response = await fetch(`${API_BASE_URL}/health`);
         return response.json();
+    },
+
+    // 订阅引擎实时事件（配送员位置、派单结果、订单状态），优先使用 WebSocket，失败时回退到 SSE
+    // 返回取消订阅函数
+    subscribeEvents: (onEvent) => {
+        let source = null;
+        const fallbackToSse = () => {
+            source = new EventSource(`${API_BASE_URL}/api/events`);
+            source.addEventListener('message', (e) => onEvent(JSON.parse(e.data)));
+            ['OrderSubmitted', 'OrderDispatched', 'DispatchFailed', 'DriverAdded', 'DriverRemoved',
+             'DriverMoved', 'OrderStatusChanged', 'OrderReleased', 'StrategyChanged']
+                .forEach(type => source.addEventListener(type, (e) => onEvent(JSON.parse(e.data))));
+        };
+
+        let opened = false;
+        const ws = new WebSocket(`${API_BASE_URL.replace(/^http/, 'ws')}/api/events/ws`);
+        ws.onopen = () => { opened = true; };
+        ws.onmessage = (e) => onEvent(JSON.parse(e.data));
+        ws.onerror = () => { if (!opened) fallbackToSse(); };
+
+        return () => {
+            ws.close();
+            if (source) source.close();
+        };
     }
 };
 